@@ -17,7 +17,7 @@ macro_rules! dt {
 fn main() {
     let now: DateTime<FixedOffset> = timeseries::chrono::Utc::now().into();
     let headers = vec![s!("h1"), s!("h2")];
-    let times = vec![dt!("2019-01-01T12:00:00Z").unwrap(), now.clone()];
+    let times = vec![dt!("2019-01-01T12:00:00Z").unwrap(), now];
     let d = vec2![["Hark", "Bark"], ["Hans", "kaas"],];
 
     let t1 = Table::new(headers.clone(), times, d).unwrap();
@@ -0,0 +1,68 @@
+use timeseries::postgres;
+use timeseries::tokio;
+use timeseries::{BtreeMapTrait, PostgresConfig, RedisConfig, Table};
+
+fn new_table_data() -> Table<u16, i32> {
+    let headers = vec![
+        String::from("p10"),
+        String::from("data"),
+        String::from("twentyfive"),
+        String::from("squares"),
+    ];
+
+    let mut indexes = Vec::new();
+    let mut d = Vec::new();
+    for i in 1..2500 {
+        indexes.push(i as u16);
+        let row: Vec<i32> = vec![i * 10, i * 7 + 2, 25, i.pow(2)];
+        d.push(row);
+    }
+
+    Table::new(headers, indexes, d).unwrap()
+}
+
+async fn run(postgres_config: &PostgresConfig<postgres::NoTls>, redis_config: &RedisConfig) {
+    let mut table = new_table_data();
+
+    // the async methods share the layout of the sync ones
+    table.init_postgresql_async(postgres_config).await.unwrap();
+    table.save_postgresql_async(postgres_config).await.unwrap();
+    table.save_redis_async(redis_config).await.unwrap();
+
+    table.insert(3000, vec![30000, 123456, 25, 900000]);
+
+    // only update the keys that are different
+    table
+        .update_postgresql_async(postgres_config)
+        .await
+        .unwrap();
+    table.update_redis_async(redis_config).await.unwrap();
+
+    let t1: Table<u16, i32> = Table::from_postgresql_async(postgres_config).await.unwrap();
+    let t2: Table<u16, i32> = Table::load_range_redis_async(redis_config, 2000..)
+        .await
+        .unwrap();
+
+    if table != t1 {
+        println!("tables are not the same");
+    };
+    println!("loaded {} rows from redis", t2.keys().count());
+}
+
+fn main() {
+    let postgres_ip = "postgres://postgres@192.168.99.100:5432/postgres";
+    let postgres_config = PostgresConfig::new(postgres_ip, postgres::NoTls).unwrap();
+    let redis_config = RedisConfig::new("redis://192.168.99.100/3").unwrap();
+
+    // the connections need a runtime with io enabled
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(run(&postgres_config, &redis_config));
+
+    // the sync methods start their own runtime, so they can't run inside `block_on`
+    let table: Table<u16, i32> = new_table_data();
+    table.uninit_postgresql(&postgres_config).unwrap();
+    table.delete_redis(&redis_config).unwrap();
+}
@@ -1,8 +1,8 @@
 use timeseries::chrono::{NaiveDateTime, ParseError};
 use timeseries::csv;
-use timeseries::read_csv_to_datetable;
+use timeseries::{read_csv_to_datetable, write_csv};
 
-const TSV_DATA: &'static str = r#"d87datum	d87zweig	d87syst	d87av_anz	d87vl_anz	d50bezeich	d12sylang
+const TSV_DATA: &str = r#"d87datum	d87zweig	d87syst	d87av_anz	d87vl_anz	d50bezeich	d12sylang
 2010-02-26 00:00:00.000	0	2428	1	0	Centrale	Adult
 2010-03-04 00:00:00.000	0	2401	1	0	Centrale	Adult
 2010-03-05 00:00:00.000	0	2437	1	0	Centrale	Adult
@@ -27,7 +27,7 @@ pub struct YourError(String);
 
 impl YourError {
     pub fn new<S: Into<String>>(s: S) -> YourError {
-        YourError { 0: s.into() }
+        YourError(s.into())
     }
 }
 
@@ -49,10 +49,21 @@ fn main() {
         .has_headers(true)
         .from_reader(TSV_DATA.as_bytes());
     let t = read_csv_to_datetable(&mut rdr, |x| -> Result<_, ParseError> {
-        Ok(NaiveDateTime::parse_from_str(x, "%F %H:%M:%S%.3f")?)
+        NaiveDateTime::parse_from_str(x, "%F %H:%M:%S%.3f")
     })
     .unwrap();
 
     assert_eq!(y, t);
     println!("{:#?}", t);
+
+    let mut wtr = csv::WriterBuilder::default()
+        .delimiter(b'\t')
+        .from_writer(std::io::stdout());
+    write_csv(
+        &mut wtr,
+        &t,
+        |x| x.format("%F %H:%M:%S%.3f").to_string(),
+        |x| x.to_owned(),
+    )
+    .unwrap();
 }
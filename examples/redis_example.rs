@@ -1,4 +1,3 @@
-use timeseries;
 use timeseries::{BtreeMapTrait, RedisConfig, Table};
 
 fn new_table_data() -> Table<u16, i32> {
@@ -33,7 +32,8 @@ fn new_table_data() -> Table<u16, i32> {
 fn main() {
     let redis_address = "redis://192.168.99.100/3";
 
-    let config = RedisConfig::new(redis_address).unwrap();
+    // keeps one connection open for all the calls below
+    let config = RedisConfig::new(redis_address).unwrap().reuse_connection();
     let mut table = new_table_data();
 
     // saves the whole table to the database
@@ -1,4 +1,3 @@
-use timeseries;
 use timeseries::{BtreeMapTrait, SqliteConfig, Table};
 
 fn new_table_data() -> Table<u16, i32> {
@@ -42,13 +41,19 @@ fn main() {
     table.save_sqlite(&config).unwrap();
 
     table.insert(3000, vec![30000, 123456, 25, 900000]);
+    table.remove(&1);
 
-    // only update the keys that are different
-    table.update_sqlite(&config).unwrap();
+    // only update the keys that are different and delete the removed ones
+    let changes = table.update_sqlite(&config).unwrap();
+    println!("{:?}", changes);
 
     // get the table back from the sqlite database
     let t1: Table<u16, i32> = Table::from_sqlite(&config).unwrap();
 
+    // only get the rows with an index from 5 up to (but not including) 10
+    let t2: Table<u16, i32> = Table::load_range_sqlite(&config, 5..10).unwrap();
+    println!("{:?}", t2);
+
     // removes all the tables from the database. The inverse of init.
     table.uninit_sqlite(&config).unwrap();
 
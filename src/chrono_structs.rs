@@ -1,10 +1,75 @@
 #![cfg(feature = "chrono")]
 
-use crate::Table;
-use chrono::Datelike;
+use crate::enums::FillMethod;
+#[cfg(feature = "num")]
+use crate::enums::{Aggregation, RollingStatistic};
+use crate::{Table, TableError};
+use chrono::{Datelike, Duration, Timelike};
+use std::collections::BTreeMap;
+use std::ops::Add;
 
 pub type DateTable<U, V> = Table<U, V>;
 
+/// Calendar interval used to bucket the index of a `DateTable`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum ResampleRule {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+impl ResampleRule {
+    /// truncates the key to the start of the interval it falls in
+    pub fn floor<U>(self, key: &U) -> Option<U>
+    where
+        U: Datelike + Timelike + Add<Duration, Output = U>,
+    {
+        let mut key = key.with_nanosecond(0)?;
+        if self >= ResampleRule::Minute {
+            key = key.with_second(0)?;
+        }
+        if self >= ResampleRule::Hour {
+            key = key.with_minute(0)?;
+        }
+        if self >= ResampleRule::Day {
+            key = key.with_hour(0)?;
+        }
+        match self {
+            ResampleRule::Week => {
+                let days = i64::from(key.weekday().num_days_from_monday());
+                Some(key + Duration::days(-days))
+            }
+            ResampleRule::Month => key.with_day(1),
+            _ => Some(key),
+        }
+    }
+
+    /// start of the interval that follows the interval starting at key
+    pub fn next<U>(self, key: &U) -> Option<U>
+    where
+        U: Datelike + Timelike + Add<Duration, Output = U> + Clone,
+    {
+        let key = key.clone();
+        match self {
+            ResampleRule::Second => Some(key + Duration::seconds(1)),
+            ResampleRule::Minute => Some(key + Duration::minutes(1)),
+            ResampleRule::Hour => Some(key + Duration::hours(1)),
+            ResampleRule::Day => Some(key + Duration::days(1)),
+            ResampleRule::Week => Some(key + Duration::weeks(1)),
+            ResampleRule::Month => {
+                if key.month() == 12 {
+                    key.with_day(1)?.with_month(1)?.with_year(key.year() + 1)
+                } else {
+                    key.with_day(1)?.with_month(key.month() + 1)
+                }
+            }
+        }
+    }
+}
+
 impl<U, V> DateTable<U, V>
 where
     U: std::fmt::Debug + Datelike + std::cmp::Ord + Clone,
@@ -12,7 +77,188 @@ where
     // U: Datelike + std::cmp::Ord + Clone,
     // V: Clone,
 {
-    pub fn cheese() {}
+    /// downsamples the table, every row is put in the bucket of the rule
+    /// and every column of a bucket is reduced to a single value with `f`.
+    /// Buckets without rows are left out.
+    pub fn resample_with<F>(&self, rule: ResampleRule, f: F) -> Result<DateTable<U, V>, TableError>
+    where
+        U: Timelike + Add<Duration, Output = U>,
+        F: Fn(&[&V]) -> V,
+    {
+        self.try_resample_with(rule, |values| Ok(f(values)))
+    }
+
+    /// like `resample_with`, stops at the first bucket that `f` can't reduce
+    fn try_resample_with<F>(&self, rule: ResampleRule, f: F) -> Result<DateTable<U, V>, TableError>
+    where
+        U: Timelike + Add<Duration, Output = U>,
+        F: Fn(&[&V]) -> Result<V, TableError>,
+    {
+        let mut buckets: BTreeMap<U, Vec<&Vec<V>>> = BTreeMap::new();
+        for (k, row) in self.data.iter() {
+            let start = rule
+                .floor(k)
+                .ok_or_else(|| TableError::new(format!("can not resample index {:?}", k)))?;
+            buckets.entry(start).or_default().push(row);
+        }
+
+        let mut data = BTreeMap::new();
+        for (start, rows) in buckets {
+            let row = (0..self.headers.len())
+                .map(|i| {
+                    let column: Vec<&V> = rows.iter().map(|r| &r[i]).collect();
+                    f(&column)
+                })
+                .collect::<Result<_, _>>()?;
+            data.insert(start, row);
+        }
+
+        let mut table = Table::new_btreemap(self.headers.clone(), data);
+        table.meta_data = self.meta_data.clone();
+        Ok(table)
+    }
+
+    /// Downsamples the table with one of the standard aggregations.
+    ///
+    /// Fails when the mean or count of a bucket can't be represented in `V`,
+    /// e.g. a count above 255 in a `u8` column.
+    #[cfg(feature = "num")]
+    pub fn resample(
+        &self,
+        rule: ResampleRule,
+        aggregation: Aggregation,
+    ) -> Result<DateTable<U, V>, TableError>
+    where
+        U: Timelike + Add<Duration, Output = U>,
+        V: num_traits::Num + num_traits::NumCast + std::cmp::PartialOrd,
+    {
+        self.try_resample_with(rule, |values| {
+            aggregation.apply(values).ok_or_else(|| {
+                TableError::new(format!(
+                    "{:?} of {} values does not fit the value type",
+                    aggregation,
+                    values.len()
+                ))
+            })
+        })
+    }
+
+    /// upsamples the table, every interval between the first and the last row gets a row.
+    /// Intervals that contain rows get the last row of that interval, empty intervals are filled with `fill`.
+    pub fn upsample(
+        &self,
+        rule: ResampleRule,
+        fill: FillMethod<V>,
+    ) -> Result<DateTable<U, V>, TableError>
+    where
+        U: Timelike + Add<Duration, Output = U>,
+    {
+        let sampled = self.resample_with(rule, |values| values[values.len() - 1].clone())?;
+        let (first, last) = match (sampled.data.keys().next(), sampled.data.keys().last()) {
+            (Some(first), Some(last)) => (first.clone(), last.clone()),
+            _ => return Ok(sampled),
+        };
+
+        let mut data: BTreeMap<U, Vec<V>> = BTreeMap::new();
+        let mut previous: Option<&Vec<V>> = None;
+        let mut key = first;
+        while key <= last {
+            let row = match sampled.data.get(&key) {
+                Some(row) => {
+                    previous = Some(row);
+                    row.clone()
+                }
+                None => match fill {
+                    FillMethod::Forward => previous.cloned().unwrap_or_default(),
+                    FillMethod::Backward => sampled
+                        .data
+                        .range(key.clone()..)
+                        .next()
+                        .map(|(_, row)| row.clone())
+                        .unwrap_or_default(),
+                    FillMethod::Value(ref v) => vec![v.clone(); self.headers.len()],
+                },
+            };
+            let next = rule
+                .next(&key)
+                .ok_or_else(|| TableError::new(format!("can not resample index {:?}", key)))?;
+            data.insert(key, row);
+            key = next;
+        }
+
+        let mut table = Table::new_btreemap(self.headers.clone(), data);
+        table.meta_data = self.meta_data.clone();
+        Ok(table)
+    }
+
+    /// rolling statistic over the rows in the `duration` up to and including the current row,
+    /// cells are None when the statistic can't be represented in `V`
+    #[cfg(feature = "num")]
+    pub fn rolling_duration(
+        &self,
+        duration: Duration,
+        statistic: RollingStatistic,
+    ) -> Result<DateTable<U, Option<V>>, TableError>
+    where
+        U: Add<Duration, Output = U>,
+        V: num_traits::Num + num_traits::NumCast + std::cmp::PartialOrd,
+    {
+        if duration <= Duration::zero() {
+            return Err(TableError::new("duration of the window should be positive"));
+        }
+        Ok(self.rolling_by(statistic, |oldest, current, _| {
+            oldest.clone() + duration <= *current
+        }))
+    }
+
+    /// applies `f` to the values in the `duration` up to and including the current row of every column
+    pub fn rolling_duration_with<F>(
+        &self,
+        duration: Duration,
+        f: F,
+    ) -> Result<DateTable<U, V>, TableError>
+    where
+        U: Add<Duration, Output = U>,
+        F: Fn(&[&V]) -> V,
+    {
+        if duration <= Duration::zero() {
+            return Err(TableError::new("duration of the window should be positive"));
+        }
+        Ok(self.rolling_by_with(f, |oldest, current, _| {
+            oldest.clone() + duration <= *current
+        }))
+    }
+
+    /// joins every row with the row of `other` that has the nearest key at or before it,
+    /// if that key is at most `tolerance` earlier. Otherwise the cells of `other` are None.
+    /// Headers that are in both tables get the suffix `_left` or `_right`.
+    pub fn join_asof(&self, other: &DateTable<U, V>, tolerance: Duration) -> DateTable<U, Option<V>>
+    where
+        U: Add<Duration, Output = U>,
+    {
+        let data: BTreeMap<U, Vec<Option<V>>> = self
+            .data
+            .iter()
+            .map(|(k, row)| {
+                let nearest = other
+                    .data
+                    .range(..=k.clone())
+                    .next_back()
+                    .filter(|(other_key, _)| (*other_key).clone() + tolerance >= *k)
+                    .map(|(_, other_row)| other_row);
+                let mut row = Self::join_row(Some(row), self.headers.len());
+                row.extend(Self::join_row(nearest, other.headers.len()));
+                (k.clone(), row)
+            })
+            .collect();
+
+        let mut table = Table::new_btreemap(
+            Self::join_headers(&self.headers, &other.headers, "_left", "_right"),
+            data,
+        );
+        table.meta_data = self.meta_data.clone();
+        table
+    }
 }
 
 #[cfg(test)]
@@ -108,7 +354,7 @@ mod array_test {
         let now: chrono::DateTime<chrono::FixedOffset> = chrono::Utc::now().into();
         let headers = vec![s!("h1"), s!("h2")];
 
-        let times = vec![dtu!("2019-01-01T12:00:00Z"), now.clone()];
+        let times = vec![dtu!("2019-01-01T12:00:00Z"), now];
         let d = vec2![["Hark", "Bark"], ["Hans", "kaas"]];
 
         let t1 = DateTable::new(headers.clone(), times, d).unwrap();
@@ -258,3 +504,308 @@ mod array_test {
         assert_ne!(t1, t1_copy);
     }
 }
+
+#[cfg(test)]
+mod resample_test {
+    use crate::enums::FillMethod;
+    use crate::{vec2, DateTable, ResampleRule};
+
+    macro_rules! dtu {
+        ($t:expr) => {
+            chrono::DateTime::parse_from_rfc3339($t).unwrap()
+        };
+    }
+
+    macro_rules! s {
+        ($t:expr) => {
+            String::from($t)
+        };
+    }
+
+    fn new_table_hours() -> DateTable<chrono::DateTime<chrono::FixedOffset>, i32> {
+        let headers = vec![s!("a"), s!("b")];
+
+        let times = vec![
+            dtu!("2019-01-01T12:00:00Z"),
+            dtu!("2019-01-01T12:30:00Z"),
+            dtu!("2019-01-01T13:15:00Z"),
+            dtu!("2019-01-01T15:45:00Z"),
+            dtu!("2019-01-01T15:50:00Z"),
+        ];
+        let d = vec2![[1, 10], [2, 20], [3, 30], [4, 40], [5, 50]];
+
+        DateTable::new(headers, times, d).unwrap()
+    }
+
+    #[test]
+    fn rule_floor() {
+        let date = dtu!("2019-01-03T12:34:56.789Z");
+
+        assert_eq!(
+            Some(dtu!("2019-01-03T12:34:56Z")),
+            ResampleRule::Second.floor(&date)
+        );
+        assert_eq!(
+            Some(dtu!("2019-01-03T12:34:00Z")),
+            ResampleRule::Minute.floor(&date)
+        );
+        assert_eq!(
+            Some(dtu!("2019-01-03T12:00:00Z")),
+            ResampleRule::Hour.floor(&date)
+        );
+        assert_eq!(
+            Some(dtu!("2019-01-03T00:00:00Z")),
+            ResampleRule::Day.floor(&date)
+        );
+        assert_eq!(
+            Some(dtu!("2018-12-31T00:00:00Z")),
+            ResampleRule::Week.floor(&date)
+        );
+        assert_eq!(
+            Some(dtu!("2019-01-01T00:00:00Z")),
+            ResampleRule::Month.floor(&date)
+        );
+    }
+
+    #[test]
+    fn rule_next_month() {
+        let date = dtu!("2019-12-01T00:00:00Z");
+        assert_eq!(
+            Some(dtu!("2020-01-01T00:00:00Z")),
+            ResampleRule::Month.next(&date)
+        );
+    }
+
+    #[test]
+    fn resample_with_closure() {
+        let t = new_table_hours();
+        let output = t
+            .resample_with(ResampleRule::Hour, |values| {
+                values.iter().map(|v| **v).sum()
+            })
+            .unwrap();
+
+        let expected = DateTable::new(
+            vec![s!("a"), s!("b")],
+            vec![
+                dtu!("2019-01-01T12:00:00Z"),
+                dtu!("2019-01-01T13:00:00Z"),
+                dtu!("2019-01-01T15:00:00Z"),
+            ],
+            vec2![[3, 30], [3, 30], [9, 90]],
+        )
+        .unwrap();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn resample_aggregations() {
+        use crate::enums::Aggregation;
+
+        let t = new_table_hours();
+        let day = vec![dtu!("2019-01-01T00:00:00Z")];
+
+        let cases = vec![
+            (Aggregation::Sum, [15, 150]),
+            (Aggregation::Mean, [3, 30]),
+            (Aggregation::Min, [1, 10]),
+            (Aggregation::Max, [5, 50]),
+            (Aggregation::First, [1, 10]),
+            (Aggregation::Last, [5, 50]),
+        ];
+        for (aggregation, row) in cases {
+            let expected =
+                DateTable::new(vec![s!("a"), s!("b")], day.clone(), vec![row.to_vec()]).unwrap();
+            assert_eq!(
+                expected,
+                t.resample(ResampleRule::Day, aggregation).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn resample_count_overflow() {
+        use crate::enums::Aggregation;
+
+        let start = dtu!("2019-01-01T00:00:00Z");
+        let times = (0..300)
+            .map(|i| start + chrono::Duration::seconds(i))
+            .collect();
+        let t = DateTable::new(vec![s!("a")], times, vec![vec![200u8]; 300]).unwrap();
+
+        // 300 and the sum of the values do not fit in a u8, the mean does
+        assert!(t.resample(ResampleRule::Day, Aggregation::Count).is_err());
+        assert!(t.resample(ResampleRule::Day, Aggregation::Sum).is_err());
+        let t1 = t.resample(ResampleRule::Day, Aggregation::Mean).unwrap();
+        assert_eq!(Some(&vec![200u8]), t1.data.values().next());
+        let t1 = t
+            .resample(ResampleRule::Minute, Aggregation::Count)
+            .unwrap();
+        assert_eq!(
+            vec![vec![60u8]; 5],
+            t1.data.values().cloned().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn upsample_fill() {
+        let t = new_table_hours();
+        let times = vec![
+            dtu!("2019-01-01T12:00:00Z"),
+            dtu!("2019-01-01T13:00:00Z"),
+            dtu!("2019-01-01T14:00:00Z"),
+            dtu!("2019-01-01T15:00:00Z"),
+        ];
+
+        let forward = DateTable::new(
+            vec![s!("a"), s!("b")],
+            times.clone(),
+            vec2![[2, 20], [3, 30], [3, 30], [5, 50]],
+        )
+        .unwrap();
+        let backward = DateTable::new(
+            vec![s!("a"), s!("b")],
+            times.clone(),
+            vec2![[2, 20], [3, 30], [5, 50], [5, 50]],
+        )
+        .unwrap();
+        let value = DateTable::new(
+            vec![s!("a"), s!("b")],
+            times,
+            vec2![[2, 20], [3, 30], [0, 0], [5, 50]],
+        )
+        .unwrap();
+
+        assert_eq!(
+            forward,
+            t.upsample(ResampleRule::Hour, FillMethod::Forward).unwrap()
+        );
+        assert_eq!(
+            backward,
+            t.upsample(ResampleRule::Hour, FillMethod::Backward)
+                .unwrap()
+        );
+        assert_eq!(
+            value,
+            t.upsample(ResampleRule::Hour, FillMethod::Value(0))
+                .unwrap()
+        );
+    }
+}
+
+#[cfg(test)]
+mod rolling_test {
+    use crate::{vec2, DateTable};
+    use chrono::Duration;
+
+    macro_rules! dtu {
+        ($t:expr) => {
+            chrono::DateTime::parse_from_rfc3339($t).unwrap()
+        };
+    }
+
+    fn new_table_minutes() -> DateTable<chrono::DateTime<chrono::FixedOffset>, i32> {
+        let headers = vec![String::from("a")];
+
+        let times = vec![
+            dtu!("2019-01-01T12:00:00Z"),
+            dtu!("2019-01-01T12:05:00Z"),
+            dtu!("2019-01-01T12:14:00Z"),
+            dtu!("2019-01-01T12:15:00Z"),
+            dtu!("2019-01-01T12:40:00Z"),
+        ];
+        let d = vec2![[1], [2], [3], [4], [5]];
+
+        DateTable::new(headers, times, d).unwrap()
+    }
+
+    #[test]
+    fn rolling_duration_with_sum() {
+        let t = new_table_minutes();
+        let output = t
+            .rolling_duration_with(Duration::minutes(15), |values| {
+                values.iter().map(|v| **v).sum()
+            })
+            .unwrap();
+
+        let sums: Vec<i32> = output.data.values().map(|row| row[0]).collect();
+        assert_eq!(vec![1, 3, 6, 9, 5], sums);
+        assert_eq!(
+            t.data.keys().collect::<Vec<_>>(),
+            output.data.keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn rolling_duration_not_positive() {
+        let t = new_table_minutes();
+        assert!(t
+            .rolling_duration_with(Duration::zero(), |values| *values[0])
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn rolling_duration_max() {
+        use crate::enums::RollingStatistic;
+
+        let t = new_table_minutes();
+        let output = t
+            .rolling_duration(Duration::minutes(15), RollingStatistic::Max)
+            .unwrap();
+
+        let max: Vec<Option<i32>> = output.data.values().map(|row| row[0]).collect();
+        assert_eq!(vec![Some(1), Some(2), Some(3), Some(4), Some(5)], max);
+    }
+}
+
+#[cfg(test)]
+mod join_test {
+    use crate::{vec2, DateTable};
+    use chrono::Duration;
+
+    macro_rules! dtu {
+        ($t:expr) => {
+            chrono::DateTime::parse_from_rfc3339($t).unwrap()
+        };
+    }
+
+    #[test]
+    fn join_asof_tolerance() {
+        let left = DateTable::new(
+            vec![String::from("speed")],
+            vec![
+                dtu!("2019-01-01T12:00:00Z"),
+                dtu!("2019-01-01T12:00:05Z"),
+                dtu!("2019-01-01T12:01:00Z"),
+            ],
+            vec2![[1], [2], [3]],
+        )
+        .unwrap();
+        let right = DateTable::new(
+            vec![String::from("position")],
+            vec![dtu!("2019-01-01T11:59:59Z"), dtu!("2019-01-01T12:00:05Z")],
+            vec2![[10], [20]],
+        )
+        .unwrap();
+
+        let t = left.join_asof(&right, Duration::seconds(10));
+
+        assert_eq!(
+            &[String::from("speed"), String::from("position")],
+            &t.headers[..]
+        );
+        let rows: Vec<&Vec<Option<i32>>> = t.data.values().collect();
+        assert_eq!(
+            vec![
+                &vec![Some(1), Some(10)],
+                &vec![Some(2), Some(20)],
+                &vec![Some(3), None]
+            ],
+            rows
+        );
+    }
+}
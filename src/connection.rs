@@ -0,0 +1,177 @@
+#![cfg(any(
+    feature = "sqlite_db",
+    feature = "postgresql_db",
+    feature = "redis_db",
+    feature = "unqlite_db"
+))]
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Connection a config keeps open between calls, shared by the clones of the config.
+///
+/// Without a kept connection every call opens its own. Calls through a kept
+/// connection take turns, so it can be shared between threads like a pool of one.
+pub(crate) struct ReusedConnection<C> {
+    slot: Option<Arc<Mutex<Option<C>>>>,
+}
+
+impl<C> ReusedConnection<C> {
+    /// opens a connection on first use and keeps it
+    pub(crate) fn lazy() -> ReusedConnection<C> {
+        ReusedConnection {
+            slot: Some(Arc::new(Mutex::new(None))),
+        }
+    }
+
+    /// keeps a connection opened by the caller
+    pub(crate) fn with(connection: C) -> ReusedConnection<C> {
+        ReusedConnection {
+            slot: Some(Arc::new(Mutex::new(Some(connection)))),
+        }
+    }
+
+    /// Hands out the kept connection, or a new one from `connect`.
+    ///
+    /// A kept connection that is not `usable` anymore, e.g. because the server
+    /// closed it, is replaced by a new one.
+    pub(crate) fn get<E>(
+        &self,
+        usable: impl Fn(&C) -> bool,
+        connect: impl FnOnce() -> Result<C, E>,
+    ) -> Result<ConnectionGuard<'_, C>, E> {
+        let slot = match &self.slot {
+            Some(x) => x,
+            None => return Ok(ConnectionGuard::Owned(connect()?)),
+        };
+        let mut connection = slot.lock().unwrap_or_else(|err| {
+            // a panic during a call can leave the connection inside a transaction
+            slot.clear_poison();
+            let mut connection = err.into_inner();
+            *connection = None;
+            connection
+        });
+        if !connection.as_ref().is_some_and(usable) {
+            *connection = Some(connect()?);
+        }
+        Ok(ConnectionGuard::Kept(connection))
+    }
+}
+
+impl<C> Clone for ReusedConnection<C> {
+    fn clone(&self) -> Self {
+        ReusedConnection {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<C> Default for ReusedConnection<C> {
+    fn default() -> Self {
+        ReusedConnection { slot: None }
+    }
+}
+
+// not every connection type implements Debug
+impl<C> std::fmt::Debug for ReusedConnection<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReusedConnection")
+            .field("kept", &self.slot.is_some())
+            .finish()
+    }
+}
+
+/// Connection handed out by [`ReusedConnection::get`].
+pub(crate) enum ConnectionGuard<'a, C> {
+    /// opened for this call only
+    Owned(C),
+    /// the kept connection, locked until the guard is dropped
+    Kept(MutexGuard<'a, Option<C>>),
+}
+
+impl<C> Deref for ConnectionGuard<'_, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        match self {
+            ConnectionGuard::Owned(x) => x,
+            ConnectionGuard::Kept(x) => x.as_ref().expect("connected in get"),
+        }
+    }
+}
+
+impl<C> DerefMut for ConnectionGuard<'_, C> {
+    fn deref_mut(&mut self) -> &mut C {
+        match self {
+            ConnectionGuard::Owned(x) => x,
+            ConnectionGuard::Kept(x) => x.as_mut().expect("connected in get"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod reuse {
+    use super::ReusedConnection;
+
+    #[test]
+    fn connects_once() {
+        let reused = ReusedConnection::lazy();
+        let clone = reused.clone();
+        let mut opened = 0;
+        for config in [&reused, &clone, &reused] {
+            let connection = config
+                .get(
+                    |_| true,
+                    || {
+                        opened += 1;
+                        Ok::<_, ()>(opened)
+                    },
+                )
+                .unwrap();
+            assert_eq!(1, *connection);
+        }
+        assert_eq!(1, opened);
+    }
+
+    #[test]
+    fn not_reused() {
+        let reused = ReusedConnection::default();
+        let mut opened = 0;
+        for _ in 0..3 {
+            reused
+                .get(
+                    |_| true,
+                    || {
+                        opened += 1;
+                        Ok::<_, ()>(opened)
+                    },
+                )
+                .unwrap();
+        }
+        assert_eq!(3, opened);
+    }
+
+    #[test]
+    fn replaces_closed() {
+        // the connection `0` counts as closed
+        let reused = ReusedConnection::with(0);
+        assert_eq!(1, *reused.get(|x| *x > 0, || Ok::<_, ()>(1)).unwrap());
+        assert_eq!(1, *reused.get(|x| *x > 0, || Ok::<_, ()>(2)).unwrap());
+    }
+
+    #[test]
+    fn poisoned() {
+        let reused = ReusedConnection::with(1);
+        let clone = reused.clone();
+        std::thread::spawn(move || {
+            let _connection = clone.get(|_| true, || Ok::<_, ()>(2)).unwrap();
+            panic!("failed during a call");
+        })
+        .join()
+        .unwrap_err();
+
+        // the connection of the failed call is not handed out again
+        assert_eq!(3, *reused.get(|_| true, || Ok::<_, ()>(3)).unwrap());
+        assert_eq!(3, *reused.get(|_| true, || Ok::<_, ()>(4)).unwrap());
+    }
+}
@@ -7,6 +7,15 @@ pub enum DbTableError {
     DbExists,
     DbDoesNotExist,
     DbHeaderDoesNotExist,
+    /// the table has other headers than the stored ones, which are included
+    DbHeadersChanged(Vec<String>),
+    /// a stored row does not have one value per header
+    DbRowLength {
+        expected: usize,
+        found: usize,
+    },
+    /// versioning a key-value table needs a namespace to move the old version to
+    DbNamespaceRequired,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,12 +52,298 @@ impl<V> PartialEq for DbObject<V> {
     }
 }
 
+/// Keys touched by an incremental update, split by what happened to them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbChanges<U> {
+    pub inserted: Vec<U>,
+    pub updated: Vec<U>,
+    pub deleted: Vec<U>,
+}
+
+impl<U> DbChanges<U> {
+    pub fn new() -> DbChanges<U> {
+        DbChanges {
+            inserted: Vec::new(),
+            updated: Vec::new(),
+            deleted: Vec::new(),
+        }
+    }
+
+    /// true when the update did not change any row
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.updated.is_empty() && self.deleted.is_empty()
+    }
+
+    /// total number of changed rows
+    pub fn len(&self) -> usize {
+        self.inserted.len() + self.updated.len() + self.deleted.len()
+    }
+}
+
+impl<U> Default for DbChanges<U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What `update_*` does when the headers of the table differ from the stored headers.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum HeaderChange<V> {
+    /// Fails with `DbTableError::DbHeadersChanged` and leaves the stored table alone.
+    #[default]
+    Reject,
+    /// Stores the table under its new headers.
+    ///
+    /// The unqlite, redis and postgresql `update_*` keep the stored rows that
+    /// are not in the table, they are moved to the new headers and the added
+    /// columns get the fill value. Their `sync_*`, sqlite and memory delete
+    /// those rows instead, so the fill value is not used there.
+    Rewrite(V),
+    /// Keeps the stored table as the first free version, see the `version`
+    /// method of the config, and saves the table as the new current one.
+    Version,
+}
+
+/// Moves the values of a row stored under `from` to the columns of `to`.
+#[cfg(any(
+    feature = "unqlite_db",
+    feature = "postgresql_db",
+    feature = "redis_db"
+))]
+pub(crate) fn rewrite_row<V: Clone>(row: &[V], from: &[String], to: &[String], fill: &V) -> Vec<V> {
+    to.iter()
+        .map(|header| match from.iter().position(|x| x == header) {
+            Some(i) => row[i].clone(),
+            None => fill.clone(),
+        })
+        .collect()
+}
+
+/// Errors when a loaded row does not fit the headers.
+pub(crate) fn check_row_length<V>(headers: &[String], row: &[V]) -> Result<(), DbTableError> {
+    if headers.len() != row.len() {
+        return Err(DbTableError::DbRowLength {
+            expected: headers.len(),
+            found: row.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Encodes an index value into bytes that sort the same way as the value itself.
+///
+/// Bincode uses little-endian integers, so its output can't be compared by a
+/// storage backend. Backends store this encoding next to the bincode key and
+/// use it to answer range queries without loading the whole table.
+pub trait OrderedKey {
+    fn ordered_key(&self) -> Vec<u8>;
+}
+
+macro_rules! ordered_key_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl OrderedKey for $t {
+                fn ordered_key(&self) -> Vec<u8> {
+                    self.to_be_bytes().to_vec()
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! ordered_key_signed {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl OrderedKey for $t {
+                fn ordered_key(&self) -> Vec<u8> {
+                    // flipping the sign bit puts negative values before positive ones
+                    ((*self as $u) ^ (1 << (<$u>::BITS - 1))).to_be_bytes().to_vec()
+                }
+            }
+        )*
+    };
+}
+
+ordered_key_unsigned!(u8, u16, u32, u64, u128);
+ordered_key_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+impl OrderedKey for usize {
+    fn ordered_key(&self) -> Vec<u8> {
+        (*self as u64).ordered_key()
+    }
+}
+
+impl OrderedKey for isize {
+    fn ordered_key(&self) -> Vec<u8> {
+        (*self as i64).ordered_key()
+    }
+}
+
+impl OrderedKey for char {
+    fn ordered_key(&self) -> Vec<u8> {
+        (*self as u32).ordered_key()
+    }
+}
+
+impl OrderedKey for String {
+    fn ordered_key(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> OrderedKey for chrono::DateTime<Tz> {
+    fn ordered_key(&self) -> Vec<u8> {
+        self.naive_utc().ordered_key()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl OrderedKey for chrono::NaiveDateTime {
+    fn ordered_key(&self) -> Vec<u8> {
+        let mut bytes = self.timestamp().ordered_key();
+        bytes.extend(self.timestamp_subsec_nanos().ordered_key());
+        bytes
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl OrderedKey for chrono::NaiveDate {
+    fn ordered_key(&self) -> Vec<u8> {
+        chrono::Datelike::num_days_from_ce(self).ordered_key()
+    }
+}
+
+/// Encodes both ends of a range with [`OrderedKey`].
+#[cfg(any(feature = "sqlite_db", feature = "postgresql_db", feature = "redis_db"))]
+pub(crate) fn ordered_bounds<U, R>(
+    range: &R,
+) -> (std::ops::Bound<Vec<u8>>, std::ops::Bound<Vec<u8>>)
+where
+    U: OrderedKey,
+    R: std::ops::RangeBounds<U>,
+{
+    use std::ops::Bound;
+
+    let encode = |bound: Bound<&U>| match bound {
+        Bound::Included(x) => Bound::Included(x.ordered_key()),
+        Bound::Excluded(x) => Bound::Excluded(x.ordered_key()),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    (encode(range.start_bound()), encode(range.end_bound()))
+}
+
+/// Prefix of the keys of a namespace in a key-value store.
+///
+/// The length of the name comes first, so no prefix is the start of another
+/// one, e.g. `7:sensors:` and `13:sensors:room1:`.
+#[cfg(any(feature = "unqlite_db", feature = "memory_db"))]
+pub(crate) fn namespace_prefix(namespace: Option<&str>) -> Vec<u8> {
+    match namespace {
+        Some(x) => format!("{}:{}:", x.len(), x).into_bytes(),
+        None => Vec::new(),
+    }
+}
+
+/// Quotes a table name so it can be used as an SQL identifier.
+#[cfg(any(feature = "sqlite_db", feature = "postgresql_db"))]
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
 impl std::fmt::Display for DbTableError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
 
+#[cfg(test)]
+mod ordered_key {
+    use crate::OrderedKey;
+
+    fn assert_sorted<T: OrderedKey>(values: &[T]) {
+        let keys: Vec<Vec<u8>> = values.iter().map(|x| x.ordered_key()).collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(sorted, keys);
+    }
+
+    #[test]
+    fn unsigned() {
+        assert_sorted(&[0u16, 1, 255, 256, 1000, u16::MAX]);
+        assert_sorted(&[0u64, 1, 255, 256, 1 << 40, u64::MAX]);
+    }
+
+    #[test]
+    fn signed() {
+        assert_sorted(&[i8::MIN, -100, -1, 0, 1, 100, i8::MAX]);
+        assert_sorted(&[i64::MIN, -256, -255, -1, 0, 1, 255, 256, i64::MAX]);
+    }
+
+    #[test]
+    fn string() {
+        assert_sorted(&[
+            String::from(""),
+            String::from("a"),
+            String::from("ab"),
+            String::from("b"),
+        ]);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn datetime() {
+        let dates: Vec<_> = [
+            "1960-01-01T00:00:00Z",
+            "2019-01-01T12:00:00+02:00",
+            "2019-01-01T11:00:00Z",
+            "2019-01-01T11:00:00.5Z",
+            "2021-06-01T00:00:00Z",
+        ]
+        .iter()
+        .map(|x| chrono::DateTime::parse_from_rfc3339(x).unwrap())
+        .collect();
+        assert_sorted(&dates);
+    }
+}
+
+#[cfg(all(test, any(feature = "sqlite_db", feature = "postgresql_db")))]
+mod identifier {
+    use crate::db_structs::quote_identifier;
+
+    #[test]
+    fn quote() {
+        assert_eq!("\"row\"", quote_identifier("row"));
+        assert_eq!("\"my \"\"table\"\"\"", quote_identifier("my \"table\""));
+    }
+}
+
+#[cfg(test)]
+mod header_change {
+    use super::check_row_length;
+
+    #[cfg(any(
+        feature = "unqlite_db",
+        feature = "postgresql_db",
+        feature = "redis_db"
+    ))]
+    #[test]
+    fn rewrite() {
+        use super::rewrite_row;
+
+        let from = vec![String::from("a"), String::from("b"), String::from("c")];
+        let to = vec![String::from("c"), String::from("d"), String::from("a")];
+        assert_eq!(vec![3, 0, 1], rewrite_row(&[1, 2, 3], &from, &to, &0));
+    }
+
+    #[test]
+    fn row_length() {
+        let headers = vec![String::from("a"), String::from("b")];
+        assert!(check_row_length(&headers, &[1, 2]).is_ok());
+        assert!(check_row_length(&headers, &[1, 2, 3]).is_err());
+    }
+}
+
 #[cfg(test)]
 mod dbobject {
     use crate::DbObject;
@@ -70,5 +365,4 @@ mod dbobject {
         let d = DbObject::new(15);
         assert_ne!(d.hash, 0);
     }
-
 }
@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum IndexOrColumn {
     Column(String),
     Index(usize),
@@ -27,3 +27,112 @@ impl From<usize> for IndexOrColumn {
         IndexOrColumn::Index(s)
     }
 }
+
+/// Reduction applied to the values of a column that fall in the same bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    Sum,
+    Mean,
+    Min,
+    Max,
+    First,
+    Last,
+    /// number of values
+    Count,
+}
+
+impl Aggregation {
+    /// Reduces the values to a single value.
+    ///
+    /// Returns None if there are no values or the result does not fit in `V`.
+    /// Sum and Mean add up in f64, so integer columns can't overflow.
+    #[cfg(feature = "num")]
+    pub fn apply<V>(&self, values: &[&V]) -> Option<V>
+    where
+        V: num_traits::Num + num_traits::NumCast + std::cmp::PartialOrd + Clone,
+    {
+        let first = (*values.first()?).clone();
+        let sum = || -> Option<f64> { values.iter().map(|v| v.to_f64()).sum() };
+        let result = match self {
+            Aggregation::Sum => num_traits::cast(sum()?)?,
+            Aggregation::Mean => num_traits::cast(sum()? / values.len() as f64)?,
+            Aggregation::Min => values
+                .iter()
+                .fold(first, |x, v| if **v < x { (*v).clone() } else { x }),
+            Aggregation::Max => values
+                .iter()
+                .fold(first, |x, v| if **v > x { (*v).clone() } else { x }),
+            Aggregation::First => first,
+            Aggregation::Last => (*values[values.len() - 1]).clone(),
+            Aggregation::Count => num_traits::cast(values.len())?,
+        };
+        Some(result)
+    }
+}
+
+/// How to fill cells that have no value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FillMethod<V> {
+    /// use the last value before the gap
+    Forward,
+    /// use the first value after the gap
+    Backward,
+    /// use a constant value
+    Value(V),
+}
+
+/// Statistic computed over a rolling window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RollingStatistic {
+    Sum,
+    Mean,
+    Min,
+    Max,
+    /// population standard deviation
+    Std,
+    Median,
+}
+
+/// Direction in which a table is reduced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    Row,
+    Column,
+}
+
+/// Which index values are kept when two tables are joined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinType {
+    /// index values in both tables
+    Inner,
+    /// index values of the left table
+    Left,
+    /// index values of the right table
+    Right,
+    /// index values in one of the tables
+    Outer,
+}
+
+/// Combines two cells into one.
+pub type Combine<V> = Box<dyn Fn(&V, &V) -> V>;
+
+/// What to do when the same index value is found more than once.
+pub enum DuplicatePolicy<V> {
+    /// return an error with the keys that collided
+    Error,
+    KeepFirst,
+    KeepLast,
+    /// combine the cells of the kept row (first argument) and the duplicate row (second argument)
+    Aggregate(Combine<V>),
+}
+
+impl<V> std::fmt::Debug for DuplicatePolicy<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DuplicatePolicy::Error => write!(f, "Error"),
+            DuplicatePolicy::KeepFirst => write!(f, "KeepFirst"),
+            DuplicatePolicy::KeepLast => write!(f, "KeepLast"),
+            DuplicatePolicy::Aggregate(_) => write!(f, "Aggregate"),
+        }
+    }
+}
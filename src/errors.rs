@@ -1,14 +1,31 @@
 #[derive(Debug)]
 pub struct TableError {
     description: String,
+    keys: Vec<String>,
 }
 
 impl TableError {
     pub fn new<S: Into<String>>(s: S) -> TableError {
         TableError {
             description: s.into(),
+            keys: Vec::new(),
         }
     }
+
+    /// error for index values that are found more than once, every key is listed once
+    pub fn duplicates(mut keys: Vec<String>) -> TableError {
+        let mut seen = std::collections::HashSet::new();
+        keys.retain(|x| seen.insert(x.clone()));
+        TableError {
+            description: format!("duplicate index values: {}", keys.join(", ")),
+            keys,
+        }
+    }
+
+    /// the index values that caused the error, formatted with Debug
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
 }
 
 impl std::fmt::Display for TableError {
@@ -22,7 +39,7 @@ pub struct TableReadError(String);
 
 impl TableReadError {
     pub fn new<S: Into<String>>(s: S) -> TableReadError {
-        TableReadError { 0: s.into() }
+        TableReadError(s.into())
     }
 }
 
@@ -32,6 +49,34 @@ impl std::fmt::Display for TableReadError {
     }
 }
 
+#[derive(Debug)]
+pub struct TableWriteError(String);
+
+impl TableWriteError {
+    pub fn new<S: Into<String>>(s: S) -> TableWriteError {
+        TableWriteError(s.into())
+    }
+}
+
+impl std::fmt::Display for TableWriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for TableWriteError {
+    fn from(err: csv::Error) -> Self {
+        Self::new(format!("{}", err))
+    }
+}
+
+impl From<std::io::Error> for TableWriteError {
+    fn from(err: std::io::Error) -> Self {
+        Self::new(format!("{}", err))
+    }
+}
+
 #[cfg(feature = "csv")]
 impl From<csv::Error> for TableReadError {
     fn from(err: csv::Error) -> Self {
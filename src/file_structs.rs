@@ -0,0 +1,166 @@
+#![cfg(feature = "serialize")]
+
+use crate::Table;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// first bytes of every snapshot file
+const MAGIC: &[u8; 8] = b"TSTABLE\0";
+/// version of the snapshot layout, bumped on incompatible changes
+const VERSION: u32 = 1;
+/// magic, version, payload length and checksum
+const HEADER_LEN: usize = 8 + 4 + 8 + 8;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Bincode(std::boxed::Box<bincode::ErrorKind>),
+    /// the file does not start with the snapshot magic bytes
+    NotASnapshot,
+    /// the file was written by a newer version of this crate
+    UnsupportedVersion(u32),
+    /// the file is shorter than its header says
+    Truncated,
+    /// the payload does not match the checksum in the header
+    ChecksumMismatch,
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> SnapshotError {
+        SnapshotError::Io(err)
+    }
+}
+
+impl From<std::boxed::Box<bincode::ErrorKind>> for SnapshotError {
+    fn from(err: std::boxed::Box<bincode::ErrorKind>) -> SnapshotError {
+        SnapshotError::Bincode(err)
+    }
+}
+
+#[derive(Serialize)]
+struct SnapshotRef<'a, U: Ord, V> {
+    headers: &'a Vec<String>,
+    meta_data: &'a Option<HashMap<String, String>>,
+    data: &'a BTreeMap<U, Vec<V>>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot<U: Ord, V> {
+    headers: Vec<String>,
+    meta_data: Option<HashMap<String, String>>,
+    data: BTreeMap<U, Vec<V>>,
+}
+
+/// `path` with `suffix` appended to the file name, in the same directory
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    // same directory as `path`, so the rename is atomic
+    let tmp_path = with_suffix(path, ".tmp");
+    let written = File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&tmp_path, path)) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    // makes the rename itself durable
+    #[cfg(unix)]
+    {
+        if let Some(dir) = path.parent() {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            File::open(dir)?.sync_all()?;
+        }
+    }
+    Ok(())
+}
+
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord + serde::de::DeserializeOwned + serde::Serialize,
+    V: std::fmt::Debug + serde::de::DeserializeOwned + serde::Serialize,
+{
+    /// Writes the table to a versioned binary snapshot.
+    ///
+    /// The snapshot is written to a temporary file next to `path` and renamed
+    /// over it afterwards, so a crash while writing keeps the previous snapshot intact.
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        let payload = bincode::serialize(&SnapshotRef {
+            headers: &self.headers,
+            meta_data: &self.meta_data,
+            data: &self.data,
+        })?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&seahash::hash(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+
+        write_atomic(path.as_ref(), &bytes)?;
+        Ok(())
+    }
+
+    /// Reads a snapshot written by `save_file`, checking its version and checksum.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Table<U, V>, SnapshotError> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(SnapshotError::Truncated);
+        }
+
+        let mut version = [0; 4];
+        version.copy_from_slice(&bytes[8..12]);
+        let version = u32::from_le_bytes(version);
+        if version > VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let mut length = [0; 8];
+        length.copy_from_slice(&bytes[12..20]);
+        let length = u64::from_le_bytes(length);
+        let mut checksum = [0; 8];
+        checksum.copy_from_slice(&bytes[20..28]);
+        let checksum = u64::from_le_bytes(checksum);
+
+        let payload = &bytes[HEADER_LEN..];
+        if (payload.len() as u64) < length {
+            return Err(SnapshotError::Truncated);
+        }
+        let payload = &payload[..length as usize];
+        if seahash::hash(payload) != checksum {
+            return Err(SnapshotError::ChecksumMismatch);
+        }
+
+        let snapshot: Snapshot<U, V> = bincode::deserialize(payload)?;
+        Ok(Table {
+            headers: snapshot.headers,
+            data: snapshot.data,
+            meta_data: snapshot.meta_data,
+        })
+    }
+}
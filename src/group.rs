@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "num")]
+use crate::enums::Aggregation;
+use crate::enums::IndexOrColumn;
+use crate::{Table, TableError};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Rows of a table grouped by the value of a column or by a key of the index, created with
+/// [group_by](struct.Table.html#method.group_by) or [group_by_index](struct.Table.html#method.group_by_index).
+#[derive(Debug)]
+pub struct GroupBy<'a, U, V, K = V>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+    V: std::fmt::Debug,
+{
+    table: &'a Table<U, V>,
+    column: Option<usize>,
+    groups: BTreeMap<K, Vec<&'a Vec<V>>>,
+}
+
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+    V: std::fmt::Debug + Clone,
+{
+    /// groups the rows by the value in the column
+    ///
+    ///```
+    /// use timeseries::{vec2, Table};
+    ///
+    /// let headers = vec![String::from("kind"), String::from("count")];
+    /// let t = Table::new(headers, vec![1, 2, 3], vec2![[1, 10], [2, 20], [1, 30]]).unwrap();
+    ///
+    /// let sums = t
+    ///     .group_by("kind")
+    ///     .unwrap()
+    ///     .agg_with(|values| values.iter().copied().sum());
+    ///
+    /// assert_eq!(&[String::from("count")], &sums.headers[..]);
+    /// assert_eq!(Some(&vec![40]), sums.data.get(&1));
+    /// assert_eq!(Some(&vec![20]), sums.data.get(&2));
+    ///```
+    pub fn group_by<X: Into<IndexOrColumn>>(
+        &self,
+        column: X,
+    ) -> Result<GroupBy<'_, U, V>, TableError>
+    where
+        V: std::cmp::Ord,
+    {
+        let column = self.column_index(column)?;
+        let mut groups: BTreeMap<V, Vec<&Vec<V>>> = BTreeMap::new();
+        for row in self.data.values() {
+            groups.entry(row[column].clone()).or_default().push(row);
+        }
+        Ok(GroupBy {
+            table: self,
+            column: Some(column),
+            groups,
+        })
+    }
+
+    /// groups the rows by a key computed from the index, every column is kept.
+    /// The values don't have to be `Ord`, so a table of floats can be grouped
+    ///
+    ///```
+    /// use timeseries::{vec2, Table};
+    ///
+    /// let headers = vec![String::from("value")];
+    /// let t = Table::new(headers, vec![1, 2, 11], vec2![[1.5], [2.5], [4.0]]).unwrap();
+    ///
+    /// let sums = t
+    ///     .group_by_index(|k| k / 10)
+    ///     .agg_with(|values| values.iter().copied().sum());
+    ///
+    /// assert_eq!(Some(&vec![4.0]), sums.data.get(&0));
+    /// assert_eq!(Some(&vec![4.0]), sums.data.get(&1));
+    ///```
+    pub fn group_by_index<K, F>(&self, f: F) -> GroupBy<'_, U, V, K>
+    where
+        K: std::cmp::Ord,
+        F: Fn(&U) -> K,
+    {
+        let mut groups: BTreeMap<K, Vec<&Vec<V>>> = BTreeMap::new();
+        for (k, row) in self.data.iter() {
+            groups.entry(f(k)).or_default().push(row);
+        }
+        GroupBy {
+            table: self,
+            column: None,
+            groups,
+        }
+    }
+}
+
+impl<'a, U, V, K> GroupBy<'a, U, V, K>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+    V: std::fmt::Debug + Clone,
+    K: std::fmt::Debug + std::cmp::Ord + Clone,
+{
+    /// number of groups
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// the keys of the groups
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.groups.keys()
+    }
+
+    /// reduces every other column of every group with `f`,
+    /// the result is indexed by the key of the group
+    pub fn agg_with<F>(&self, f: F) -> Table<K, V>
+    where
+        F: Fn(&[&V]) -> V,
+    {
+        let data = self
+            .groups
+            .iter()
+            .map(|(k, rows)| (k.clone(), self.reduce_group(rows, &f)))
+            .collect();
+        self.to_table(data)
+    }
+
+    /// reduces every other column of every group with one of the standard aggregations,
+    /// returns an error if the result does not fit the value type, e.g. the count of a group of `u8` with more than 255 rows
+    #[cfg(feature = "num")]
+    pub fn agg(&self, aggregation: Aggregation) -> Result<Table<K, V>, TableError>
+    where
+        V: num_traits::Num + num_traits::NumCast + std::cmp::PartialOrd,
+    {
+        let data = self
+            .groups
+            .iter()
+            .map(|(k, rows)| Ok((k.clone(), self.aggregate_group(rows, &aggregation)?)))
+            .collect::<Result<_, TableError>>()?;
+        Ok(self.to_table(data))
+    }
+
+    #[cfg(feature = "num")]
+    fn aggregate_group(
+        &self,
+        rows: &[&Vec<V>],
+        aggregation: &Aggregation,
+    ) -> Result<Vec<V>, TableError>
+    where
+        V: num_traits::Num + num_traits::NumCast + std::cmp::PartialOrd,
+    {
+        self.reduce_group(rows, &|values: &[&V]| {
+            aggregation.apply(values).ok_or_else(|| {
+                TableError::new(format!(
+                    "{:?} of {} values does not fit the value type",
+                    aggregation,
+                    values.len()
+                ))
+            })
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn reduce_group<F, T>(&self, rows: &[&Vec<V>], f: &F) -> Vec<T>
+    where
+        F: Fn(&[&V]) -> T,
+    {
+        (0..self.table.headers.len())
+            .filter(|i| Some(*i) != self.column)
+            .map(|i| {
+                let column: Vec<&V> = rows.iter().map(|r| &r[i]).collect();
+                f(&column)
+            })
+            .collect()
+    }
+
+    fn to_table(&self, data: BTreeMap<K, Vec<V>>) -> Table<K, V> {
+        let headers = self
+            .table
+            .headers
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != self.column)
+            .map(|(_, h)| h.clone())
+            .collect();
+
+        let mut table = Table::new_btreemap(headers, data);
+        table.meta_data = self.table.meta_data.clone();
+        table
+    }
+}
+
+impl<'a, U, V, K> GroupBy<'a, U, V, K>
+where
+    U: std::fmt::Debug + std::cmp::Ord + Send + Sync,
+    V: std::fmt::Debug + Clone + Send + Sync,
+    K: std::fmt::Debug + std::cmp::Ord + Clone + Send + Sync,
+{
+    /// same as `agg_with`, but the groups are reduced in parallel
+    #[cfg(feature = "rayon")]
+    pub fn p_agg_with<F>(&self, f: F) -> Table<K, V>
+    where
+        F: Fn(&[&V]) -> V + Sync + Send,
+    {
+        let groups: Vec<(&K, &Vec<&Vec<V>>)> = self.groups.iter().collect();
+        let data = groups
+            .into_par_iter()
+            .map(|(k, rows)| (k.clone(), self.reduce_group(rows, &f)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect();
+        self.to_table(data)
+    }
+
+    /// same as `agg`, but the groups are reduced in parallel
+    #[cfg(all(feature = "rayon", feature = "num"))]
+    pub fn p_agg(&self, aggregation: Aggregation) -> Result<Table<K, V>, TableError>
+    where
+        V: num_traits::Num + num_traits::NumCast + std::cmp::PartialOrd,
+    {
+        let groups: Vec<(&K, &Vec<&Vec<V>>)> = self.groups.iter().collect();
+        let data = groups
+            .into_par_iter()
+            .map(|(k, rows)| Ok((k.clone(), self.aggregate_group(rows, &aggregation)?)))
+            .collect::<Result<Vec<_>, TableError>>()?
+            .into_iter()
+            .collect();
+        Ok(self.to_table(data))
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::enums::JoinType;
+use crate::Table;
+
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord + Clone,
+    V: std::fmt::Debug + Clone,
+{
+    /// joins the columns of two tables on their index.
+    /// Headers that are in both tables get the suffix `_left` or `_right`,
+    /// cells without a row in one of the tables are None.
+    ///
+    ///```
+    /// use timeseries::{vec2, Table};
+    /// use timeseries::enums::JoinType;
+    ///
+    /// let left = Table::new(vec![String::from("a")], vec![1, 2], vec2![[1], [2]]).unwrap();
+    /// let right = Table::new(vec![String::from("a")], vec![2, 3], vec2![[20], [30]]).unwrap();
+    ///
+    /// let t = left.join(&right, JoinType::Outer);
+    ///
+    /// assert_eq!(&[String::from("a_left"), String::from("a_right")], &t.headers[..]);
+    /// assert_eq!(Some(&vec![Some(2), Some(20)]), t.data.get(&2));
+    /// assert_eq!(Some(&vec![None, Some(30)]), t.data.get(&3));
+    ///```
+    pub fn join(&self, other: &Table<U, V>, how: JoinType) -> Table<U, Option<V>> {
+        self.join_suffixed(other, how, "_left", "_right")
+    }
+
+    /// same as `join`, with the suffixes for duplicate headers
+    pub fn join_suffixed(
+        &self,
+        other: &Table<U, V>,
+        how: JoinType,
+        left_suffix: &str,
+        right_suffix: &str,
+    ) -> Table<U, Option<V>> {
+        let keys: BTreeSet<&U> = match how {
+            JoinType::Inner => self
+                .data
+                .keys()
+                .filter(|k| other.data.contains_key(k))
+                .collect(),
+            JoinType::Left => self.data.keys().collect(),
+            JoinType::Right => other.data.keys().collect(),
+            JoinType::Outer => self.data.keys().chain(other.data.keys()).collect(),
+        };
+
+        let data: BTreeMap<U, Vec<Option<V>>> = keys
+            .into_iter()
+            .map(|k| {
+                let mut row = Self::join_row(self.data.get(k), self.headers.len());
+                row.extend(Self::join_row(other.data.get(k), other.headers.len()));
+                (k.clone(), row)
+            })
+            .collect();
+
+        let mut table = Table::new_btreemap(
+            Self::join_headers(&self.headers, &other.headers, left_suffix, right_suffix),
+            data,
+        );
+        table.meta_data = self.meta_data.clone();
+        table
+    }
+
+    /// the cells of a row as options, or None for every column if there is no row
+    pub(crate) fn join_row(row: Option<&Vec<V>>, len: usize) -> Vec<Option<V>> {
+        match row {
+            Some(row) => row.iter().cloned().map(Some).collect(),
+            None => vec![None; len],
+        }
+    }
+
+    /// headers of both tables, with the suffix added to the headers that are in both
+    pub(crate) fn join_headers(
+        left: &[String],
+        right: &[String],
+        left_suffix: &str,
+        right_suffix: &str,
+    ) -> Vec<String> {
+        let left_headers = left.iter().map(|h| {
+            if right.contains(h) {
+                format!("{}{}", h, left_suffix)
+            } else {
+                h.clone()
+            }
+        });
+        let right_headers = right.iter().map(|h| {
+            if left.contains(h) {
+                format!("{}{}", h, right_suffix)
+            } else {
+                h.clone()
+            }
+        });
+        left_headers.chain(right_headers).collect()
+    }
+}
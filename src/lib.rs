@@ -19,6 +19,12 @@ pub extern crate num_traits;
 #[cfg(feature = "postgres")]
 pub extern crate postgres;
 
+#[cfg(feature = "tokio-postgres")]
+pub extern crate tokio_postgres;
+
+#[cfg(feature = "async")]
+pub extern crate tokio;
+
 #[cfg(feature = "rayon")]
 pub extern crate rayon;
 
@@ -47,33 +53,61 @@ pub extern crate redis;
 // }
 #[cfg(feature = "chrono")]
 mod chrono_structs;
+#[cfg(any(
+    feature = "sqlite_db",
+    feature = "postgresql_db",
+    feature = "redis_db",
+    feature = "unqlite_db"
+))]
+mod connection;
 mod errors;
 #[cfg(feature = "ndarray")]
 mod ndarray_structs;
 mod structs;
 #[macro_use]
 mod macros;
-#[cfg(feature = "serde")]
+#[cfg(feature = "_db_base")]
 mod db_structs;
 pub mod enums;
+#[cfg(feature = "serialize")]
+mod file_structs;
+mod group;
+mod join;
+#[cfg(feature = "serialize")]
+mod log_structs;
+#[cfg(feature = "memory_db")]
+mod memory_structs;
+mod missing;
 #[cfg(feature = "postgresql_db")]
 mod postgresql_structs;
 #[cfg(feature = "redis_db")]
 mod redis_structs;
+mod reshape;
+mod rolling;
 #[cfg(feature = "sqlite_db")]
 mod sqlite_structs;
+#[cfg(feature = "_db_base")]
+mod store;
 #[cfg(test)]
 mod tests;
 mod traits;
+mod typed_structs;
 #[cfg(feature = "unqlite_db")]
 mod unqlite_structs;
 mod utils;
 
 #[cfg(feature = "chrono")]
 pub use chrono_structs::*;
-#[cfg(feature = "serde")]
+#[cfg(feature = "_db_base")]
 pub use db_structs::*;
 pub use errors::*;
+#[cfg(feature = "serialize")]
+pub use file_structs::*;
+pub use group::*;
+#[cfg(feature = "serialize")]
+pub use log_structs::*;
+#[cfg(feature = "memory_db")]
+pub use memory_structs::*;
 #[cfg(feature = "ndarray")]
 pub use ndarray_structs::*;
 #[cfg(feature = "postgresql_db")]
@@ -82,6 +116,10 @@ pub use postgresql_structs::*;
 pub use redis_structs::*;
 #[cfg(feature = "sqlite_db")]
 pub use sqlite_structs::*;
+#[cfg(feature = "_db_base")]
+pub use store::*;
 pub use structs::*;
 pub use traits::*;
+pub use typed_structs::*;
+#[cfg(feature = "csv")]
 pub use utils::*;
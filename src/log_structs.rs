@@ -0,0 +1,365 @@
+#![cfg(feature = "serialize")]
+
+use crate::file_structs::with_suffix;
+use crate::{BtreeMapTrait, SnapshotError, Table, TableError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// first bytes of every log file
+const MAGIC: &[u8; 8] = b"TSLOG\0\0\0";
+/// version of the log layout, bumped on incompatible changes
+const VERSION: u32 = 1;
+/// magic and version
+const FILE_HEADER_LEN: usize = 8 + 4;
+/// payload length and checksum in front of every record
+const FRAME_HEADER_LEN: usize = 4 + 8;
+
+#[derive(Debug)]
+pub enum LogError {
+    Io(io::Error),
+    Bincode(std::boxed::Box<bincode::ErrorKind>),
+    Snapshot(SnapshotError),
+    Table(TableError),
+    /// the file does not start with the log magic bytes
+    NotALog,
+    /// the file was written by a newer version of this crate
+    UnsupportedVersion(u32),
+    /// the log or its snapshot was created with other headers than the ones passed to `open`
+    HeaderMismatch(Vec<String>),
+    /// the record at this offset is damaged and is not the end of the file, or is the headers record
+    Corrupt(u64),
+}
+
+impl std::fmt::Display for LogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<io::Error> for LogError {
+    fn from(err: io::Error) -> LogError {
+        LogError::Io(err)
+    }
+}
+
+impl From<std::boxed::Box<bincode::ErrorKind>> for LogError {
+    fn from(err: std::boxed::Box<bincode::ErrorKind>) -> LogError {
+        LogError::Bincode(err)
+    }
+}
+
+impl From<SnapshotError> for LogError {
+    fn from(err: SnapshotError) -> LogError {
+        LogError::Snapshot(err)
+    }
+}
+
+impl From<TableError> for LogError {
+    fn from(err: TableError) -> LogError {
+        LogError::Table(err)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum Record<U, V> {
+    /// headers of the table, always the first record
+    Headers(Vec<String>),
+    Row(U, Vec<V>),
+}
+
+/// Splits the bytes after the file header into record payloads.
+///
+/// Returns the payloads and the length of the valid part of the log. A torn
+/// tail, a last frame that runs past the end of the file or fails its checksum,
+/// is left out of the valid part. A frame that fails its checksum with more
+/// bytes after it is `LogError::Corrupt`.
+fn frames(bytes: &[u8]) -> Result<(Vec<&[u8]>, usize), LogError> {
+    let mut payloads = Vec::new();
+    let mut position = FILE_HEADER_LEN;
+    while bytes.len() >= position + FRAME_HEADER_LEN {
+        let mut length = [0; 4];
+        length.copy_from_slice(&bytes[position..position + 4]);
+        let length = u32::from_le_bytes(length) as usize;
+        let mut checksum = [0; 8];
+        checksum.copy_from_slice(&bytes[position + 4..position + FRAME_HEADER_LEN]);
+        let checksum = u64::from_le_bytes(checksum);
+
+        let start = position + FRAME_HEADER_LEN;
+        if bytes.len() < start + length {
+            break;
+        }
+        if seahash::hash(&bytes[start..start + length]) != checksum {
+            if bytes.len() > start + length {
+                return Err(LogError::Corrupt(position as u64));
+            }
+            break;
+        }
+        payloads.push(&bytes[start..start + length]);
+        position = start + length;
+    }
+    Ok((payloads, position))
+}
+
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&seahash::hash(payload).to_le_bytes());
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// Append-only log of rows, for tables that grow a few rows at a time.
+///
+/// Every `append` writes one framed and checksummed record to the end of the
+/// file. `replay` rebuilds the table from the last snapshot and the records
+/// after it, and `compact` folds the records into the snapshot at `<path>.snapshot`.
+/// A record that failed to write is cut off right away, and one that was only
+/// partly written because of a crash is cut off when the log is opened again.
+/// A damaged record anywhere else fails `open` and `replay` with
+/// `LogError::Corrupt` instead, the records after it are not dropped.
+#[derive(Debug)]
+pub struct TableLog<U, V> {
+    path: PathBuf,
+    file: File,
+    headers: Vec<String>,
+    /// length of the file header and the headers record, the part that survives compaction
+    base_len: u64,
+    /// end of the last complete record
+    len: u64,
+    /// bytes of a partly written record cut off by `open`
+    truncated: u64,
+    records: usize,
+    marker: PhantomData<(U, V)>,
+}
+
+impl<U, V> TableLog<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord + serde::de::DeserializeOwned + serde::Serialize,
+    V: std::fmt::Debug + serde::de::DeserializeOwned + serde::Serialize,
+{
+    /// Opens the log at `path`, or creates it for a table with `headers`.
+    pub fn open<P: AsRef<Path>>(path: P, headers: Vec<String>) -> Result<TableLog<U, V>, LogError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let mut log = TableLog {
+            path,
+            file,
+            headers,
+            base_len: 0,
+            len: 0,
+            truncated: 0,
+            records: 0,
+            marker: PhantomData,
+        };
+
+        // a crash during the first write can leave a partial file header behind
+        if !bytes.starts_with(MAGIC) {
+            if bytes.len() < MAGIC.len() && MAGIC.starts_with(&bytes) {
+                log.initialize()?;
+                return Ok(log);
+            }
+            return Err(LogError::NotALog);
+        }
+        // nothing follows the file header yet, a damaged headers record is an
+        // error instead since rows may follow it
+        if bytes.len() <= FILE_HEADER_LEN {
+            log.initialize()?;
+            return Ok(log);
+        }
+        let mut version = [0; 4];
+        version.copy_from_slice(&bytes[8..12]);
+        let version = u32::from_le_bytes(version);
+        if version > VERSION {
+            return Err(LogError::UnsupportedVersion(version));
+        }
+
+        let (payloads, valid_len) = frames(&bytes)?;
+        let first = match payloads.first() {
+            Some(x) => x,
+            None => return Err(LogError::Corrupt(FILE_HEADER_LEN as u64)),
+        };
+        log.check_headers(first)?;
+
+        log.base_len = (FILE_HEADER_LEN + FRAME_HEADER_LEN + first.len()) as u64;
+        log.len = valid_len as u64;
+        log.records = payloads.len() - 1;
+        if valid_len < bytes.len() {
+            log.truncated = (bytes.len() - valid_len) as u64;
+            log.file.set_len(log.len)?;
+            log.file.sync_data()?;
+        }
+        log.file.seek(SeekFrom::Start(log.len))?;
+        Ok(log)
+    }
+
+    /// checks that the first record of the log matches the headers of this log
+    fn check_headers(&self, payload: &[u8]) -> Result<(), LogError> {
+        match bincode::deserialize::<Record<U, V>>(payload) {
+            Ok(Record::Headers(headers)) if headers != self.headers => {
+                Err(LogError::HeaderMismatch(headers))
+            }
+            Ok(Record::Headers(_)) => Ok(()),
+            _ => Err(LogError::NotALog),
+        }
+    }
+
+    /// Writes `bytes` after the last complete record.
+    ///
+    /// A failed write can leave part of a record behind, which would hide every
+    /// record appended after it on the next `open`, so it is cut off again.
+    fn write_record(&mut self, bytes: &[u8]) -> Result<(), LogError> {
+        if let Err(err) = self.file.write_all(bytes) {
+            self.file.set_len(self.len)?;
+            self.file.seek(SeekFrom::Start(self.len))?;
+            return Err(err.into());
+        }
+        self.len += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// writes the file header and the headers record to an empty log
+    fn initialize(&mut self) -> Result<(), LogError> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend(frame(&bincode::serialize(&Record::<U, V>::Headers(
+            self.headers.clone(),
+        ))?));
+
+        self.len = 0;
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.write_record(&bytes)?;
+        self.file.sync_data()?;
+        self.base_len = self.len;
+        self.records = 0;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// location of the snapshot that `compact` writes
+    pub fn snapshot_path(&self) -> PathBuf {
+        with_suffix(&self.path, ".snapshot")
+    }
+
+    /// number of records appended since the last compaction
+    pub fn len(&self) -> usize {
+        self.records
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    /// Number of bytes of a partly written record that `open` cut off, 0 when the log was intact.
+    pub fn truncated(&self) -> u64 {
+        self.truncated
+    }
+
+    /// Appends one row. A row with an existing key replaces the old one on replay.
+    ///
+    /// The record is handed to the operating system, call `sync` to make sure it reached the disk.
+    pub fn append(&mut self, key: U, row: Vec<V>) -> Result<(), LogError> {
+        if row.len() != self.headers.len() {
+            return Err(TableError::new(format!(
+                "row has {} values, the log has {} headers",
+                row.len(),
+                self.headers.len()
+            ))
+            .into());
+        }
+
+        let payload = bincode::serialize(&Record::Row(key, row))?;
+        self.write_record(&frame(&payload))?;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// flushes the appended records to disk
+    pub fn sync(&mut self) -> Result<(), LogError> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Rebuilds the table from the snapshot and the records appended after it.
+    ///
+    /// Fails when the log or the snapshot no longer matches the headers of this
+    /// log, e.g. because another table was written to the same path. The key and
+    /// value types are not stored, a log must be opened with the types it was
+    /// written with.
+    pub fn replay(&self) -> Result<Table<U, V>, LogError> {
+        let mut bytes = Vec::new();
+        File::open(&self.path)?.read_to_end(&mut bytes)?;
+        if !bytes.starts_with(MAGIC) {
+            return Err(LogError::NotALog);
+        }
+        let (payloads, _) = frames(&bytes)?;
+        match payloads.first() {
+            Some(first) => self.check_headers(first)?,
+            None => return Err(LogError::NotALog),
+        }
+
+        let snapshot_path = self.snapshot_path();
+        let mut table = if snapshot_path.exists() {
+            Table::load_file(snapshot_path)?
+        } else {
+            Table::new_btreemap(self.headers.clone(), BTreeMap::new())
+        };
+        if table.headers != self.headers {
+            return Err(LogError::HeaderMismatch(table.headers));
+        }
+
+        for payload in payloads.into_iter().skip(1) {
+            match bincode::deserialize(payload)? {
+                Record::Row(k, v) if v.len() == self.headers.len() => table.insert(k, v),
+                Record::Row(k, v) => {
+                    return Err(TableError::new(format!(
+                        "row {:?} has {} values, the log has {} headers",
+                        k,
+                        v.len(),
+                        self.headers.len()
+                    ))
+                    .into())
+                }
+                Record::Headers(..) => return Err(LogError::NotALog),
+            };
+        }
+        Ok(table)
+    }
+
+    /// Folds the log into the snapshot and empties the log.
+    ///
+    /// The snapshot is replaced atomically before the log is truncated. After a
+    /// crash in between, the records are replayed over a snapshot that already
+    /// contains them, which gives the same table.
+    pub fn compact(&mut self) -> Result<Table<U, V>, LogError> {
+        let table = self.replay()?;
+        table.save_file(self.snapshot_path())?;
+
+        self.file.set_len(self.base_len)?;
+        self.file.sync_data()?;
+        self.len = self.base_len;
+        self.file.seek(SeekFrom::Start(self.len))?;
+        self.records = 0;
+        Ok(table)
+    }
+}
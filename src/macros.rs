@@ -25,13 +25,7 @@ macro_rules! vec2 {
     [$
         ($t:expr)
     ,+ $(,)*] => {
-        {
-            let mut v = Vec::new();
-            $(
-                v.push($t.to_vec());
-            )*
-            v
-        }
+        vec![$($t.to_vec()),+]
     };
 }
 
@@ -0,0 +1,371 @@
+#![cfg(feature = "memory_db")]
+
+use crate::db_structs::{check_row_length, namespace_prefix};
+use crate::{
+    BtreeMapTrait, DbChanges, DbObject, DbTableError, HeaderChange, StoreError, Table,
+    TableMetaTrait, TableStore,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Bound, RangeBounds};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// serialized keys and values of every table in a store
+type Entries = BTreeMap<Vec<u8>, Vec<u8>>;
+
+#[derive(Debug)]
+pub enum MemoryError {
+    Bincode(std::boxed::Box<bincode::ErrorKind>),
+    DbTableError(DbTableError),
+}
+
+impl std::fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<std::boxed::Box<bincode::ErrorKind>> for MemoryError {
+    fn from(err: std::boxed::Box<bincode::ErrorKind>) -> MemoryError {
+        MemoryError::Bincode(err)
+    }
+}
+
+/// Key-value store that lives in memory, meant for tests that should not need a database.
+///
+/// Rows are stored the same way as in the other backends: bincode keys, the
+/// `__HEADER` and `__META_DATA` special keys and hashed `DbObject` values.
+/// Clones share the same data, so a clone can be handed to the code under test.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryConfig {
+    entries: Arc<Mutex<Entries>>,
+    /// prefix for every key of the table, `None` means the table owns the whole store
+    pub namespace: Option<String>,
+}
+
+impl MemoryConfig {
+    pub fn new() -> MemoryConfig {
+        MemoryConfig::default()
+    }
+
+    /// Prefixes all keys with the length of `name` and `name`, e.g. `7:sensors:`,
+    /// so many tables can share one store.
+    ///
+    /// The returned config still shares its data with `self`.
+    pub fn with_namespace<S>(mut self, name: S) -> MemoryConfig
+    where
+        S: Into<String>,
+    {
+        self.namespace = Some(name.into());
+        self
+    }
+
+    /// Config of an older version of the table, kept by `HeaderChange::Version`.
+    ///
+    /// The version lives in the namespace `<namespace>_v<n>`.
+    pub fn version(&self, n: u32) -> MemoryConfig {
+        let namespace = self.namespace.as_deref().unwrap_or_default();
+        self.clone().with_namespace(format!("{}_v{}", namespace, n))
+    }
+
+    /// number of stored keys over all namespaces, including the special keys
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        // the map only holds bytes, so a panic while holding the lock can't leave it invalid
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn prefix(&self) -> Vec<u8> {
+        namespace_prefix(self.namespace.as_deref())
+    }
+
+    /// adds the namespace prefix to a serialized key
+    fn key(&self, key: Vec<u8>) -> Vec<u8> {
+        let mut prefixed = self.prefix();
+        prefixed.extend(key);
+        prefixed
+    }
+
+    /// checks if a table was saved before
+    pub(crate) fn table_exists(&self) -> Result<bool, MemoryError> {
+        self.table_exists_in(&self.entries())
+    }
+
+    /// Like `table_exists`, on entries that are already locked.
+    fn table_exists_in(&self, entries: &Entries) -> Result<bool, MemoryError> {
+        let header_key = self.key(bincode::serialize("__HEADER")?);
+        Ok(entries.contains_key(&header_key))
+    }
+
+    /// removes every key of the table
+    pub(crate) fn delete_keys(&self) {
+        let prefix = self.prefix();
+        self.entries().retain(|k, _| !k.starts_with(&prefix));
+    }
+
+    /// headers of the saved table
+    fn stored_headers(&self, entries: &Entries) -> Result<Vec<String>, MemoryError> {
+        let header_key = self.key(bincode::serialize("__HEADER")?);
+        let headers: DbObject<Vec<String>> = match entries.get(&header_key) {
+            Some(x) => bincode::deserialize(x)?,
+            None => {
+                return Err(MemoryError::DbTableError(
+                    DbTableError::DbHeaderDoesNotExist,
+                ))
+            }
+        };
+        Ok(headers.item)
+    }
+
+    /// moves the table to the first free version and returns its number
+    fn move_to_version(&self, entries: &mut Entries) -> Result<u32, MemoryError> {
+        if self.namespace.is_none() {
+            return Err(MemoryError::DbTableError(DbTableError::DbNamespaceRequired));
+        }
+        let mut n = 1;
+        while self.version(n).table_exists_in(entries)? {
+            n += 1;
+        }
+
+        let prefix = self.prefix();
+        let version_prefix = self.version(n).prefix();
+        let keys: Vec<Vec<u8>> = entries
+            .keys()
+            .filter(|k| k.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for key in keys {
+            if let Some(value) = entries.remove(&key) {
+                let mut moved = version_prefix.clone();
+                moved.extend_from_slice(&key[prefix.len()..]);
+                entries.insert(moved, value);
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug + Clone + std::cmp::Ord + serde::de::DeserializeOwned + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    pub fn from_memory(config: &MemoryConfig) -> Result<Table<U, V>, MemoryError> {
+        Self::load_range_memory(config, ..)
+    }
+
+    /// Loads only the rows whose index falls in `range`.
+    pub fn load_range_memory<R>(config: &MemoryConfig, range: R) -> Result<Table<U, V>, MemoryError>
+    where
+        R: RangeBounds<U>,
+    {
+        let prefix = config.prefix();
+        let header_key = bincode::serialize("__HEADER")?;
+        let meta_key = bincode::serialize("__META_DATA")?;
+
+        let mut headers: Option<DbObject<Vec<String>>> = None;
+        let mut metadata: DbObject<Option<HashMap<String, String>>> = DbObject::new(None);
+        let mut btable = BTreeMap::new();
+
+        for (item_k, item_v) in config.entries().iter() {
+            if !item_k.starts_with(&prefix) {
+                continue;
+            }
+            let item_k = &item_k[prefix.len()..];
+
+            if item_k == header_key.as_slice() {
+                headers = Some(bincode::deserialize(item_v)?);
+            } else if item_k == meta_key.as_slice() {
+                metadata = bincode::deserialize(item_v)?;
+            } else {
+                let k: U = bincode::deserialize(item_k)?;
+                if range.contains(&k) {
+                    let v: DbObject<Vec<V>> = bincode::deserialize(item_v)?;
+                    btable.insert(k, v.item);
+                }
+            }
+        }
+
+        let headers = headers.ok_or(MemoryError::DbTableError(
+            DbTableError::DbHeaderDoesNotExist,
+        ))?;
+        for row in btable.values() {
+            check_row_length(&headers.item, row).map_err(MemoryError::DbTableError)?;
+        }
+        let mut table = Table::new_btreemap(headers.item, btable);
+        if let Some(x) = metadata.item {
+            table.set_meta_data(x)
+        }
+        Ok(table)
+    }
+
+    /// Saves the table, the store stays locked from the existence check to the last row.
+    pub fn save_memory(&self, config: &MemoryConfig) -> Result<(), MemoryError> {
+        let mut entries = config.entries();
+        if config.table_exists_in(&entries)? {
+            return Err(MemoryError::DbTableError(DbTableError::DbExists));
+        }
+        self.save_memory_in(config, &mut entries)
+    }
+
+    fn save_memory_in(
+        &self,
+        config: &MemoryConfig,
+        entries: &mut Entries,
+    ) -> Result<(), MemoryError> {
+        entries.insert(
+            config.key(bincode::serialize("__HEADER")?),
+            bincode::serialize(&DbObject::new(self.headers.clone()))?,
+        );
+        entries.insert(
+            config.key(bincode::serialize("__META_DATA")?),
+            bincode::serialize(&DbObject::new(self.meta_data.clone()))?,
+        );
+
+        for (k, v) in self.iter() {
+            let tmp = DbObject::new(v.clone());
+            entries.insert(
+                config.key(bincode::serialize(k)?),
+                bincode::serialize(&tmp)?,
+            );
+        }
+        Ok(())
+    }
+
+    /// Writes the rows whose hash changed and deletes the rows that are no
+    /// longer in the table. Returns the keys that were inserted, updated and deleted.
+    ///
+    /// Fails with `DbTableError::DbHeadersChanged` when the headers changed,
+    /// see `update_memory_with` for the other options.
+    pub fn update_memory(&self, config: &MemoryConfig) -> Result<DbChanges<U>, MemoryError> {
+        self.update_memory_with(config, &HeaderChange::Reject)
+    }
+
+    /// Like `update_memory`, with `on_change` deciding what happens when the headers changed.
+    pub fn update_memory_with(
+        &self,
+        config: &MemoryConfig,
+        on_change: &HeaderChange<V>,
+    ) -> Result<DbChanges<U>, MemoryError> {
+        // held for the whole update, so no other writer can interleave
+        let mut entries = config.entries();
+        if !config.table_exists_in(&entries)? {
+            return Err(MemoryError::DbTableError(DbTableError::DbDoesNotExist));
+        }
+
+        let stored_headers = config.stored_headers(&entries)?;
+        if stored_headers != self.headers {
+            match on_change {
+                HeaderChange::Reject => {
+                    return Err(MemoryError::DbTableError(DbTableError::DbHeadersChanged(
+                        stored_headers,
+                    )))
+                }
+                // every stored row is overwritten or deleted below
+                HeaderChange::Rewrite(_) => (),
+                HeaderChange::Version => {
+                    config.move_to_version(&mut entries)?;
+                    self.save_memory_in(config, &mut entries)?;
+                    let mut changes = DbChanges::new();
+                    changes.inserted = self.keys().cloned().collect();
+                    return Ok(changes);
+                }
+            }
+        }
+
+        let prefix = config.prefix();
+        let header_key = config.key(bincode::serialize("__HEADER")?);
+        let meta_key = config.key(bincode::serialize("__META_DATA")?);
+        let mut changes = DbChanges::new();
+
+        entries.insert(
+            header_key.clone(),
+            bincode::serialize(&DbObject::new(self.headers.clone()))?,
+        );
+        entries.insert(
+            meta_key.clone(),
+            bincode::serialize(&DbObject::new(self.meta_data.clone()))?,
+        );
+
+        let mut stale: HashSet<Vec<u8>> = entries
+            .keys()
+            .filter(|k| k.starts_with(&prefix) && **k != header_key && **k != meta_key)
+            .cloned()
+            .collect();
+
+        for (k, v) in self.iter() {
+            let tmp = DbObject::new(v.clone());
+            let key = config.key(bincode::serialize(k)?);
+            match entries.get(&key) {
+                Some(x) => {
+                    let db_object: DbObject<Vec<V>> = bincode::deserialize(x)?;
+                    if tmp != db_object {
+                        entries.insert(key.clone(), bincode::serialize(&tmp)?);
+                        changes.updated.push(k.clone());
+                    }
+                    stale.remove(&key);
+                }
+                None => {
+                    entries.insert(key, bincode::serialize(&tmp)?);
+                    changes.inserted.push(k.clone());
+                }
+            }
+        }
+
+        for key in stale {
+            entries.remove(&key);
+            changes
+                .deleted
+                .push(bincode::deserialize(&key[prefix.len()..])?);
+        }
+        changes.deleted.sort();
+        Ok(changes)
+    }
+
+    pub fn delete_memory(&self, config: &MemoryConfig) -> Result<(), MemoryError> {
+        config.delete_keys();
+        Ok(())
+    }
+}
+
+impl<U, V> TableStore<U, V> for MemoryConfig
+where
+    U: std::fmt::Debug + Clone + std::cmp::Ord + serde::de::DeserializeOwned + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    /// the memory store needs no preparation
+    fn init(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    fn save(&self, table: &Table<U, V>) -> Result<(), StoreError> {
+        Ok(table.save_memory(self)?)
+    }
+
+    fn update(&self, table: &Table<U, V>) -> Result<DbChanges<U>, StoreError> {
+        Ok(table.update_memory(self)?)
+    }
+
+    fn load(&self) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::from_memory(self)?)
+    }
+
+    fn load_range(&self, range: (Bound<&U>, Bound<&U>)) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::load_range_memory(self, range)?)
+    }
+
+    fn delete(&self) -> Result<(), StoreError> {
+        self.delete_keys();
+        Ok(())
+    }
+
+    fn exists(&self) -> Result<bool, StoreError> {
+        Ok(self.table_exists()?)
+    }
+}
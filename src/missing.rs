@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+
+use crate::enums::{Axis, FillMethod};
+use crate::Table;
+
+/// Methods for tables where `None` is a missing value,
+/// like the output of [from_map_safe](struct.Table.html#method.from_map_safe).
+impl<U, V> Table<U, Option<V>>
+where
+    U: std::fmt::Debug + std::cmp::Ord + Clone,
+    V: std::fmt::Debug + Clone,
+{
+    /// number of missing values per column
+    pub fn isna(&self) -> Vec<(String, usize)> {
+        self.fold_columns(0, |x, (_k, v)| if v.is_none() { x + 1 } else { x })
+    }
+
+    /// fills missing values, forward and backward fill leave a gap at the start or end of a column
+    pub fn fillna(&self, fill: FillMethod<V>) -> Table<U, Option<V>> {
+        let mut table = self.clone();
+        match fill {
+            FillMethod::Value(v) => {
+                for cell in table.data.values_mut().flat_map(|row| row.iter_mut()) {
+                    if cell.is_none() {
+                        *cell = Some(v.clone());
+                    }
+                }
+            }
+            FillMethod::Forward => {
+                let mut last: Vec<Option<V>> = vec![None; self.headers.len()];
+                for row in table.data.values_mut() {
+                    Self::fill_row(row, &mut last);
+                }
+            }
+            FillMethod::Backward => {
+                let mut last: Vec<Option<V>> = vec![None; self.headers.len()];
+                for row in table.data.values_mut().rev() {
+                    Self::fill_row(row, &mut last);
+                }
+            }
+        }
+        table
+    }
+
+    fn fill_row(row: &mut [Option<V>], last: &mut [Option<V>]) {
+        for (cell, previous) in row.iter_mut().zip(last.iter_mut()) {
+            match cell {
+                Some(x) => *previous = Some(x.clone()),
+                None => *cell = previous.clone(),
+            }
+        }
+    }
+
+    /// fills missing values by linear interpolation between the surrounding values of the column,
+    /// the position of the rows is used as distance. Gaps at the start or end of a column are kept.
+    #[cfg(feature = "num")]
+    pub fn interpolate(&self) -> Table<U, Option<V>>
+    where
+        V: num_traits::NumCast,
+    {
+        let mut table = self.clone();
+        let mut rows: Vec<&mut Vec<Option<V>>> = table.data.values_mut().collect();
+
+        for column in 0..self.headers.len() {
+            let mut previous: Option<(usize, V)> = None;
+            for i in 0..rows.len() {
+                let current = match rows[i][column] {
+                    Some(ref x) => x.clone(),
+                    None => continue,
+                };
+                if let Some((start, ref value)) = previous {
+                    // computed in f64 so a decreasing column of an unsigned type can't underflow
+                    let from: Option<f64> = num_traits::cast(value.clone());
+                    let to: Option<f64> = num_traits::cast(current.clone());
+                    if let (Some(from), Some(to)) = (from, to) {
+                        let steps = (i - start) as f64;
+                        for (j, row) in rows.iter_mut().enumerate().take(i).skip(start + 1) {
+                            let offset = (j - start) as f64;
+                            row[column] = num_traits::cast(from + (to - from) * offset / steps);
+                        }
+                    }
+                }
+                previous = Some((i, current));
+            }
+        }
+        table
+    }
+
+    /// removes every row or column that has a missing value
+    pub fn dropna(&self, axis: Axis) -> Table<U, V> {
+        let mut table = match axis {
+            Axis::Row => {
+                let data: BTreeMap<U, Vec<V>> = self
+                    .data
+                    .iter()
+                    .filter_map(|(k, row)| {
+                        Some((k.clone(), row.iter().cloned().collect::<Option<Vec<V>>>()?))
+                    })
+                    .collect();
+                Table::new_btreemap(self.headers.clone(), data)
+            }
+            Axis::Column => {
+                let keep: Vec<bool> = self.isna().iter().map(|(_, count)| *count == 0).collect();
+                let headers = self
+                    .headers
+                    .iter()
+                    .zip(&keep)
+                    .filter(|(_, keep)| **keep)
+                    .map(|(h, _)| h.clone())
+                    .collect();
+                let data: BTreeMap<U, Vec<V>> = self
+                    .data
+                    .iter()
+                    .map(|(k, row)| {
+                        let values = row
+                            .iter()
+                            .zip(&keep)
+                            .filter(|(_, keep)| **keep)
+                            .filter_map(|(v, _)| v.clone())
+                            .collect();
+                        (k.clone(), values)
+                    })
+                    .collect();
+                Table::new_btreemap(headers, data)
+            }
+        };
+        table.meta_data = self.meta_data.clone();
+        table
+    }
+
+    /// sum of the columns, missing values are skipped
+    pub fn sum_columns_skipna(&self) -> Vec<(String, V)>
+    where
+        V: std::ops::Add + Default + From<<V as std::ops::Add>::Output>,
+    {
+        self.fold_columns(V::default(), |x, (_k, v)| match v {
+            Some(v) => V::from(x + v.clone()),
+            None => x,
+        })
+    }
+
+    /// average of the columns, missing values are skipped.
+    /// Columns without values are None
+    #[cfg(feature = "num")]
+    pub fn avg_columns_skipna(&self) -> Vec<(String, Option<V>)>
+    where
+        V: num_traits::Num + num_traits::NumCast + Default + From<<V as std::ops::Add>::Output>,
+    {
+        self.sum_columns_skipna()
+            .into_iter()
+            .zip(self.isna())
+            .map(|((k, sum), (_, missing))| {
+                let len: Option<V> = match self.data.len() - missing {
+                    0 => None,
+                    x => num_traits::cast(x),
+                };
+                (k, len.map(|len| sum / len))
+            })
+            .collect()
+    }
+
+    /// variance of the columns, missing values are skipped.
+    /// Columns without values are None
+    #[cfg(feature = "num")]
+    pub fn var_columns_skipna(&self) -> Vec<(String, Option<V>)>
+    where
+        V: num_traits::Num + num_traits::NumCast + Default + From<<V as std::ops::Add>::Output>,
+    {
+        let mut t = Vec::new();
+        for (p, (header, avg)) in self.iter_columns().zip(self.avg_columns_skipna()) {
+            let avg = match avg {
+                Some(x) => x,
+                None => {
+                    t.push((header, None));
+                    continue;
+                }
+            };
+            let mut result = V::zero();
+            let mut len = 0;
+            for v in p.filter_map(|(_, v)| v.as_ref()) {
+                let s = v.to_owned() - avg.to_owned();
+                result = result + s.clone() * s;
+                len += 1;
+            }
+            t.push((header, num_traits::cast(len).map(|len: V| result / len)));
+        }
+        t
+    }
+}
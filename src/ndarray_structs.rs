@@ -47,7 +47,7 @@ where
 
         let mut tree_data: BTreeMap<U, ndarray::Array<V, <D as ndarray::Dimension>::Smaller>> =
             BTreeMap::new();
-        if data.len_of(ndarray::Axis { 0: 0 }) != time_data.len() {
+        if data.len_of(ndarray::Axis(0)) != time_data.len() {
             return Err(TableError::new("time and data length should be equal"));
         }
 
@@ -1,13 +1,27 @@
 #![cfg(feature = "postgresql_db")]
-use crate::{BtreeMapTrait, DbObject, DbTableError, Table, TableMetaTrait};
-use postgres::types::ToSql;
-use std::collections::{BTreeMap, HashMap};
+use crate::connection::{ConnectionGuard, ReusedConnection};
+use crate::db_structs::{check_row_length, ordered_bounds, quote_identifier, rewrite_row};
+use crate::enums::DuplicatePolicy;
+use crate::{
+    BtreeMapTrait, DbChanges, DbObject, DbTableError, HeaderChange, OrderedKey, StoreError, Table,
+    TableError, TableMetaTrait, TableStore,
+};
+use postgres::binary_copy::BinaryCopyInWriter;
+use postgres::tls::{MakeTlsConnect, TlsConnect};
+use postgres::types::{FromSql, ToSql, Type};
+use postgres::{Client, GenericClient, NoTls, Row, Socket};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::iter;
+use std::ops::{Bound, RangeBounds};
 
 #[derive(Debug)]
 pub enum PostgresqlError {
     Bincode(std::boxed::Box<bincode::ErrorKind>),
     DbTableError(DbTableError),
     Postgres(postgres::Error),
+    /// the SQL table has no column with this name
+    ColumnDoesNotExist(String),
+    Table(TableError),
 }
 
 impl std::fmt::Display for PostgresqlError {
@@ -28,184 +42,733 @@ impl From<std::boxed::Box<bincode::ErrorKind>> for PostgresqlError {
     }
 }
 
-#[derive(Debug)]
-pub struct PostgresConfig<'a> {
-    pub config: postgres::params::ConnectParams,
-    pub tls: postgres::TlsMode<'a>,
+impl From<TableError> for PostgresqlError {
+    fn from(err: TableError) -> PostgresqlError {
+        PostgresqlError::Table(err)
+    }
 }
 
-impl<'a> PostgresConfig<'a> {
-    pub fn new<S>(
-        str_config: S,
-        tls_config: postgres::TlsMode<'a>,
-    ) -> Result<PostgresConfig, Box<std::error::Error + Sync + Send>>
-    where
-        S: postgres::params::IntoConnectParams,
-    {
+/// TLS connectors that can be used to open a postgres connection, e.g. `postgres::NoTls`.
+pub trait PostgresTls:
+    MakeTlsConnect<Socket, Stream: Send, TlsConnect: Send + TlsConnect<Socket, Future: Send>>
+    + Clone
+    + Send
+    + 'static
+{
+}
+
+impl<T> PostgresTls for T where
+    T: MakeTlsConnect<Socket, Stream: Send, TlsConnect: Send + TlsConnect<Socket, Future: Send>>
+        + Clone
+        + Send
+        + 'static
+{
+}
+
+/// Rust types that can be stored in a typed postgres column.
+pub trait PostgresColumn: ToSql + for<'a> FromSql<'a> + Sync {
+    /// type of the column, e.g. `Type::INT8` for `i64`
+    fn sql_type() -> Type;
+}
+
+macro_rules! impl_postgres_column {
+    ($($t:ty => $sql_type:expr),*) => {
+        $(
+            impl PostgresColumn for $t {
+                fn sql_type() -> Type {
+                    $sql_type
+                }
+            }
+        )*
+    };
+}
+
+impl_postgres_column!(
+    bool => Type::BOOL,
+    i16 => Type::INT2,
+    i32 => Type::INT4,
+    i64 => Type::INT8,
+    f32 => Type::FLOAT4,
+    f64 => Type::FLOAT8,
+    String => Type::TEXT
+);
+
+#[cfg(feature = "chrono")]
+impl_postgres_column!(
+    chrono::DateTime<chrono::Utc> => Type::TIMESTAMPTZ,
+    chrono::NaiveDateTime => Type::TIMESTAMP,
+    chrono::NaiveDate => Type::DATE
+);
+
+#[derive(Debug, Clone)]
+pub struct PostgresConfig<T = NoTls> {
+    pub config: tokio_postgres::Config,
+    pub tls: T,
+    /// name of the SQL table the rows are stored in, defaults to `row`
+    pub table: String,
+    /// column that holds the index in the columnar layout, defaults to `index`
+    pub index_column: String,
+    reused: ReusedConnection<Client>,
+}
+
+impl<T: PostgresTls> PostgresConfig<T> {
+    pub fn new(str_config: &str, tls_config: T) -> Result<PostgresConfig<T>, PostgresqlError> {
         Ok(PostgresConfig {
-            config: str_config.into_connect_params()?,
+            config: str_config.parse()?,
             tls: tls_config,
+            table: String::from("row"),
+            index_column: String::from("index"),
+            reused: ReusedConnection::default(),
         })
     }
-}
 
-impl<U, V> Table<U, V>
-where
-    U: std::fmt::Debug + Clone + std::cmp::Ord + serde::de::DeserializeOwned + serde::Serialize,
-    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
-{
-    /// creates tables
-    pub fn init_postgresql(&self, config: PostgresConfig) -> Result<(), PostgresqlError> {
-        let connection = Self::connect_postgresql(config)?;
-        connection.execute(
-            "CREATE TABLE row (
+    /// Stores the table under `name`, so one database can hold many tables.
+    pub fn with_table<S>(mut self, name: S) -> PostgresConfig<T>
+    where
+        S: Into<String>,
+    {
+        self.table = name.into();
+        self
+    }
+
+    /// Uses the column `name` as index in the columnar layout.
+    pub fn with_index_column<S>(mut self, name: S) -> PostgresConfig<T>
+    where
+        S: Into<String>,
+    {
+        self.index_column = name.into();
+        self
+    }
+
+    /// Keeps one connection open and uses it for every call made with this
+    /// config and its clones, instead of opening a connection per call.
+    ///
+    /// A connection closed by the server is replaced on the next call. The
+    /// async methods still open their own connections.
+    pub fn reuse_connection(mut self) -> PostgresConfig<T> {
+        self.reused = ReusedConnection::lazy();
+        self
+    }
+
+    /// Like `reuse_connection`, with a connection opened by the caller.
+    pub fn with_connection(mut self, connection: Client) -> PostgresConfig<T> {
+        self.reused = ReusedConnection::with(connection);
+        self
+    }
+
+    /// opens a new connection, also when the config keeps one
+    pub fn connect(&self) -> Result<Client, PostgresqlError> {
+        let config = postgres::Config::from(self.config.clone());
+        Ok(config.connect(self.tls.clone())?)
+    }
+
+    /// the kept connection, or a new one when connections are not reused
+    fn connection(&self) -> Result<ConnectionGuard<'_, Client>, PostgresqlError> {
+        self.reused.get(|x| !x.is_closed(), || self.connect())
+    }
+
+    /// Opens a connection for the async methods and spawns its task on the tokio runtime.
+    #[cfg(feature = "async")]
+    pub async fn connect_async(&self) -> Result<tokio_postgres::Client, PostgresqlError> {
+        let (client, connection) = self.config.connect(self.tls.clone()).await?;
+        // errors of the connection task also show up in the calls on the client
+        tokio::spawn(connection);
+        Ok(client)
+    }
+
+    /// Config of an older version of the table, kept by `HeaderChange::Version`.
+    ///
+    /// The version is stored in the SQL table `<table>_v<n>`.
+    pub fn version(&self, n: u32) -> PostgresConfig<T> {
+        self.clone().with_table(format!("{}_v{}", self.table, n))
+    }
+
+    /// creates the table and its index
+    pub(crate) fn create_table(&self) -> Result<(), PostgresqlError> {
+        let mut connection = self.connection()?;
+        self.create_table_on(&mut *connection)
+    }
+
+    fn create_table_on<C: GenericClient>(&self, connection: &mut C) -> Result<(), PostgresqlError> {
+        connection.batch_execute(&self.create_table_sql())?;
+        Ok(())
+    }
+
+    fn create_table_sql(&self) -> String {
+        let table = quote_identifier(&self.table);
+        let index = quote_identifier(&format!("{}_sort_key", self.table));
+        format!(
+            "CREATE TABLE {} (
                     id              SERIAL PRIMARY KEY,
                     key             BYTEA NOT NULL,
                     item            BYTEA NOT NULL,
                     hash            BIGINT NOT NULL,
+                    sort_key        BYTEA,
                     UNIQUE(key)
-                  )",
+                  );
+            CREATE INDEX {} ON {} (sort_key);",
+            table, index, table
+        )
+    }
+
+    fn insert_sql(&self) -> String {
+        format!(
+            "INSERT INTO {} (key, item, hash, sort_key) VALUES ($1, $2, $3, $4)",
+            quote_identifier(&self.table)
+        )
+    }
+
+    /// insert that only overwrites rows whose hash changed
+    fn upsert_sql(&self) -> String {
+        format!(
+            "INSERT INTO {0} (key, item, hash, sort_key) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (key) DO UPDATE
+                SET item = excluded.item, hash = excluded.hash, sort_key = excluded.sort_key
+                WHERE {0}.hash != excluded.hash",
+            quote_identifier(&self.table)
+        )
+    }
+
+    fn keys_sql(&self) -> String {
+        format!("SELECT key FROM {}", quote_identifier(&self.table))
+    }
+
+    fn rows_sql(&self) -> String {
+        format!(
+            "SELECT key, item FROM {} WHERE key = ANY($1)",
+            quote_identifier(&self.table)
+        )
+    }
+
+    fn delete_sql(&self) -> String {
+        format!(
+            "DELETE FROM {} WHERE key = ANY($1)",
+            quote_identifier(&self.table)
+        )
+    }
+
+    fn header_sql(&self) -> String {
+        format!(
+            "SELECT item FROM {} WHERE key = $1",
+            quote_identifier(&self.table)
+        )
+    }
+
+    /// query for the special keys and the rows in `range`, and its parameters
+    fn load_range_sql<U, R>(&self, range: &R) -> (String, Vec<Vec<u8>>)
+    where
+        U: OrderedKey,
+        R: RangeBounds<U>,
+    {
+        let (start, end) = ordered_bounds(range);
+
+        let mut conditions = vec![String::from("sort_key IS NOT NULL")];
+        let mut data: Vec<Vec<u8>> = Vec::new();
+        for (bound, included, excluded) in [(start, ">=", ">"), (end, "<=", "<")] {
+            let (op, x) = match bound {
+                Bound::Included(x) => (included, x),
+                Bound::Excluded(x) => (excluded, x),
+                Bound::Unbounded => continue,
+            };
+            data.push(x);
+            conditions.push(format!("sort_key {} ${}", op, data.len()));
+        }
+
+        let query = format!(
+            "SELECT key, item FROM {} WHERE sort_key IS NULL OR ({})",
+            quote_identifier(&self.table),
+            conditions.join(" AND ")
+        );
+        (query, data)
+    }
+
+    /// Checks if the table exists and if it has the `sort_key` column, tables
+    /// saved before range queries don't.
+    fn sort_key_sql(&self) -> &'static str {
+        "SELECT to_regclass($1) IS NOT NULL, EXISTS (
+            SELECT 1 FROM pg_attribute
+            WHERE attrelid = to_regclass($1) AND attname = 'sort_key' AND NOT attisdropped
+        )"
+    }
+
+    fn add_sort_key_sql(&self) -> String {
+        format!(
+            "ALTER TABLE {0} ADD COLUMN sort_key BYTEA;
+            CREATE INDEX IF NOT EXISTS {1} ON {0} (sort_key);",
+            quote_identifier(&self.table),
+            quote_identifier(&format!("{}_sort_key", self.table)),
+        )
+    }
+
+    fn set_sort_key_sql(&self) -> String {
+        format!(
+            "UPDATE {} SET sort_key = $1 WHERE key = $2",
+            quote_identifier(&self.table)
+        )
+    }
+
+    /// `(created, has sort_key)` of the table
+    fn sort_key_state<C: GenericClient>(
+        &self,
+        connection: &mut C,
+    ) -> Result<(bool, bool), PostgresqlError> {
+        let row = connection.query_one(self.sort_key_sql(), &[&quote_identifier(&self.table)])?;
+        Ok((row.get(0), row.get(1)))
+    }
+
+    /// Adds the `sort_key` column and its index to a table saved before range
+    /// queries and fills it from the stored keys. Other tables are left alone.
+    fn add_sort_key<U, C>(&self, connection: &mut C) -> Result<(), PostgresqlError>
+    where
+        U: OrderedKey + serde::de::DeserializeOwned,
+        C: GenericClient,
+    {
+        if self.sort_key_state(connection)? != (true, false) {
+            return Ok(());
+        }
+        connection.batch_execute(&self.add_sort_key_sql())?;
+        let rows = connection.query(
+            &format!("SELECT key FROM {}", quote_identifier(&self.table)),
             &[],
         )?;
+        let stmt = connection.prepare(&self.set_sort_key_sql())?;
+        for (sort_key, key) in sort_keys::<U>(&rows)? {
+            connection.execute(&stmt, &[&sort_key, &key])?;
+        }
         Ok(())
     }
 
+    /// Renames the table and its index to the first free version and returns its number.
+    fn move_to_version<C: GenericClient>(
+        &self,
+        connection: &mut C,
+    ) -> Result<u32, PostgresqlError> {
+        let mut n = 1;
+        loop {
+            let taken: bool = connection
+                .query_one(
+                    "SELECT to_regclass($1) IS NOT NULL",
+                    &[&quote_identifier(&self.version(n).table)],
+                )?
+                .get(0);
+            if !taken {
+                break;
+            }
+            n += 1;
+        }
+
+        let version = self.version(n);
+        connection.batch_execute(&format!(
+            "ALTER TABLE {} RENAME TO {};
+            ALTER INDEX {} RENAME TO {};",
+            quote_identifier(&self.table),
+            quote_identifier(&version.table),
+            quote_identifier(&format!("{}_sort_key", self.table)),
+            quote_identifier(&format!("{}_sort_key", version.table)),
+        ))?;
+        Ok(n)
+    }
+
+    /// headers of the saved table, `None` when nothing was saved yet
+    fn stored_headers<C: GenericClient>(
+        &self,
+        connection: &mut C,
+    ) -> Result<Option<Vec<String>>, PostgresqlError> {
+        let row = connection.query_opt(&self.header_sql(), &[&bincode::serialize("__HEADER")?])?;
+        parse_headers(row)
+    }
+
+    pub(crate) fn drop_table(&self) -> Result<(), PostgresqlError> {
+        let mut connection = self.connection()?;
+        connection.execute(
+            &format!(
+                "DROP TABLE IF EXISTS {} CASCADE",
+                quote_identifier(&self.table)
+            ),
+            &[],
+        )?;
+        Ok(())
+    }
+
+    /// checks if the table was created and saved before
+    pub(crate) fn table_exists(&self) -> Result<bool, PostgresqlError> {
+        let mut connection = self.connection()?;
+        let created: bool = connection
+            .query_one(
+                "SELECT to_regclass($1) IS NOT NULL",
+                &[&quote_identifier(&self.table)],
+            )?
+            .get(0);
+        if !created {
+            return Ok(false);
+        }
+
+        let count: i64 = connection
+            .query_one(
+                &format!(
+                    "SELECT count(*) FROM {} WHERE key = $1",
+                    quote_identifier(&self.table)
+                ),
+                &[&bincode::serialize("__HEADER")?],
+            )?
+            .get(0);
+        Ok(count > 0)
+    }
+}
+
+/// one stored row: key, item, hash and sort key
+type PostgresRecord = (Vec<u8>, Vec<u8>, i64, Option<Vec<u8>>);
+
+fn record_params(record: &PostgresRecord) -> [&(dyn ToSql + Sync); 4] {
+    [&record.0, &record.1, &record.2, &record.3]
+}
+
+/// sort key and key of a stored row
+type SortKey = (Vec<u8>, Vec<u8>);
+
+/// sort keys of the stored rows, the special keys have no sort key
+fn sort_keys<U>(rows: &[Row]) -> Result<Vec<SortKey>, PostgresqlError>
+where
+    U: OrderedKey + serde::de::DeserializeOwned,
+{
+    let header_key = bincode::serialize("__HEADER")?;
+    let meta_key = bincode::serialize("__META_DATA")?;
+    let mut sort_keys = Vec::with_capacity(rows.len());
+    for row in rows {
+        let key: Vec<u8> = row.get(0);
+        if key == header_key || key == meta_key {
+            continue;
+        }
+        let k: U = bincode::deserialize(&key)?;
+        sort_keys.push((k.ordered_key(), key));
+    }
+    Ok(sort_keys)
+}
+
+/// reads the headers from the result of `header_sql`
+fn parse_headers(row: Option<Row>) -> Result<Option<Vec<String>>, PostgresqlError> {
+    Ok(match row {
+        Some(x) => Some(bincode::deserialize(x.get(0))?),
+        None => None,
+    })
+}
+
+impl<T, U, V> TableStore<U, V> for PostgresConfig<T>
+where
+    T: PostgresTls,
+    U: std::fmt::Debug
+        + Clone
+        + std::cmp::Ord
+        + OrderedKey
+        + serde::de::DeserializeOwned
+        + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    fn init(&self) -> Result<(), StoreError> {
+        Ok(self.create_table()?)
+    }
+
+    fn save(&self, table: &Table<U, V>) -> Result<(), StoreError> {
+        Ok(table.save_postgresql(self)?)
+    }
+
+    fn update(&self, table: &Table<U, V>) -> Result<DbChanges<U>, StoreError> {
+        Ok(table.sync_postgresql(self)?)
+    }
+
+    fn load(&self) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::from_postgresql(self)?)
+    }
+
+    fn load_range(&self, range: (Bound<&U>, Bound<&U>)) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::load_range_postgresql(self, range)?)
+    }
+
+    fn delete(&self) -> Result<(), StoreError> {
+        Ok(self.drop_table()?)
+    }
+
+    fn exists(&self) -> Result<bool, StoreError> {
+        Ok(self.table_exists()?)
+    }
+}
+
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug
+        + Clone
+        + std::cmp::Ord
+        + OrderedKey
+        + serde::de::DeserializeOwned
+        + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    /// creates tables
+    pub fn init_postgresql<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<(), PostgresqlError> {
+        config.create_table()
+    }
+
     /// removes all data and tables
-    pub fn uninit_postgresql(&self, config: PostgresConfig) -> Result<(), PostgresqlError> {
-        let connection = Self::connect_postgresql(config)?;
-        connection.execute("DROP TABLE IF EXISTS row CASCADE", &[])?;
+    pub fn uninit_postgresql<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<(), PostgresqlError> {
+        config.drop_table()
+    }
+
+    pub fn connect_postgresql<T: PostgresTls>(
+        config: &PostgresConfig<T>,
+    ) -> Result<Client, PostgresqlError> {
+        config.connect()
+    }
+
+    pub fn save_postgresql<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<(), PostgresqlError> {
+        let mut connection = config.connection()?;
+        let mut transaction = connection.transaction()?;
+        if config.stored_headers(&mut transaction)?.is_some() {
+            return Err(PostgresqlError::DbTableError(DbTableError::DbExists));
+        }
+        config.add_sort_key::<U, _>(&mut transaction)?;
+        self.save_postgresql_on(config, &mut transaction)?;
+        transaction.commit()?;
         Ok(())
     }
 
-    pub fn connect_postgresql(
-        config: PostgresConfig,
-    ) -> Result<postgres::Connection, PostgresqlError> {
-        Ok(postgres::Connection::connect(config.config, config.tls)?)
+    fn save_postgresql_on<T, C>(
+        &self,
+        config: &PostgresConfig<T>,
+        transaction: &mut C,
+    ) -> Result<(), PostgresqlError>
+    where
+        T: PostgresTls,
+        C: GenericClient,
+    {
+        let stmt = transaction.prepare(&config.insert_sql())?;
+        for record in self.postgresql_records()? {
+            transaction.execute(&stmt, &record_params(&record))?;
+        }
+        Ok(())
     }
 
-    pub fn save_postgresql(&self, config: PostgresConfig) -> Result<(), PostgresqlError> {
-        let connection = Self::connect_postgresql(config)?;
-        let stmt = connection.prepare("INSERT INTO row (key, item, hash) VALUES ($1, $2, $3)")?;
+    /// encodes the headers, the meta data and the rows the way they are stored
+    fn postgresql_records(&self) -> Result<Vec<PostgresRecord>, PostgresqlError> {
+        let mut records = Vec::with_capacity(self.data.len() + 2);
 
         let tmp = DbObject::new(self.headers.clone());
-        let data: &[&ToSql] = &[
-            &bincode::serialize("__HEADER")?,
-            &bincode::serialize(&tmp.item)?,
-            &(tmp.hash as i64),
-        ];
-        stmt.execute(data)?;
+        records.push((
+            bincode::serialize("__HEADER")?,
+            bincode::serialize(&tmp.item)?,
+            tmp.hash as i64,
+            None,
+        ));
 
         let tmp = DbObject::new(self.meta_data.clone());
-        let data: &[&ToSql] = &[
-            &bincode::serialize("__META_DATA")?,
-            &bincode::serialize(&tmp.item)?,
-            &(tmp.hash as i64),
-        ];
-        stmt.execute(data)?;
+        records.push((
+            bincode::serialize("__META_DATA")?,
+            bincode::serialize(&tmp.item)?,
+            tmp.hash as i64,
+            None,
+        ));
 
         for (k, v) in self.iter() {
             let tmp = DbObject::new(v.clone());
-            let data: &[&ToSql] = &[
-                &bincode::serialize(k)?,
-                &bincode::serialize(&tmp.item)?,
-                &(tmp.hash as i64),
-            ];
-            stmt.execute(data)?;
+            records.push((
+                bincode::serialize(k)?,
+                bincode::serialize(&tmp.item)?,
+                tmp.hash as i64,
+                Some(k.ordered_key()),
+            ));
         }
-        Ok(())
+        Ok(records)
     }
 
-    pub fn update_postgresql(&self, config: PostgresConfig) -> Result<(), PostgresqlError> {
-        let connection = Self::connect_postgresql(config)?;
-        let insert_stmt =
-            connection.prepare("INSERT INTO row (key, item, hash) VALUES ($1, $2, $3)")?;
-        let update_stmt =
-            connection.prepare("UPDATE row SET item = $2, hash = $3 WHERE key = $1")?;
-        let count_stmt =
-            connection.prepare("SELECT count(*) from row WHERE key = $1 AND hash = $2")?;
-        let exist_stmt = connection.prepare("SELECT count(*) from row WHERE key = $1")?;
+    /// Writes the rows whose hash changed inside a single transaction. Stored
+    /// rows that are not in the table are kept, use `sync_postgresql` to delete
+    /// them. Returns the keys that were inserted and updated.
+    ///
+    /// Fails with `DbTableError::DbDoesNotExist` when no table was saved and with
+    /// `DbTableError::DbHeadersChanged` when the headers changed, see
+    /// `update_postgresql_with` for the other options.
+    pub fn update_postgresql<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<DbChanges<U>, PostgresqlError> {
+        self.update_postgresql_with(config, &HeaderChange::Reject)
+    }
 
-        let tmp = DbObject::new(self.headers.clone());
-        let data: &[&ToSql] = &[
-            &bincode::serialize("__HEADER")?,
-            &bincode::serialize(&tmp.item)?,
-            &(tmp.hash as i64),
-        ];
-
-        for row in &count_stmt.query(&[data[0], data[2]])? {
-            if row.get::<_, i64>("count") == 0 {
-                for row in &exist_stmt.query(&[data[0]])? {
-                    match row.get::<_, i64>("count") {
-                        0 => {
-                            insert_stmt.execute(data)?;
-                        }
-                        1 => {
-                            update_stmt.execute(data)?;
-                        }
-                        _ => unreachable!(),
-                    }
+    /// Like `update_postgresql`, with `on_change` deciding what happens when the headers changed.
+    pub fn update_postgresql_with<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+        on_change: &HeaderChange<V>,
+    ) -> Result<DbChanges<U>, PostgresqlError> {
+        self.write_postgresql(config, on_change, false)
+    }
+
+    /// Like `update_postgresql`, but also deletes the stored rows that are no
+    /// longer in the table, so the stored table equals this table afterwards.
+    /// Returns the keys that were inserted, updated and deleted.
+    pub fn sync_postgresql<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<DbChanges<U>, PostgresqlError> {
+        self.write_postgresql(config, &HeaderChange::Reject, true)
+    }
+
+    fn write_postgresql<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+        on_change: &HeaderChange<V>,
+        delete_stale: bool,
+    ) -> Result<DbChanges<U>, PostgresqlError> {
+        let mut connection = config.connection()?;
+        let mut transaction = connection.transaction()?;
+        config.add_sort_key::<U, _>(&mut transaction)?;
+        // the stored headers and fill value when the stored rows that are kept need a rewrite
+        let mut rewrite = None;
+
+        let stored_headers = config
+            .stored_headers(&mut transaction)?
+            .ok_or(PostgresqlError::DbTableError(DbTableError::DbDoesNotExist))?;
+        if stored_headers != self.headers {
+            match on_change {
+                HeaderChange::Reject => {
+                    return Err(PostgresqlError::DbTableError(
+                        DbTableError::DbHeadersChanged(stored_headers),
+                    ))
+                }
+                HeaderChange::Rewrite(fill) => rewrite = Some((stored_headers, fill)),
+                HeaderChange::Version => {
+                    config.move_to_version(&mut transaction)?;
+                    config.create_table_on(&mut transaction)?;
+                    self.save_postgresql_on(config, &mut transaction)?;
+                    transaction.commit()?;
+                    let mut changes = DbChanges::new();
+                    changes.inserted = self.keys().cloned().collect();
+                    return Ok(changes);
                 }
             }
         }
 
-        let tmp = DbObject::new(self.meta_data.clone());
-        let data: &[&ToSql] = &[
-            &bincode::serialize("__META_DATA")?,
-            &bincode::serialize(&tmp.item)?,
-            &(tmp.hash as i64),
-        ];
-
-        for row in &count_stmt.query(&[data[0], data[2]])? {
-            if row.get::<_, i64>("count") == 0 {
-                for row in &exist_stmt.query(&[data[0]])? {
-                    match row.get::<_, i64>("count") {
-                        0 => {
-                            insert_stmt.execute(data)?;
-                        }
-                        1 => {
-                            update_stmt.execute(data)?;
-                        }
-                        _ => unreachable!(),
-                    }
-                }
+        let stored = transaction
+            .query(&config.keys_sql(), &[])?
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+        let records = self.postgresql_records()?;
+        let stmt = transaction.prepare(&config.upsert_sql())?;
+        let mut written = Vec::with_capacity(records.len());
+        for record in &records {
+            written.push(transaction.execute(&stmt, &record_params(record))?);
+        }
+        let (mut changes, stale) =
+            self.postgresql_changes(&records, &written, stored, delete_stale)?;
+        if delete_stale {
+            transaction.execute(&config.delete_sql(), &[&stale])?;
+        } else if let Some((from, fill)) = rewrite {
+            for row in transaction.query(&config.rows_sql(), &[&stale])? {
+                let k: U = bincode::deserialize(row.get(0))?;
+                let item: Vec<V> = bincode::deserialize(row.get(1))?;
+                let tmp = DbObject::new(rewrite_row(&item, &from, &self.headers, fill));
+                let record = (
+                    row.get(0),
+                    bincode::serialize(&tmp.item)?,
+                    tmp.hash as i64,
+                    Some(k.ordered_key()),
+                );
+                transaction.execute(&stmt, &record_params(&record))?;
+                changes.updated.push(k);
             }
+            changes.updated.sort();
         }
+        transaction.commit()?;
+        Ok(changes)
+    }
 
-        for (k, v) in self.iter() {
-            let tmp = DbObject::new(v.clone());
-            let data: &[&ToSql] = &[
-                &bincode::serialize(k)?,
-                &bincode::serialize(&tmp.item)?,
-                &(tmp.hash as i64),
-            ];
-
-            for row in &count_stmt.query(&[data[0], data[2]])? {
-                if row.get::<_, i64>("count") == 0 {
-                    for row in &exist_stmt.query(&[data[0]])? {
-                        match row.get::<_, i64>("count") {
-                            0 => {
-                                insert_stmt.execute(data)?;
-                            }
-                            1 => {
-                                update_stmt.execute(data)?;
-                            }
-                            _ => unreachable!(),
-                        }
-                    }
-                }
+    /// Sorts the rows of the table into inserted and updated, by the keys
+    /// `stored` before the update and the number of rows each record `written`.
+    /// Returns the changes and the stored keys that are no longer in the table,
+    /// which count as deleted when `delete_stale` is set.
+    fn postgresql_changes(
+        &self,
+        records: &[PostgresRecord],
+        written: &[u64],
+        mut stored: HashSet<Vec<u8>>,
+        delete_stale: bool,
+    ) -> Result<(DbChanges<U>, Vec<Vec<u8>>), PostgresqlError> {
+        let mut changes = DbChanges::new();
+        // the first two records are the headers and the meta data
+        for record in records.iter().take(2) {
+            stored.remove(&record.0);
+        }
+        for ((record, written), k) in records.iter().zip(written).skip(2).zip(self.keys()) {
+            if !stored.remove(&record.0) {
+                changes.inserted.push(k.clone());
+            } else if *written > 0 {
+                changes.updated.push(k.clone());
             }
         }
-        Ok(())
+
+        let stale: Vec<Vec<u8>> = stored.into_iter().collect();
+        if delete_stale {
+            for key in &stale {
+                changes.deleted.push(bincode::deserialize(key)?);
+            }
+            changes.deleted.sort();
+        }
+        Ok((changes, stale))
     }
 
-    pub fn from_postgresql(config: PostgresConfig) -> Result<Table<U, V>, PostgresqlError> {
-        let connection = Self::connect_postgresql(config)?;
+    pub fn from_postgresql<T: PostgresTls>(
+        config: &PostgresConfig<T>,
+    ) -> Result<Table<U, V>, PostgresqlError> {
+        let mut connection = config.connection()?;
+        let rows = connection.query(
+            &format!("SELECT key, item FROM {}", quote_identifier(&config.table)),
+            &[],
+        )?;
+        Self::from_postgresql_rows(&rows)
+    }
 
+    /// Loads only the rows whose index falls in `range`.
+    ///
+    /// The range is compared against the `sort_key` column, so the filtering
+    /// happens inside Postgres. A table saved before range queries has no such
+    /// column until the next save or update, it is loaded whole and filtered in memory.
+    pub fn load_range_postgresql<T, R>(
+        config: &PostgresConfig<T>,
+        range: R,
+    ) -> Result<Table<U, V>, PostgresqlError>
+    where
+        T: PostgresTls,
+        R: RangeBounds<U>,
+    {
+        let mut connection = config.connection()?;
+        if !config.sort_key_state(&mut *connection)?.1 {
+            drop(connection);
+            let mut table = Self::from_postgresql(config)?;
+            table.data.retain(|k, _| range.contains(k));
+            return Ok(table);
+        }
+        let (query, data) = config.load_range_sql(&range);
+        let params: Vec<&(dyn ToSql + Sync)> =
+            data.iter().map(|x| x as &(dyn ToSql + Sync)).collect();
+        let rows = connection.query(&query, &params)?;
+        Self::from_postgresql_rows(&rows)
+    }
+
+    fn from_postgresql_rows(rows: &[Row]) -> Result<Table<U, V>, PostgresqlError> {
         let mut headers: Vec<String> = Vec::new();
         let mut metadata: Option<HashMap<String, String>> = None;
         let mut btable = BTreeMap::new();
 
-        for row in &connection.query("SELECT key, item FROM row", &[])? {
+        for row in rows {
             let item_k: Vec<u8> = row.get(0);
             let item_v: Vec<u8> = row.get(1);
             let mut n = true;
@@ -224,7 +787,6 @@ where
             if n {
                 let k: U = bincode::deserialize(&item_k)?;
                 let v: Vec<V> = bincode::deserialize(&item_v)?;
-                // let v: Vec<V> = bincode::deserialize(&item_v)?;
                 btable.insert(k, v);
             }
         }
@@ -233,6 +795,9 @@ where
                 DbTableError::DbHeaderDoesNotExist,
             ));
         }
+        for row in btable.values() {
+            check_row_length(&headers, row).map_err(PostgresqlError::DbTableError)?;
+        }
 
         let mut table = Table::new_btreemap(headers, btable);
         if let Some(x) = metadata {
@@ -241,3 +806,293 @@ where
         Ok(table)
     }
 }
+
+/// Async versions of the blob layout methods, for use inside a tokio runtime.
+#[cfg(feature = "async")]
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug
+        + Clone
+        + std::cmp::Ord
+        + OrderedKey
+        + serde::de::DeserializeOwned
+        + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    /// creates tables
+    pub async fn init_postgresql_async<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<(), PostgresqlError> {
+        let client = config.connect_async().await?;
+        client.batch_execute(&config.create_table_sql()).await?;
+        Ok(())
+    }
+
+    pub async fn save_postgresql_async<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<(), PostgresqlError> {
+        let records = self.postgresql_records()?;
+        let mut client = config.connect_async().await?;
+        let transaction = client.transaction().await?;
+        let row = transaction
+            .query_opt(&config.header_sql(), &[&bincode::serialize("__HEADER")?])
+            .await?;
+        if row.is_some() {
+            return Err(PostgresqlError::DbTableError(DbTableError::DbExists));
+        }
+        Self::add_sort_key_async(config, &transaction).await?;
+        let stmt = transaction.prepare(&config.insert_sql()).await?;
+        for record in &records {
+            transaction.execute(&stmt, &record_params(record)).await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Writes the rows whose hash changed inside a single transaction, stored
+    /// rows that are not in the table are kept. Returns the keys that were
+    /// inserted and updated.
+    ///
+    /// Fails with `DbTableError::DbDoesNotExist` when no table was saved and with
+    /// `DbTableError::DbHeadersChanged` when the headers changed,
+    /// `update_postgresql_with` can migrate the stored table.
+    pub async fn update_postgresql_async<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<DbChanges<U>, PostgresqlError> {
+        self.write_postgresql_async(config, false).await
+    }
+
+    /// Like `update_postgresql_async`, but also deletes the stored rows that are no longer in the table.
+    pub async fn sync_postgresql_async<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<DbChanges<U>, PostgresqlError> {
+        self.write_postgresql_async(config, true).await
+    }
+
+    async fn write_postgresql_async<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+        delete_stale: bool,
+    ) -> Result<DbChanges<U>, PostgresqlError> {
+        let records = self.postgresql_records()?;
+        let mut client = config.connect_async().await?;
+        let transaction = client.transaction().await?;
+        Self::add_sort_key_async(config, &transaction).await?;
+
+        let row = transaction
+            .query_opt(&config.header_sql(), &[&bincode::serialize("__HEADER")?])
+            .await?;
+        let stored_headers = parse_headers(row)?
+            .ok_or(PostgresqlError::DbTableError(DbTableError::DbDoesNotExist))?;
+        if stored_headers != self.headers {
+            return Err(PostgresqlError::DbTableError(
+                DbTableError::DbHeadersChanged(stored_headers),
+            ));
+        }
+
+        let stored = transaction
+            .query(&config.keys_sql(), &[])
+            .await?
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+        let stmt = transaction.prepare(&config.upsert_sql()).await?;
+        let mut written = Vec::with_capacity(records.len());
+        for record in &records {
+            written.push(transaction.execute(&stmt, &record_params(record)).await?);
+        }
+        let (changes, stale) = self.postgresql_changes(&records, &written, stored, delete_stale)?;
+        if delete_stale {
+            transaction.execute(&config.delete_sql(), &[&stale]).await?;
+        }
+        transaction.commit().await?;
+        Ok(changes)
+    }
+
+    pub async fn from_postgresql_async<T: PostgresTls>(
+        config: &PostgresConfig<T>,
+    ) -> Result<Table<U, V>, PostgresqlError> {
+        Self::load_range_postgresql_async(config, ..).await
+    }
+
+    /// Loads only the rows whose index falls in `range`.
+    pub async fn load_range_postgresql_async<T, R>(
+        config: &PostgresConfig<T>,
+        range: R,
+    ) -> Result<Table<U, V>, PostgresqlError>
+    where
+        T: PostgresTls,
+        R: RangeBounds<U>,
+    {
+        let client = config.connect_async().await?;
+        let row = client
+            .query_one(config.sort_key_sql(), &[&quote_identifier(&config.table)])
+            .await?;
+        let (query, data) = if row.get(1) {
+            config.load_range_sql(&range)
+        } else {
+            // saved before range queries, filtered in memory
+            (
+                format!("SELECT key, item FROM {}", quote_identifier(&config.table)),
+                Vec::new(),
+            )
+        };
+        let params: Vec<&(dyn ToSql + Sync)> =
+            data.iter().map(|x| x as &(dyn ToSql + Sync)).collect();
+        let rows = client.query(&query, &params).await?;
+        let mut table = Self::from_postgresql_rows(&rows)?;
+        table.data.retain(|k, _| range.contains(k));
+        Ok(table)
+    }
+
+    /// async version of `PostgresConfig::add_sort_key`
+    async fn add_sort_key_async<T: PostgresTls>(
+        config: &PostgresConfig<T>,
+        transaction: &tokio_postgres::Transaction<'_>,
+    ) -> Result<(), PostgresqlError> {
+        let row = transaction
+            .query_one(config.sort_key_sql(), &[&quote_identifier(&config.table)])
+            .await?;
+        if (row.get(0), row.get(1)) != (true, false) {
+            return Ok(());
+        }
+        transaction
+            .batch_execute(&config.add_sort_key_sql())
+            .await?;
+        let rows = transaction
+            .query(
+                &format!("SELECT key FROM {}", quote_identifier(&config.table)),
+                &[],
+            )
+            .await?;
+        let stmt = transaction.prepare(&config.set_sort_key_sql()).await?;
+        for (sort_key, key) in sort_keys::<U>(&rows)? {
+            transaction.execute(&stmt, &[&sort_key, &key]).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Columnar layout: a plain SQL table with the index as primary key and one
+/// typed column per header, so the data can be queried with SQL directly.
+/// Meta data is not stored in this layout.
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord + PostgresColumn,
+    V: std::fmt::Debug + PostgresColumn,
+{
+    /// Creates the SQL table `config.table` with a column for the index and every header.
+    pub fn init_postgresql_columns<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<(), PostgresqlError> {
+        let mut columns = vec![format!(
+            "{} {} PRIMARY KEY",
+            quote_identifier(&config.index_column),
+            U::sql_type().name()
+        )];
+        for header in &self.headers {
+            columns.push(format!(
+                "{} {} NOT NULL",
+                quote_identifier(header),
+                V::sql_type().name()
+            ));
+        }
+
+        let mut connection = config.connection()?;
+        connection.batch_execute(&format!(
+            "CREATE TABLE {} ({})",
+            quote_identifier(&config.table),
+            columns.join(", ")
+        ))?;
+        Ok(())
+    }
+
+    /// Writes all rows with a binary `COPY ... FROM STDIN`, inside a single transaction.
+    ///
+    /// The SQL table needs a column for the index and every header, e.g. made by
+    /// `init_postgresql_columns`. Other columns must have a default value.
+    pub fn save_postgresql_columns<T: PostgresTls>(
+        &self,
+        config: &PostgresConfig<T>,
+    ) -> Result<(), PostgresqlError> {
+        let columns: Vec<String> = iter::once(&config.index_column)
+            .chain(self.headers.iter())
+            .map(|x| quote_identifier(x))
+            .collect();
+        let types: Vec<Type> = iter::once(U::sql_type())
+            .chain(iter::repeat_n(V::sql_type(), self.headers.len()))
+            .collect();
+
+        let mut connection = config.connection()?;
+        let mut transaction = connection.transaction()?;
+        let sink = transaction.copy_in(&format!(
+            "COPY {} ({}) FROM STDIN (FORMAT binary)",
+            quote_identifier(&config.table),
+            columns.join(", ")
+        ))?;
+
+        let mut writer = BinaryCopyInWriter::new(sink, &types);
+        for (k, v) in self.iter() {
+            let values: Vec<&(dyn ToSql + Sync)> = iter::once(k as &(dyn ToSql + Sync))
+                .chain(v.iter().map(|x| x as &(dyn ToSql + Sync)))
+                .collect();
+            writer.write(&values)?;
+        }
+        writer.finish()?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Loads the SQL table `config.table`, which does not have to be made by this crate.
+    ///
+    /// `config.index_column` becomes the index and all other columns become
+    /// headers, in the order of the table. Fails with `PostgresqlError::Table`
+    /// listing the index values found in more than one row.
+    pub fn from_postgresql_columns<T: PostgresTls>(
+        config: &PostgresConfig<T>,
+    ) -> Result<Table<U, V>, PostgresqlError> {
+        let mut connection = config.connection()?;
+        let stmt = connection.prepare(&format!(
+            "SELECT * FROM {}",
+            quote_identifier(&config.table)
+        ))?;
+
+        let index = stmt
+            .columns()
+            .iter()
+            .position(|x| x.name() == config.index_column)
+            .ok_or_else(|| PostgresqlError::ColumnDoesNotExist(config.index_column.clone()))?;
+        let headers: Vec<String> = stmt
+            .columns()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, x)| x.name().to_string())
+            .collect();
+
+        let mut btable = BTreeMap::new();
+        let mut collided = Vec::new();
+        for row in connection.query(&stmt, &[])? {
+            let mut values = Vec::with_capacity(headers.len());
+            for i in (0..row.len()).filter(|i| *i != index) {
+                values.push(row.try_get(i)?);
+            }
+            Table::insert_row(
+                &mut btable,
+                row.try_get(index)?,
+                values,
+                &DuplicatePolicy::Error,
+                &mut collided,
+            );
+        }
+        if !collided.is_empty() {
+            return Err(TableError::duplicates(collided).into());
+        }
+        Ok(Table::new_btreemap(headers, btable))
+    }
+}
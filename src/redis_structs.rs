@@ -1,14 +1,24 @@
 #![cfg(feature = "redis_db")]
-use crate::{BtreeMapTrait, DbObject, DbTableError, Table, TableMetaTrait};
+use crate::connection::{ConnectionGuard, ReusedConnection};
+use crate::db_structs::{check_row_length, ordered_bounds, rewrite_row};
+use crate::{
+    BtreeMapTrait, DbChanges, DbObject, DbTableError, HeaderChange, OrderedKey, StoreError, Table,
+    TableMetaTrait, TableStore,
+};
 use std::collections::{BTreeMap, HashMap};
+use std::ops::{Bound, RangeBounds};
 
-use redis::{Client, Commands, Connection, ConnectionInfo, IntoConnectionInfo};
+#[cfg(feature = "async")]
+use redis::AsyncCommands;
+use redis::{Client, Commands, Connection, ConnectionInfo, ConnectionLike, IntoConnectionInfo};
 
 #[derive(Debug)]
 pub enum RedisError {
     Bincode(std::boxed::Box<bincode::ErrorKind>),
     DbTableError(DbTableError),
     Redis(redis::RedisError),
+    /// the database holds a table saved by an older release, `Table::migrate_redis` moves it
+    LegacyLayout,
 }
 
 impl std::fmt::Display for RedisError {
@@ -29,9 +39,46 @@ impl From<std::boxed::Box<bincode::ErrorKind>> for RedisError {
     }
 }
 
+/// An encoded row, stored under the same field in the `__ROWS` and `__HASHES` hashes.
+struct RedisRow {
+    /// the [`OrderedKey`] bytes of the index
+    field: Vec<u8>,
+    /// the index and the row with its hash
+    row: Vec<u8>,
+    /// the index and the hash of the row
+    hash: Vec<u8>,
+}
+
+/// The rows an update writes and the stored rows that are not in the table.
+struct RedisUpdate<U> {
+    rows: Vec<RedisRow>,
+    changes: DbChanges<U>,
+    /// field and index of every stored row that is missing from the table
+    stale: Vec<(Vec<u8>, U)>,
+}
+
+/// rows written per command, keeps single commands small for large tables
+const CHUNK_SIZE: usize = 1000;
+
+/// Connection settings and key names for a table stored in redis.
+///
+/// A table uses four keys: the hash `__TABLE` with the headers and meta data,
+/// the hash `__ROWS` from the encoded index to the row, the hash `__HASHES`
+/// from the encoded index to the hash of the row, which updates compare
+/// without reading the rows, and the sorted set `__INDEX` with the encoded
+/// indexes. All indexes have the same score, so the set is ordered by their
+/// [`OrderedKey`] bytes and ranges are read with ZRANGEBYLEX. Other keys in
+/// the database are left alone.
+///
+/// Older releases stored the headers under a top-level `__HEADER` key and every
+/// row under its own key. Without a namespace, loading, saving and updating
+/// fail with `RedisError::LegacyLayout` while such a table is in the database.
 #[derive(Debug, Clone)]
 pub struct RedisConfig {
     pub info: ConnectionInfo,
+    /// prefix for every key of the table
+    pub namespace: Option<String>,
+    reused: ReusedConnection<Connection>,
 }
 
 impl RedisConfig {
@@ -41,45 +88,267 @@ impl RedisConfig {
     {
         Ok(RedisConfig {
             info: info.into_connection_info()?,
+            namespace: None,
+            reused: ReusedConnection::default(),
+        })
+    }
+
+    /// Prefixes all keys with `name:`, so many tables can share one database.
+    pub fn with_namespace<S>(mut self, name: S) -> RedisConfig
+    where
+        S: Into<String>,
+    {
+        self.namespace = Some(name.into());
+        self
+    }
+
+    /// Config of an older version of the table, kept by `HeaderChange::Version`.
+    ///
+    /// The version lives in the namespace `<namespace>_v<n>`.
+    pub fn version(&self, n: u32) -> RedisConfig {
+        let namespace = self.namespace.as_deref().unwrap_or_default();
+        self.clone().with_namespace(format!("{}_v{}", namespace, n))
+    }
+
+    /// adds the namespace prefix to a key name
+    fn key(&self, name: &str) -> String {
+        match &self.namespace {
+            Some(x) => format!("{}:{}", x, name),
+            None => name.to_string(),
+        }
+    }
+
+    fn table_key(&self) -> String {
+        self.key("__TABLE")
+    }
+
+    fn rows_key(&self) -> String {
+        self.key("__ROWS")
+    }
+
+    fn hashes_key(&self) -> String {
+        self.key("__HASHES")
+    }
+
+    fn index_key(&self) -> String {
+        self.key("__INDEX")
+    }
+
+    /// Keeps one connection open and uses it for every call made with this
+    /// config and its clones, instead of opening a connection per call.
+    ///
+    /// A connection closed by the server is replaced on the next call. The
+    /// async methods still open their own connections.
+    pub fn reuse_connection(mut self) -> RedisConfig {
+        self.reused = ReusedConnection::lazy();
+        self
+    }
+
+    /// Like `reuse_connection`, with a connection opened by the caller.
+    pub fn with_connection(mut self, connection: Connection) -> RedisConfig {
+        self.reused = ReusedConnection::with(connection);
+        self
+    }
+
+    /// opens a new connection, also when the config keeps one
+    pub fn connect(&self) -> Result<Connection, RedisError> {
+        let client = Client::open(self.info.to_owned())?;
+        let con = client.get_connection()?;
+        Ok(con)
+    }
+
+    /// the kept connection, or a new one when connections are not reused
+    fn connection(&self) -> Result<ConnectionGuard<'_, Connection>, RedisError> {
+        self.reused.get(Connection::is_open, || self.connect())
+    }
+
+    /// Opens a connection for the async methods, needs a tokio runtime.
+    #[cfg(feature = "async")]
+    pub async fn connect_async(&self) -> Result<redis::aio::Connection, RedisError> {
+        let client = Client::open(self.info.to_owned())?;
+        Ok(client.get_async_connection().await?)
+    }
+
+    /// removes every key of the table
+    pub(crate) fn delete_keys(&self) -> Result<(), RedisError> {
+        let mut connection = self.connection()?;
+        connection.del::<_, ()>(&[
+            self.table_key(),
+            self.rows_key(),
+            self.hashes_key(),
+            self.index_key(),
+        ])?;
+        Ok(())
+    }
+
+    /// checks if a table was saved before
+    pub(crate) fn table_exists(&self) -> Result<bool, RedisError> {
+        let mut connection = self.connection()?;
+        Ok(connection.hexists(self.table_key(), "__HEADER")?)
+    }
+
+    /// Fails with `RedisError::LegacyLayout` when the database holds a table
+    /// saved by an older release, whose keys never had a namespace.
+    fn check_layout(&self, connection: &mut Connection) -> Result<(), RedisError> {
+        if self.namespace.is_none() && connection.exists(bincode::serialize("__HEADER")?)? {
+            return Err(RedisError::LegacyLayout);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    async fn check_layout_async(
+        &self,
+        connection: &mut redis::aio::Connection,
+    ) -> Result<(), RedisError> {
+        if self.namespace.is_none() && connection.exists(bincode::serialize("__HEADER")?).await? {
+            return Err(RedisError::LegacyLayout);
+        }
+        Ok(())
+    }
+
+    /// headers of the saved table, `None` when nothing was saved yet
+    fn stored_headers(
+        &self,
+        connection: &mut Connection,
+    ) -> Result<Option<Vec<String>>, RedisError> {
+        let item: Option<Vec<u8>> = connection.hget(self.table_key(), "__HEADER")?;
+        Ok(match item {
+            Some(x) => Some(bincode::deserialize::<DbObject<Vec<String>>>(&x)?.item),
+            None => None,
         })
     }
+
+    /// Adds renaming the keys of the table to the first free version to a
+    /// pipeline, and returns the version number.
+    fn move_to_version(
+        &self,
+        connection: &mut Connection,
+        pipe: &mut redis::Pipeline,
+    ) -> Result<u32, RedisError> {
+        let mut n = 1;
+        while connection.hexists(self.version(n).table_key(), "__HEADER")? {
+            n += 1;
+        }
+
+        let version = self.version(n);
+        for (key, version_key) in [
+            (self.table_key(), version.table_key()),
+            (self.rows_key(), version.rows_key()),
+            (self.hashes_key(), version.hashes_key()),
+            (self.index_key(), version.index_key()),
+        ] {
+            // RENAME fails on missing keys, an empty table has no rows and index
+            if connection.exists(&key)? {
+                pipe.rename(key, version_key).ignore();
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Turns an encoded range into the `min` and `max` arguments of ZRANGEBYLEX.
+fn lex_bounds(range: (Bound<Vec<u8>>, Bound<Vec<u8>>)) -> (Vec<u8>, Vec<u8>) {
+    let encode = |bound: Bound<Vec<u8>>, unbounded: u8| match bound {
+        Bound::Included(mut x) => {
+            x.insert(0, b'[');
+            x
+        }
+        Bound::Excluded(mut x) => {
+            x.insert(0, b'(');
+            x
+        }
+        Bound::Unbounded => vec![unbounded],
+    };
+    (encode(range.0, b'-'), encode(range.1, b'+'))
 }
 
 impl<U, V> Table<U, V>
 where
-    U: std::fmt::Debug + Clone + std::cmp::Ord + serde::de::DeserializeOwned + serde::Serialize,
+    U: std::fmt::Debug
+        + Clone
+        + std::cmp::Ord
+        + OrderedKey
+        + serde::de::DeserializeOwned
+        + serde::Serialize,
     V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
 {
     pub fn connect_redis(config: &RedisConfig) -> Result<Connection, RedisError> {
-        let client = Client::open(config.info.to_owned())?;
-        let con = client.get_connection()?;
-        Ok(con)
+        config.connect()
     }
     pub fn from_redis(config: &RedisConfig) -> Result<Table<U, V>, RedisError> {
-        let mut connection = Self::connect_redis(config)?;
-        let mut connection2 = Self::connect_redis(config)?;
+        Self::load_range_redis(config, ..)
+    }
+
+    /// Loads only the rows whose index falls in `range`.
+    ///
+    /// The matching indexes are read from the sorted set together with the
+    /// headers, and the rows are fetched with a single HMGET afterwards.
+    ///
+    /// The range is read with ZRANGEBYLEX rather than ZRANGEBYSCORE. Scores are
+    /// `f64`, which can't hold every key of 8 bytes or more without rounding
+    /// neighbouring keys together, while members with equal scores are ordered
+    /// by their bytes, which the [`OrderedKey`] encoding keeps in index order.
+    pub fn load_range_redis<R>(config: &RedisConfig, range: R) -> Result<Table<U, V>, RedisError>
+    where
+        R: RangeBounds<U>,
+    {
+        let mut connection = config.connection()?;
+        let (table_data, members): (Vec<Option<Vec<u8>>>, Vec<Vec<u8>>) =
+            Self::redis_load_pipeline(config, &range).query(&mut *connection)?;
+        if table_data[0].is_none() {
+            config.check_layout(&mut connection)?;
+        }
+        let rows = if members.is_empty() {
+            Vec::new()
+        } else {
+            Self::redis_rows_cmd(config, members).query(&mut *connection)?
+        };
+        Self::from_redis_parts(table_data, rows)
+    }
+
+    /// pipeline reading the headers, the meta data and the indexes in `range`
+    fn redis_load_pipeline<R>(config: &RedisConfig, range: &R) -> redis::Pipeline
+    where
+        R: RangeBounds<U>,
+    {
+        let (min, max) = lex_bounds(ordered_bounds(range));
+        let mut pipe = redis::pipe();
+        pipe.cmd("HMGET")
+            .arg(config.table_key())
+            .arg("__HEADER")
+            .arg("__META_DATA")
+            .zrangebylex(config.index_key(), min, max);
+        pipe
+    }
+
+    /// command reading the rows of the indexes found by `redis_load_pipeline`
+    fn redis_rows_cmd(config: &RedisConfig, members: Vec<Vec<u8>>) -> redis::Cmd {
+        let mut cmd = redis::cmd("HMGET");
+        cmd.arg(config.rows_key()).arg(members);
+        cmd
+    }
+
+    /// builds the table from the results of `redis_load_pipeline` and `redis_rows_cmd`
+    fn from_redis_parts(
+        table_data: Vec<Option<Vec<u8>>>,
+        rows: Vec<Option<Vec<u8>>>,
+    ) -> Result<Table<U, V>, RedisError> {
+        let headers: DbObject<Vec<String>> = match &table_data[0] {
+            Some(x) => bincode::deserialize(x)?,
+            None => return Err(RedisError::DbTableError(DbTableError::DbHeaderDoesNotExist)),
+        };
+        let meta_data: DbObject<Option<HashMap<String, String>>> = match &table_data[1] {
+            Some(x) => bincode::deserialize(x)?,
+            None => DbObject::new(None),
+        };
+
         let mut btreemap = BTreeMap::new();
-        let mut headers: DbObject<Vec<String>> = DbObject::new(vec![]);
-        let mut meta_data: DbObject<Option<HashMap<String, String>>> = DbObject::new(None);
-
-        for item in connection.scan::<Vec<u8>>()? {
-            let mut n = true;
-            let row_data: Vec<u8> = connection2.get(item.clone())?;
-            if let Ok(x) = bincode::deserialize::<String>(&item) {
-                if x == "__HEADER" {
-                    headers = bincode::deserialize(&row_data)?;
-                    n = false;
-                }
-                if x == "__META_DATA" {
-                    meta_data = bincode::deserialize(&row_data)?;
-                    n = false;
-                }
-            }
-            if n {
-                let key: U = bincode::deserialize(&item)?;
-                let row: DbObject<Vec<V>> = bincode::deserialize(&row_data)?;
-                btreemap.insert(key, row.item);
-            }
+        // rows deleted since the index was read come back as nil
+        for row_data in rows.into_iter().flatten() {
+            let (key, row): (U, DbObject<Vec<V>>) = bincode::deserialize(&row_data)?;
+            check_row_length(&headers.item, &row.item).map_err(RedisError::DbTableError)?;
+            btreemap.insert(key, row.item);
         }
 
         let mut table = Table::new_btreemap(headers.item, btreemap);
@@ -88,53 +357,486 @@ where
         }
         Ok(table)
     }
-    pub fn save_redis(&self, config: &RedisConfig) -> Result<(), RedisError> {
-        let mut connection = Self::connect_redis(config)?;
 
-        let has_header: bool = connection.exists(bincode::serialize("__HEADER")?)?;
-        if has_header {
-            return Err(RedisError::DbTableError(DbTableError::DbExists));
+    /// encodes a row for the `__ROWS` and `__HASHES` hashes
+    fn redis_row(k: &U, v: &[V]) -> Result<RedisRow, RedisError> {
+        let object = DbObject::new(v.to_vec());
+        Ok(RedisRow {
+            field: k.ordered_key(),
+            row: bincode::serialize(&(k, &object))?,
+            hash: bincode::serialize(&(k, object.hash))?,
+        })
+    }
+
+    /// adds writing the headers and meta data to a pipeline
+    fn redis_table_data(
+        &self,
+        config: &RedisConfig,
+        pipe: &mut redis::Pipeline,
+    ) -> Result<(), RedisError> {
+        let items = [
+            (
+                "__HEADER",
+                bincode::serialize(&DbObject::new(self.headers.clone()))?,
+            ),
+            (
+                "__META_DATA",
+                bincode::serialize(&DbObject::new(self.meta_data.clone()))?,
+            ),
+        ];
+        pipe.hset_multiple(config.table_key(), &items).ignore();
+        Ok(())
+    }
+
+    /// adds writing `rows` to the hashes and the index to a pipeline
+    fn redis_write_rows(config: &RedisConfig, pipe: &mut redis::Pipeline, rows: &[RedisRow]) {
+        for chunk in rows.chunks(CHUNK_SIZE) {
+            let values: Vec<(&Vec<u8>, &Vec<u8>)> =
+                chunk.iter().map(|x| (&x.field, &x.row)).collect();
+            let hashes: Vec<(&Vec<u8>, &Vec<u8>)> =
+                chunk.iter().map(|x| (&x.field, &x.hash)).collect();
+            let members: Vec<(u8, &Vec<u8>)> = chunk.iter().map(|x| (0, &x.field)).collect();
+            pipe.hset_multiple(config.rows_key(), &values)
+                .ignore()
+                .hset_multiple(config.hashes_key(), &hashes)
+                .ignore()
+                .zadd_multiple(config.index_key(), &members)
+                .ignore();
         }
+    }
 
-        connection.set(
-            bincode::serialize("__HEADER")?,
-            bincode::serialize(&DbObject::new(self.headers.clone()))?,
-        )?;
+    /// encodes every row of the table
+    fn redis_rows(&self) -> Result<Vec<RedisRow>, RedisError> {
+        self.iter().map(|(k, v)| Self::redis_row(k, v)).collect()
+    }
 
-        connection.set(
-            bincode::serialize("__META_DATA")?,
-            bincode::serialize(&DbObject::new(self.meta_data.clone()))?,
-        )?;
+    /// transaction writing the whole table
+    fn redis_save_pipeline(&self, config: &RedisConfig) -> Result<redis::Pipeline, RedisError> {
+        let rows = self.redis_rows()?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        self.redis_table_data(config, &mut pipe)?;
+        Self::redis_write_rows(config, &mut pipe, &rows);
+        Ok(pipe)
+    }
 
+    /// Compares the rows with the `stored` `__HASHES` entries.
+    fn redis_update(
+        &self,
+        mut stored: HashMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<RedisUpdate<U>, RedisError> {
+        let mut changes = DbChanges::new();
+        let mut rows = Vec::new();
         for (k, v) in self.iter() {
-            let tmp = DbObject::new(v.clone());
-            connection.set(bincode::serialize(k)?, bincode::serialize(&tmp)?)?;
+            let row = Self::redis_row(k, v)?;
+            match stored.remove(&row.field) {
+                Some(x) if x == row.hash => continue,
+                Some(_) => changes.updated.push(k.clone()),
+                None => changes.inserted.push(k.clone()),
+            }
+            rows.push(row);
+        }
+        let mut stale = Vec::with_capacity(stored.len());
+        for (field, hash) in stored {
+            let (k, _): (U, u64) = bincode::deserialize(&hash)?;
+            stale.push((field, k));
         }
+        Ok(RedisUpdate {
+            rows,
+            changes,
+            stale,
+        })
+    }
+
+    /// Moves the stale rows of `update` from the `from` headers to the headers
+    /// of the table, they are written together with the changed rows.
+    fn redis_rewrite_stale(
+        &self,
+        config: &RedisConfig,
+        connection: &mut Connection,
+        update: &mut RedisUpdate<U>,
+        from: &[String],
+        fill: &V,
+    ) -> Result<(), RedisError> {
+        if update.stale.is_empty() {
+            return Ok(());
+        }
+        let fields = update.stale.drain(..).map(|(field, _)| field).collect();
+        let rows: Vec<Option<Vec<u8>>> = Self::redis_rows_cmd(config, fields).query(connection)?;
+        for row_data in rows.into_iter().flatten() {
+            let (k, row): (U, DbObject<Vec<V>>) = bincode::deserialize(&row_data)?;
+            let row = rewrite_row(&row.item, from, &self.headers, fill);
+            update.rows.push(Self::redis_row(&k, &row)?);
+            update.changes.updated.push(k);
+        }
+        update.changes.updated.sort();
         Ok(())
     }
-    pub fn update_redis(&self, config: &RedisConfig) -> Result<(), RedisError> {
-        let mut connection = Self::connect_redis(config)?;
-        let mut headers: DbObject<Vec<String>> = DbObject::new(vec![]);
-        let mut meta_data: DbObject<Option<HashMap<String, String>>> = DbObject::new(None);
-        for (k, v) in self.iter() {
-            let tmp = DbObject::new(v.clone());
-            let key = bincode::serialize(k)?;
-            let key_exist: bool = connection.exists(key.clone())?;
-            if !key_exist {
-                connection.set(bincode::serialize(k)?, bincode::serialize(&tmp)?)?;
-            } else {
-                let db_value: Vec<u8> = connection.get(key)?;
-                let db_object: DbObject<Vec<V>> = bincode::deserialize(&db_value)?;
-                if tmp != db_object {
-                    connection.set(bincode::serialize(k)?, bincode::serialize(&tmp)?)?;
-                }
+
+    /// Transaction writing the rows of `update`, and deleting its stale rows
+    /// when `delete_stale` is set. Returns the keys it changes.
+    fn redis_update_pipeline(
+        &self,
+        config: &RedisConfig,
+        update: RedisUpdate<U>,
+        delete_stale: bool,
+    ) -> Result<(redis::Pipeline, DbChanges<U>), RedisError> {
+        let mut changes = update.changes;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        self.redis_table_data(config, &mut pipe)?;
+        Self::redis_write_rows(config, &mut pipe, &update.rows);
+        if delete_stale {
+            let mut fields = Vec::with_capacity(update.stale.len());
+            for (field, k) in update.stale {
+                fields.push(field);
+                changes.deleted.push(k);
+            }
+            changes.deleted.sort();
+            for chunk in fields.chunks(CHUNK_SIZE) {
+                pipe.hdel(config.rows_key(), chunk)
+                    .ignore()
+                    .hdel(config.hashes_key(), chunk)
+                    .ignore()
+                    .zrem(config.index_key(), chunk)
+                    .ignore();
             }
         }
+        Ok((pipe, changes))
+    }
+
+    /// Saves the table in one pipelined MULTI/EXEC transaction.
+    pub fn save_redis(&self, config: &RedisConfig) -> Result<(), RedisError> {
+        let mut connection = config.connection()?;
+        if connection.hexists(config.table_key(), "__HEADER")? {
+            return Err(RedisError::DbTableError(DbTableError::DbExists));
+        }
+        config.check_layout(&mut connection)?;
+
+        self.redis_save_pipeline(config)?
+            .query::<()>(&mut *connection)?;
         Ok(())
     }
+
+    /// Writes the rows whose hash changed in one pipelined MULTI/EXEC
+    /// transaction. Stored rows that are not in the table are kept, use
+    /// `sync_redis` to delete them.
+    ///
+    /// Returns the keys that were inserted and updated.
+    ///
+    /// Fails with `DbTableError::DbDoesNotExist` when no table was saved and with
+    /// `DbTableError::DbHeadersChanged` when the headers changed, see
+    /// `update_redis_with` for the other options.
+    pub fn update_redis(&self, config: &RedisConfig) -> Result<DbChanges<U>, RedisError> {
+        self.update_redis_with(config, &HeaderChange::Reject)
+    }
+
+    /// Like `update_redis`, with `on_change` deciding what happens when the headers changed.
+    pub fn update_redis_with(
+        &self,
+        config: &RedisConfig,
+        on_change: &HeaderChange<V>,
+    ) -> Result<DbChanges<U>, RedisError> {
+        self.write_redis(config, on_change, false)
+    }
+
+    /// Like `update_redis`, but also deletes the stored rows that are no
+    /// longer in the table, so the stored table equals this table afterwards.
+    /// Returns the keys that were inserted, updated and deleted.
+    pub fn sync_redis(&self, config: &RedisConfig) -> Result<DbChanges<U>, RedisError> {
+        self.write_redis(config, &HeaderChange::Reject, true)
+    }
+
+    fn write_redis(
+        &self,
+        config: &RedisConfig,
+        on_change: &HeaderChange<V>,
+        delete_stale: bool,
+    ) -> Result<DbChanges<U>, RedisError> {
+        let mut connection = config.connection()?;
+        // the stored headers and fill value when the stored rows that are kept need a rewrite
+        let mut rewrite = None;
+
+        let stored_headers = match config.stored_headers(&mut connection)? {
+            Some(x) => x,
+            None => {
+                config.check_layout(&mut connection)?;
+                return Err(RedisError::DbTableError(DbTableError::DbDoesNotExist));
+            }
+        };
+        if stored_headers != self.headers {
+            match on_change {
+                HeaderChange::Reject => {
+                    return Err(RedisError::DbTableError(DbTableError::DbHeadersChanged(
+                        stored_headers,
+                    )))
+                }
+                HeaderChange::Rewrite(fill) => rewrite = Some((stored_headers, fill)),
+                HeaderChange::Version => {
+                    let rows = self.redis_rows()?;
+
+                    let mut pipe = redis::pipe();
+                    pipe.atomic();
+                    config.move_to_version(&mut connection, &mut pipe)?;
+                    self.redis_table_data(config, &mut pipe)?;
+                    Self::redis_write_rows(config, &mut pipe, &rows);
+                    pipe.query::<()>(&mut *connection)?;
+                    let mut changes = DbChanges::new();
+                    changes.inserted = self.keys().cloned().collect();
+                    return Ok(changes);
+                }
+            }
+        }
+
+        let stored = connection.hgetall(config.hashes_key())?;
+        let mut update = self.redis_update(stored)?;
+        if let (Some((from, fill)), false) = (rewrite, delete_stale) {
+            self.redis_rewrite_stale(config, &mut connection, &mut update, &from, fill)?;
+        }
+        let (pipe, changes) = self.redis_update_pipeline(config, update, delete_stale)?;
+        pipe.query::<()>(&mut *connection)?;
+        Ok(changes)
+    }
     pub fn delete_redis(&self, config: &RedisConfig) -> Result<(), RedisError> {
-        let mut connection = Self::connect_redis(config)?;
-        redis::cmd("FLUSHDB").query(&mut connection)?;
+        config.delete_keys()
+    }
+
+    /// Moves a table saved by an older release, with the headers under a
+    /// top-level `__HEADER` key and every row under its own key, to the keys of
+    /// `config`. The old keys are deleted in the same MULTI/EXEC transaction.
+    ///
+    /// The old layout used the whole database, so every string key besides the
+    /// headers and meta data is read as a row. The hashes and sorted sets of
+    /// tables in the current layout are skipped.
+    pub fn migrate_redis(config: &RedisConfig) -> Result<Table<U, V>, RedisError> {
+        let mut connection = config.connection()?;
+        if connection.hexists(config.table_key(), "__HEADER")? {
+            return Err(RedisError::DbTableError(DbTableError::DbExists));
+        }
+        let header_key = bincode::serialize("__HEADER")?;
+        let meta_key = bincode::serialize("__META_DATA")?;
+
+        let keys: Vec<Vec<u8>> = connection.scan()?.collect();
+        let mut values: Vec<Option<Vec<u8>>> = Vec::with_capacity(keys.len());
+        for chunk in keys.chunks(CHUNK_SIZE) {
+            // MGET returns nil for keys that are not strings
+            values.extend(
+                redis::cmd("MGET")
+                    .arg(chunk)
+                    .query::<Vec<Option<Vec<u8>>>>(&mut *connection)?,
+            );
+        }
+
+        let mut headers = None;
+        let mut meta_data = None;
+        let mut btreemap = BTreeMap::new();
+        let mut legacy_keys = Vec::new();
+        for (key, value) in keys.into_iter().zip(values) {
+            let value = match value {
+                Some(x) => x,
+                None => continue,
+            };
+            if key == header_key {
+                headers = Some(bincode::deserialize::<DbObject<Vec<String>>>(&value)?.item);
+            } else if key == meta_key {
+                meta_data =
+                    bincode::deserialize::<DbObject<Option<HashMap<String, String>>>>(&value)?.item;
+            } else {
+                let row: DbObject<Vec<V>> = bincode::deserialize(&value)?;
+                btreemap.insert(bincode::deserialize::<U>(&key)?, row.item);
+            }
+            legacy_keys.push(key);
+        }
+
+        let headers =
+            headers.ok_or(RedisError::DbTableError(DbTableError::DbHeaderDoesNotExist))?;
+        for row in btreemap.values() {
+            check_row_length(&headers, row).map_err(RedisError::DbTableError)?;
+        }
+        let mut table = Table::new_btreemap(headers, btreemap);
+        if let Some(x) = meta_data {
+            table.set_meta_data(x)
+        }
+
+        let mut pipe = table.redis_save_pipeline(config)?;
+        for chunk in legacy_keys.chunks(CHUNK_SIZE) {
+            pipe.del(chunk).ignore();
+        }
+        pipe.query::<()>(&mut *connection)?;
+        Ok(table)
+    }
+}
+
+/// Async versions of the methods above, for use inside a tokio runtime.
+#[cfg(feature = "async")]
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug
+        + Clone
+        + std::cmp::Ord
+        + OrderedKey
+        + serde::de::DeserializeOwned
+        + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    pub async fn from_redis_async(config: &RedisConfig) -> Result<Table<U, V>, RedisError> {
+        Self::load_range_redis_async(config, ..).await
+    }
+
+    /// Loads only the rows whose index falls in `range`.
+    pub async fn load_range_redis_async<R>(
+        config: &RedisConfig,
+        range: R,
+    ) -> Result<Table<U, V>, RedisError>
+    where
+        R: RangeBounds<U>,
+    {
+        let mut connection = config.connect_async().await?;
+        let (table_data, members): (Vec<Option<Vec<u8>>>, Vec<Vec<u8>>) =
+            Self::redis_load_pipeline(config, &range)
+                .query_async(&mut connection)
+                .await?;
+        if table_data[0].is_none() {
+            config.check_layout_async(&mut connection).await?;
+        }
+        let rows = if members.is_empty() {
+            Vec::new()
+        } else {
+            Self::redis_rows_cmd(config, members)
+                .query_async(&mut connection)
+                .await?
+        };
+        Self::from_redis_parts(table_data, rows)
+    }
+
+    pub async fn save_redis_async(&self, config: &RedisConfig) -> Result<(), RedisError> {
+        let mut connection = config.connect_async().await?;
+        if connection.hexists(config.table_key(), "__HEADER").await? {
+            return Err(RedisError::DbTableError(DbTableError::DbExists));
+        }
+        config.check_layout_async(&mut connection).await?;
+
+        self.redis_save_pipeline(config)?
+            .query_async::<_, ()>(&mut connection)
+            .await?;
         Ok(())
     }
+
+    /// Writes the rows whose hash changed, stored rows that are not in the table are kept.
+    /// Returns the keys that were inserted and updated.
+    ///
+    /// Fails with `DbTableError::DbDoesNotExist` when no table was saved and with
+    /// `DbTableError::DbHeadersChanged` when the headers changed,
+    /// `update_redis_with` can migrate the stored table.
+    pub async fn update_redis_async(
+        &self,
+        config: &RedisConfig,
+    ) -> Result<DbChanges<U>, RedisError> {
+        self.write_redis_async(config, false).await
+    }
+
+    /// Like `update_redis_async`, but also deletes the stored rows that are no longer in the table.
+    pub async fn sync_redis_async(&self, config: &RedisConfig) -> Result<DbChanges<U>, RedisError> {
+        self.write_redis_async(config, true).await
+    }
+
+    async fn write_redis_async(
+        &self,
+        config: &RedisConfig,
+        delete_stale: bool,
+    ) -> Result<DbChanges<U>, RedisError> {
+        let mut connection = config.connect_async().await?;
+
+        let item: Option<Vec<u8>> = connection.hget(config.table_key(), "__HEADER").await?;
+        let item = match item {
+            Some(x) => x,
+            None => {
+                config.check_layout_async(&mut connection).await?;
+                return Err(RedisError::DbTableError(DbTableError::DbDoesNotExist));
+            }
+        };
+        let stored_headers = bincode::deserialize::<DbObject<Vec<String>>>(&item)?.item;
+        if stored_headers != self.headers {
+            return Err(RedisError::DbTableError(DbTableError::DbHeadersChanged(
+                stored_headers,
+            )));
+        }
+
+        let stored = connection.hgetall(config.hashes_key()).await?;
+        let update = self.redis_update(stored)?;
+        let (pipe, changes) = self.redis_update_pipeline(config, update, delete_stale)?;
+        pipe.query_async::<_, ()>(&mut connection).await?;
+        Ok(changes)
+    }
+}
+
+impl<U, V> TableStore<U, V> for RedisConfig
+where
+    U: std::fmt::Debug
+        + Clone
+        + std::cmp::Ord
+        + OrderedKey
+        + serde::de::DeserializeOwned
+        + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    /// redis needs no preparation
+    fn init(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    fn save(&self, table: &Table<U, V>) -> Result<(), StoreError> {
+        Ok(table.save_redis(self)?)
+    }
+
+    fn update(&self, table: &Table<U, V>) -> Result<DbChanges<U>, StoreError> {
+        Ok(table.sync_redis(self)?)
+    }
+
+    fn load(&self) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::from_redis(self)?)
+    }
+
+    fn load_range(&self, range: (Bound<&U>, Bound<&U>)) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::load_range_redis(self, range)?)
+    }
+
+    fn delete(&self) -> Result<(), StoreError> {
+        Ok(self.delete_keys()?)
+    }
+
+    fn exists(&self) -> Result<bool, StoreError> {
+        Ok(self.table_exists()?)
+    }
+}
+
+#[cfg(test)]
+mod namespace {
+    use super::lex_bounds;
+    use crate::RedisConfig;
+    use std::ops::Bound;
+
+    #[test]
+    fn key_prefix() {
+        let config = RedisConfig::new("redis://127.0.0.1/").unwrap();
+        assert_eq!("__ROWS", config.rows_key());
+
+        let config = config.with_namespace("prices");
+        assert_eq!("prices:__TABLE", config.table_key());
+        assert_eq!("prices:__ROWS", config.rows_key());
+        assert_eq!("prices:__HASHES", config.hashes_key());
+        assert_eq!("prices:__INDEX", config.index_key());
+    }
+
+    #[test]
+    fn range_arguments() {
+        assert_eq!(
+            (b"-".to_vec(), b"+".to_vec()),
+            lex_bounds((Bound::Unbounded, Bound::Unbounded))
+        );
+        assert_eq!(
+            (vec![b'[', 0, 2], vec![b'(', 0, 4]),
+            lex_bounds((Bound::Included(vec![0, 2]), Bound::Excluded(vec![0, 4])))
+        );
+    }
 }
@@ -0,0 +1,104 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::enums::IndexOrColumn;
+use crate::{Table, TableError};
+
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord + Clone,
+    V: std::fmt::Debug + Clone,
+{
+    /// turns a long table into a wide table.
+    /// The values of the `index` column become the index, the values of the `columns` column the headers
+    /// and the cells are taken from the `values` column. Cells without a value are None.
+    /// Returns an error if an index and column pair is found more than once.
+    ///
+    ///```
+    /// use timeseries::{vec2, Table};
+    ///
+    /// let headers = vec![String::from("time"), String::from("sensor"), String::from("value")];
+    /// let data = vec2![[1, 10, 5], [1, 11, 6], [2, 10, 7]];
+    /// let long = Table::new(headers, vec![1, 2, 3], data).unwrap();
+    ///
+    /// let wide = long.pivot("time", "sensor", "value").unwrap();
+    ///
+    /// assert_eq!(&[String::from("10"), String::from("11")], &wide.headers[..]);
+    /// assert_eq!(Some(&vec![Some(5), Some(6)]), wide.data.get(&1));
+    /// assert_eq!(Some(&vec![Some(7), None]), wide.data.get(&2));
+    ///```
+    pub fn pivot<X, Y, Z>(
+        &self,
+        index: X,
+        columns: Y,
+        values: Z,
+    ) -> Result<Table<V, Option<V>>, TableError>
+    where
+        X: Into<IndexOrColumn>,
+        Y: Into<IndexOrColumn>,
+        Z: Into<IndexOrColumn>,
+        V: std::cmp::Ord + std::fmt::Display,
+    {
+        let index = self.column_index(index)?;
+        let columns = self.column_index(columns)?;
+        let values = self.column_index(values)?;
+
+        let headers: Vec<&V> = self
+            .data
+            .values()
+            .map(|row| &row[columns])
+            .collect::<BTreeSet<&V>>()
+            .into_iter()
+            .collect();
+
+        let mut data: BTreeMap<V, Vec<Option<V>>> = BTreeMap::new();
+        let mut collided = Vec::new();
+        for row in self.data.values() {
+            let cells = data
+                .entry(row[index].clone())
+                .or_insert_with(|| vec![None; headers.len()]);
+            let position = headers
+                .binary_search(&&row[columns])
+                .expect("headers contain every column value");
+            if cells[position].is_some() {
+                collided.push(format!("({:?}, {:?})", row[index], row[columns]));
+            }
+            cells[position] = Some(row[values].clone());
+        }
+        if !collided.is_empty() {
+            return Err(TableError::duplicates(collided));
+        }
+
+        let mut table = Table::new_btreemap(headers.iter().map(|h| h.to_string()).collect(), data);
+        table.meta_data = self.meta_data.clone();
+        Ok(table)
+    }
+
+    /// turns a wide table into a long table,
+    /// every cell becomes a row indexed by the original index and its header.
+    ///
+    ///```
+    /// use timeseries::{vec2, Table};
+    ///
+    /// let headers = vec![String::from("a"), String::from("b")];
+    /// let wide = Table::new(headers, vec![1, 2], vec2![[1, 2], [3, 4]]).unwrap();
+    ///
+    /// let long = wide.melt();
+    ///
+    /// assert_eq!(&[String::from("value")], &long.headers[..]);
+    /// assert_eq!(4, long.data.len());
+    /// assert_eq!(Some(&vec![3]), long.data.get(&(2, String::from("a"))));
+    ///```
+    pub fn melt(&self) -> Table<(U, String), V> {
+        let data = self
+            .iter_rows()
+            .flat_map(|row| {
+                let index = row.index;
+                row.map(move |(h, v)| ((index.clone(), h.clone()), vec![v.clone()]))
+            })
+            .collect();
+
+        let mut table = Table::new_btreemap(vec![String::from("value")], data);
+        table.meta_data = self.meta_data.clone();
+        table
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, VecDeque};
+
+#[cfg(feature = "num")]
+use crate::enums::RollingStatistic;
+use crate::{Table, TableError};
+
+/// State of a single column in a rolling window.
+/// Values are pushed at the back and popped at the front,
+/// only the bookkeeping needed for the statistic is kept up to date.
+#[cfg(feature = "num")]
+#[derive(Debug)]
+pub(crate) struct RollingWindow<V> {
+    statistic: RollingStatistic,
+    values: VecDeque<V>,
+    /// running sum, mean and sum of squared deviations (Welford's algorithm) in f64,
+    /// so integer columns can't overflow
+    sum: f64,
+    mean: f64,
+    deviations: f64,
+    /// number of values in `sum`
+    count: usize,
+    /// values in the window that can't be cast to f64
+    uncastable: usize,
+    /// values in the window that are not ordered, i.e. NaN
+    unordered: usize,
+    extremes: VecDeque<V>,
+    sorted: Vec<V>,
+}
+
+/// true for values that can't be compared to themselves, i.e. NaN
+#[cfg(feature = "num")]
+fn is_unordered<V: std::cmp::PartialOrd>(value: &V) -> bool {
+    value.partial_cmp(value).is_none()
+}
+
+#[cfg(feature = "num")]
+impl<V> RollingWindow<V>
+where
+    V: num_traits::Num + num_traits::NumCast + std::cmp::PartialOrd + Clone,
+{
+    pub(crate) fn new(statistic: RollingStatistic) -> RollingWindow<V> {
+        RollingWindow {
+            statistic,
+            values: VecDeque::new(),
+            sum: 0.0,
+            mean: 0.0,
+            deviations: 0.0,
+            count: 0,
+            uncastable: 0,
+            unordered: 0,
+            extremes: VecDeque::new(),
+            sorted: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: V) {
+        if is_unordered(&value) {
+            self.unordered += 1;
+            self.values.push_back(value);
+            return;
+        }
+        match self.statistic {
+            RollingStatistic::Sum | RollingStatistic::Mean | RollingStatistic::Std => {
+                match num_traits::cast::<V, f64>(value.clone()) {
+                    Some(x) => {
+                        self.count += 1;
+                        self.sum += x;
+                        let delta = x - self.mean;
+                        self.mean += delta / self.count as f64;
+                        self.deviations += delta * (x - self.mean);
+                    }
+                    None => self.uncastable += 1,
+                }
+            }
+            RollingStatistic::Min => {
+                while self.extremes.back().is_some_and(|x| *x > value) {
+                    self.extremes.pop_back();
+                }
+                self.extremes.push_back(value.clone());
+            }
+            RollingStatistic::Max => {
+                while self.extremes.back().is_some_and(|x| *x < value) {
+                    self.extremes.pop_back();
+                }
+                self.extremes.push_back(value.clone());
+            }
+            RollingStatistic::Median => {
+                let position = self.sorted.partition_point(|x| *x < value);
+                self.sorted.insert(position, value.clone());
+            }
+        }
+        self.values.push_back(value);
+    }
+
+    pub(crate) fn pop(&mut self) {
+        let value = match self.values.pop_front() {
+            Some(x) => x,
+            None => return,
+        };
+        if is_unordered(&value) {
+            self.unordered -= 1;
+            return;
+        }
+        match self.statistic {
+            RollingStatistic::Sum | RollingStatistic::Mean | RollingStatistic::Std => {
+                match num_traits::cast::<V, f64>(value) {
+                    // starting over also drops the rounding errors collected so far
+                    Some(_) if self.count <= 1 => {
+                        self.count = 0;
+                        self.sum = 0.0;
+                        self.mean = 0.0;
+                        self.deviations = 0.0;
+                    }
+                    Some(x) => {
+                        let n = self.count as f64;
+                        let old_mean = self.mean;
+                        self.count -= 1;
+                        self.sum -= x;
+                        self.mean = (old_mean * n - x) / (n - 1.0);
+                        self.deviations -= (x - old_mean) * (x - self.mean);
+                    }
+                    None => self.uncastable -= 1,
+                }
+            }
+            RollingStatistic::Min | RollingStatistic::Max => {
+                if self.extremes.front() == Some(&value) {
+                    self.extremes.pop_front();
+                }
+            }
+            RollingStatistic::Median => {
+                // `sorted` holds no NaN, so the search finds the first equal value
+                let position = self.sorted.partition_point(|x| *x < value);
+                if self.sorted.get(position) == Some(&value) {
+                    self.sorted.remove(position);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// The statistic over the values in the window.
+    ///
+    /// None if the window is empty or the statistic can't be represented in `V`.
+    pub(crate) fn value(&self) -> Option<V> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        // like f64 arithmetic, a window holding NaN gives NaN
+        if self.unordered > 0 {
+            return num_traits::cast(f64::NAN);
+        }
+        match self.statistic {
+            RollingStatistic::Sum | RollingStatistic::Mean | RollingStatistic::Std
+                if self.uncastable > 0 =>
+            {
+                None
+            }
+            RollingStatistic::Sum => num_traits::cast(self.sum),
+            RollingStatistic::Mean => num_traits::cast(self.sum / len as f64),
+            RollingStatistic::Std => {
+                // rounding can leave a tiny negative sum for a constant window
+                let var = self.deviations.max(0.0) / len as f64;
+                num_traits::cast(var.sqrt())
+            }
+            RollingStatistic::Min | RollingStatistic::Max => self.extremes.front().cloned(),
+            RollingStatistic::Median => {
+                let middle = len / 2;
+                if len % 2 == 1 {
+                    Some(self.sorted[middle].clone())
+                } else {
+                    let two = V::one() + V::one();
+                    Some((self.sorted[middle - 1].clone() + self.sorted[middle].clone()) / two)
+                }
+            }
+        }
+    }
+}
+
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord + Clone,
+    V: std::fmt::Debug + Clone,
+{
+    /// rolling statistic over the last `window` rows, including the current row.
+    /// The first rows use the rows that are available.
+    /// Cells are None when the statistic can't be represented in `V`.
+    #[cfg(feature = "num")]
+    pub fn rolling(
+        &self,
+        window: usize,
+        statistic: RollingStatistic,
+    ) -> Result<Table<U, Option<V>>, TableError>
+    where
+        V: num_traits::Num + num_traits::NumCast + std::cmp::PartialOrd,
+    {
+        if window == 0 {
+            return Err(TableError::new("window should contain at least one row"));
+        }
+        Ok(self.rolling_by(statistic, |_, _, len| len > window))
+    }
+
+    /// applies `f` to the values of the last `window` rows of every column, including the current row.
+    /// The first rows use the rows that are available.
+    pub fn rolling_with<F>(&self, window: usize, f: F) -> Result<Table<U, V>, TableError>
+    where
+        F: Fn(&[&V]) -> V,
+    {
+        if window == 0 {
+            return Err(TableError::new("window should contain at least one row"));
+        }
+        Ok(self.rolling_by_with(f, |_, _, len| len > window))
+    }
+
+    /// runs a rolling window over the rows,
+    /// `evict` gets the oldest key, the current key and the window length
+    /// and returns if the oldest row should leave the window
+    #[cfg(feature = "num")]
+    pub(crate) fn rolling_by<E>(&self, statistic: RollingStatistic, evict: E) -> Table<U, Option<V>>
+    where
+        V: num_traits::Num + num_traits::NumCast + std::cmp::PartialOrd,
+        E: Fn(&U, &U, usize) -> bool,
+    {
+        let mut windows: Vec<RollingWindow<V>> = (0..self.headers.len())
+            .map(|_| RollingWindow::new(statistic))
+            .collect();
+        let mut keys: VecDeque<&U> = VecDeque::new();
+        let mut data = BTreeMap::new();
+
+        for (k, row) in self.data.iter() {
+            keys.push_back(k);
+            for (window, v) in windows.iter_mut().zip(row) {
+                window.push(v.clone());
+            }
+            while let Some(front) = keys.front() {
+                if !evict(front, k, keys.len()) {
+                    break;
+                }
+                keys.pop_front();
+                for window in windows.iter_mut() {
+                    window.pop();
+                }
+            }
+            let values = windows.iter().map(|w| w.value()).collect();
+            data.insert(k.clone(), values);
+        }
+
+        let mut table = Table::new_btreemap(self.headers.clone(), data);
+        table.meta_data = self.meta_data.clone();
+        table
+    }
+
+    /// same as `rolling_by` but with a closure that gets every value in the window
+    pub(crate) fn rolling_by_with<F, E>(&self, f: F, evict: E) -> Table<U, V>
+    where
+        F: Fn(&[&V]) -> V,
+        E: Fn(&U, &U, usize) -> bool,
+    {
+        let mut rows: VecDeque<(&U, &Vec<V>)> = VecDeque::new();
+        let mut data = BTreeMap::new();
+
+        for (k, row) in self.data.iter() {
+            rows.push_back((k, row));
+            while let Some((front, _)) = rows.front() {
+                if !evict(front, k, rows.len()) {
+                    break;
+                }
+                rows.pop_front();
+            }
+            let values = (0..self.headers.len())
+                .map(|i| {
+                    let column: Vec<&V> = rows.iter().map(|(_, r)| &r[i]).collect();
+                    f(&column)
+                })
+                .collect();
+            data.insert(k.clone(), values);
+        }
+
+        let mut table = Table::new_btreemap(self.headers.clone(), data);
+        table.meta_data = self.meta_data.clone();
+        table
+    }
+}
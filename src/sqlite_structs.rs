@@ -1,7 +1,15 @@
 #![cfg(feature = "sqlite_db")]
-use crate::{BtreeMapTrait, DbObject, DbTableError, Table, TableMetaTrait};
-use rusqlite::{Connection, OpenFlags, ToSql, NO_PARAMS};
-use std::collections::{BTreeMap, HashMap};
+use crate::connection::{ConnectionGuard, ReusedConnection};
+use crate::db_structs::{check_row_length, ordered_bounds, quote_identifier};
+use crate::enums::DuplicatePolicy;
+use crate::{
+    BtreeMapTrait, DbChanges, DbObject, DbTableError, HeaderChange, OrderedKey, StoreError, Table,
+    TableError, TableMetaTrait, TableStore,
+};
+use rusqlite::types::FromSql;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, Rows, Statement, ToSql};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Bound, RangeBounds};
 
 #[derive(Debug)]
 pub enum SqliteError {
@@ -9,6 +17,9 @@ pub enum SqliteError {
     DbTableError(DbTableError),
     Sqlite(rusqlite::Error),
     Io(std::io::Error),
+    /// the SQL table has no column with this name
+    ColumnDoesNotExist(String),
+    Table(TableError),
 }
 
 impl std::fmt::Display for SqliteError {
@@ -35,10 +46,50 @@ impl From<std::io::Error> for SqliteError {
     }
 }
 
+impl From<TableError> for SqliteError {
+    fn from(err: TableError) -> SqliteError {
+        SqliteError::Table(err)
+    }
+}
+
+/// Rust types that can be stored in a typed SQLite column.
+pub trait SqliteColumn: ToSql + FromSql {
+    /// type affinity of the column, e.g. `INTEGER` for `i64`
+    fn affinity() -> &'static str;
+}
+
+macro_rules! impl_sqlite_column {
+    ($affinity:expr => $($t:ty),*) => {
+        $(
+            impl SqliteColumn for $t {
+                fn affinity() -> &'static str {
+                    $affinity
+                }
+            }
+        )*
+    };
+}
+
+impl_sqlite_column!("INTEGER" => bool, i8, i16, i32, i64, isize, u8, u16, u32);
+impl_sqlite_column!("REAL" => f32, f64);
+impl_sqlite_column!("TEXT" => String);
+impl_sqlite_column!("BLOB" => Vec<u8>);
+
+// stored as ISO 8601 text, which sorts in time order
+#[cfg(feature = "chrono")]
+impl_sqlite_column!(
+    "TEXT" => chrono::DateTime<chrono::Utc>, chrono::NaiveDateTime, chrono::NaiveDate
+);
+
 #[derive(Debug, Clone)]
 pub struct SqliteConfig {
     pub location: std::path::PathBuf,
     pub flags: OpenFlags,
+    /// name of the SQL table the rows are stored in, defaults to `row`
+    pub table: String,
+    /// column that holds the index in the columnar layout, defaults to `index`
+    pub index_column: String,
+    reused: ReusedConnection<Connection>,
 }
 
 impl SqliteConfig {
@@ -49,6 +100,9 @@ impl SqliteConfig {
         SqliteConfig {
             location: path.into(),
             flags: OpenFlags::default(),
+            table: String::from("row"),
+            index_column: String::from("index"),
+            reused: ReusedConnection::default(),
         }
     }
 
@@ -58,36 +112,289 @@ impl SqliteConfig {
     {
         SqliteConfig {
             location: path.into(),
-            flags: flags,
+            flags,
+            table: String::from("row"),
+            index_column: String::from("index"),
+            reused: ReusedConnection::default(),
         }
     }
-}
 
-impl<U, V> Table<U, V>
-where
-    U: std::fmt::Debug + Clone + std::cmp::Ord + serde::de::DeserializeOwned + serde::Serialize,
-    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
-{
-    /// creates tables
-    pub fn init_sqlite(&self, config: &SqliteConfig) -> Result<(), SqliteError> {
-        let connection = Self::connect_sqlite(config)?;
+    /// Stores the table under `name`, so one database file can hold many tables.
+    pub fn with_table<S>(mut self, name: S) -> SqliteConfig
+    where
+        S: Into<String>,
+    {
+        self.table = name.into();
+        self
+    }
+
+    /// Uses the column `name` as index in the columnar layout.
+    pub fn with_index_column<S>(mut self, name: S) -> SqliteConfig
+    where
+        S: Into<String>,
+    {
+        self.index_column = name.into();
+        self
+    }
+
+    /// Keeps one connection open and uses it for every call made with this
+    /// config and its clones, instead of opening a connection per call.
+    pub fn reuse_connection(mut self) -> SqliteConfig {
+        self.reused = ReusedConnection::lazy();
+        self
+    }
+
+    /// Like `reuse_connection`, with a connection opened by the caller.
+    pub fn with_connection(mut self, connection: Connection) -> SqliteConfig {
+        self.reused = ReusedConnection::with(connection);
+        self
+    }
+
+    /// opens a new connection, also when the config keeps one
+    pub fn connect(&self) -> Result<Connection, SqliteError> {
+        Ok(Connection::open_with_flags(
+            &self.location,
+            self.flags.to_owned(),
+        )?)
+    }
+
+    /// the kept connection, or a new one when connections are not reused
+    fn connection(&self) -> Result<ConnectionGuard<'_, Connection>, SqliteError> {
+        self.reused.get(|_| true, || self.connect())
+    }
+
+    /// Config of an older version of the table, kept by `HeaderChange::Version`.
+    ///
+    /// The version is stored in the SQL table `<table>_v<n>`.
+    pub fn version(&self, n: u32) -> SqliteConfig {
+        self.clone().with_table(format!("{}_v{}", self.table, n))
+    }
+
+    /// creates the table and its index
+    pub(crate) fn create_table(&self) -> Result<(), SqliteError> {
+        let connection = self.connection()?;
+        self.create_table_on(&connection)
+    }
+
+    fn create_table_on(&self, connection: &Connection) -> Result<(), SqliteError> {
+        let table = quote_identifier(&self.table);
         connection.execute(
-            "CREATE TABLE row (
+            &format!(
+                "CREATE TABLE {} (
                     id              INTEGER NOT NULL PRIMARY KEY,
                     key             BLOB NOT NULL UNIQUE,
                     item            BLOB NOT NULL,
-                    hash            INTEGER NOT NULL
+                    hash            INTEGER NOT NULL,
+                    sort_key        BLOB
                   )",
-            NO_PARAMS,
+                table
+            ),
+            [],
+        )?;
+        connection.execute(
+            &format!(
+                "CREATE INDEX {} ON {} (sort_key)",
+                quote_identifier(&format!("{}_sort_key", self.table)),
+                table
+            ),
+            [],
         )?;
         Ok(())
     }
 
+    /// Renames the table to the first free version and returns its number.
+    ///
+    /// The index keeps its name on rename, so it is created again under the new name.
+    fn move_to_version(&self, connection: &Connection) -> Result<u32, SqliteError> {
+        let mut n = 1;
+        loop {
+            let taken: i64 = connection.query_row(
+                "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [&self.version(n).table],
+                |row| row.get(0),
+            )?;
+            if taken == 0 {
+                break;
+            }
+            n += 1;
+        }
+
+        let version = self.version(n);
+        let table = quote_identifier(&version.table);
+        connection.execute_batch(&format!(
+            "ALTER TABLE {} RENAME TO {};
+            DROP INDEX {};
+            CREATE INDEX {} ON {} (sort_key);",
+            quote_identifier(&self.table),
+            table,
+            quote_identifier(&format!("{}_sort_key", self.table)),
+            quote_identifier(&format!("{}_sort_key", version.table)),
+            table
+        ))?;
+        Ok(n)
+    }
+
+    /// checks if the table has the `sort_key` column, tables saved before range queries don't
+    fn has_sort_key(&self, connection: &Connection) -> Result<bool, SqliteError> {
+        let count: i64 = connection.query_row(
+            "SELECT count(*) FROM pragma_table_info(?1) WHERE name = 'sort_key'",
+            [&self.table],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Adds the `sort_key` column and its index to a table saved before range
+    /// queries and fills it from the stored keys. Other tables are left alone.
+    fn add_sort_key<U>(&self, connection: &Connection) -> Result<(), SqliteError>
+    where
+        U: OrderedKey + serde::de::DeserializeOwned,
+    {
+        let columns: i64 = connection.query_row(
+            "SELECT count(*) FROM pragma_table_info(?1)",
+            [&self.table],
+            |row| row.get(0),
+        )?;
+        if columns == 0 || self.has_sort_key(connection)? {
+            return Ok(());
+        }
+
+        let table = quote_identifier(&self.table);
+        connection.execute_batch(&format!(
+            "ALTER TABLE {0} ADD COLUMN sort_key BLOB;
+            CREATE INDEX IF NOT EXISTS {1} ON {0} (sort_key);",
+            table,
+            quote_identifier(&format!("{}_sort_key", self.table)),
+        ))?;
+
+        let header_key = bincode::serialize("__HEADER")?;
+        let meta_key = bincode::serialize("__META_DATA")?;
+        let keys: Vec<Vec<u8>> = connection
+            .prepare(&format!("SELECT key FROM {}", table))?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        let mut stmt = connection.prepare(&format!(
+            "UPDATE {} SET sort_key = ?1 WHERE key = ?2",
+            table
+        ))?;
+        for key in keys {
+            if key == header_key || key == meta_key {
+                continue;
+            }
+            let k: U = bincode::deserialize(&key)?;
+            stmt.execute(params![k.ordered_key(), key])?;
+        }
+        Ok(())
+    }
+
+    /// headers of the saved table, `None` when nothing was saved yet
+    fn stored_headers(&self, connection: &Connection) -> Result<Option<Vec<String>>, SqliteError> {
+        let item = connection.query_row(
+            &format!(
+                "SELECT item FROM {} WHERE key = ?1",
+                quote_identifier(&self.table)
+            ),
+            [bincode::serialize("__HEADER")?],
+            |row| row.get::<_, Vec<u8>>(0),
+        );
+        match item {
+            Ok(x) => Ok(Some(bincode::deserialize(&x)?)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(crate) fn drop_table(&self) -> Result<(), SqliteError> {
+        let connection = self.connection()?;
+        connection.execute(
+            &format!("DROP TABLE IF EXISTS {}", quote_identifier(&self.table)),
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// checks if the table was created and saved before
+    pub(crate) fn table_exists(&self) -> Result<bool, SqliteError> {
+        let connection = self.connection()?;
+        let created: i64 = connection.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [&self.table],
+            |row| row.get(0),
+        )?;
+        if created == 0 {
+            return Ok(false);
+        }
+
+        let count: i64 = connection.query_row(
+            &format!(
+                "SELECT count(*) FROM {} WHERE key = ?1",
+                quote_identifier(&self.table)
+            ),
+            [bincode::serialize("__HEADER")?],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+}
+
+impl<U, V> TableStore<U, V> for SqliteConfig
+where
+    U: std::fmt::Debug
+        + Clone
+        + std::cmp::Ord
+        + OrderedKey
+        + serde::de::DeserializeOwned
+        + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    fn init(&self) -> Result<(), StoreError> {
+        Ok(self.create_table()?)
+    }
+
+    fn save(&self, table: &Table<U, V>) -> Result<(), StoreError> {
+        Ok(table.save_sqlite(self)?)
+    }
+
+    fn update(&self, table: &Table<U, V>) -> Result<DbChanges<U>, StoreError> {
+        Ok(table.update_sqlite(self)?)
+    }
+
+    fn load(&self) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::from_sqlite(self)?)
+    }
+
+    fn load_range(&self, range: (Bound<&U>, Bound<&U>)) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::load_range_sqlite(self, range)?)
+    }
+
+    fn delete(&self) -> Result<(), StoreError> {
+        Ok(self.drop_table()?)
+    }
+
+    fn exists(&self) -> Result<bool, StoreError> {
+        Ok(self.table_exists()?)
+    }
+}
+
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug
+        + Clone
+        + std::cmp::Ord
+        + OrderedKey
+        + serde::de::DeserializeOwned
+        + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    /// creates tables
+    pub fn init_sqlite(&self, config: &SqliteConfig) -> Result<(), SqliteError> {
+        config.create_table()
+    }
+
     /// removes all data and tables
     pub fn uninit_sqlite(&self, config: &SqliteConfig) -> Result<(), SqliteError> {
-        let connection = Self::connect_sqlite(config)?;
-        connection.execute("DROP TABLE IF EXISTS row", NO_PARAMS)?;
-        Ok(())
+        config.drop_table()
     }
 
     pub fn remove_sqlite(&self, config: &SqliteConfig) -> Result<(), SqliteError> {
@@ -96,125 +403,250 @@ where
     }
 
     pub fn connect_sqlite(config: &SqliteConfig) -> Result<Connection, SqliteError> {
-        Ok(Connection::open_with_flags(
-            &config.location,
-            config.flags.to_owned(),
-        )?)
+        config.connect()
     }
 
+    /// Inserts the table inside a single transaction.
+    ///
+    /// Fails with `DbTableError::DbExists` when a table was saved before. A
+    /// table saved before range queries gets its `sort_key` column first.
     pub fn save_sqlite(&self, config: &SqliteConfig) -> Result<(), SqliteError> {
-        let connection = Self::connect_sqlite(config)?;
-        let mut stmt =
-            connection.prepare("INSERT INTO row (key, item, hash) VALUES (?1, ?2, ?3)")?;
+        let mut connection = config.connection()?;
+        let transaction = connection.transaction()?;
+        if config.stored_headers(&transaction)?.is_some() {
+            return Err(SqliteError::DbTableError(DbTableError::DbExists));
+        }
+        config.add_sort_key::<U>(&transaction)?;
+        self.save_sqlite_on(config, &transaction)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn save_sqlite_on(
+        &self,
+        config: &SqliteConfig,
+        connection: &Connection,
+    ) -> Result<(), SqliteError> {
+        let mut stmt = connection.prepare(&format!(
+            "INSERT INTO {} (key, item, hash, sort_key) VALUES (?1, ?2, ?3, ?4)",
+            quote_identifier(&config.table)
+        ))?;
 
         let tmp = DbObject::new(self.headers.clone());
-        let data: &[&ToSql] = &[
+        let data: &[&dyn ToSql] = &[
             &bincode::serialize("__HEADER")?,
             &bincode::serialize(&tmp.item)?,
             &(tmp.hash as i64),
+            &None::<Vec<u8>>,
         ];
         stmt.execute(data)?;
 
         let tmp = DbObject::new(self.meta_data.clone());
-        let data: &[&ToSql] = &[
+        let data: &[&dyn ToSql] = &[
             &bincode::serialize("__META_DATA")?,
             &bincode::serialize(&tmp.item)?,
             &(tmp.hash as i64),
+            &None::<Vec<u8>>,
         ];
         stmt.execute(data)?;
 
         for (k, v) in self.iter() {
             let tmp = DbObject::new(v.clone());
-            let data: &[&ToSql] = &[
+            let data: &[&dyn ToSql] = &[
                 &bincode::serialize(k)?,
                 &bincode::serialize(&tmp.item)?,
                 &(tmp.hash as i64),
+                &k.ordered_key(),
             ];
             stmt.execute(data)?;
         }
         Ok(())
     }
 
-    pub fn update_sqlite(&self, config: &SqliteConfig) -> Result<(), SqliteError> {
-        let connection = Self::connect_sqlite(config)?;
-        let mut insert_stmt =
-            connection.prepare("INSERT INTO row (key, item, hash) VALUES (?1, ?2, ?3)")?;
-        let mut update_stmt =
-            connection.prepare("UPDATE row SET item = ?2, hash = ?3 WHERE key = ?1")?;
-        let mut count_stmt =
-            connection.prepare("SELECT count(*) from row WHERE key = ?1 AND hash = ?2")?;
-        let mut exist_stmt = connection.prepare("SELECT count(*) from row WHERE key = ?1")?;
+    /// Synchronises the database with the table inside a single transaction.
+    ///
+    /// Rows are upserted only when their hash changed and rows whose key is no
+    /// longer in the table are deleted. Returns the keys that were inserted,
+    /// updated and deleted.
+    ///
+    /// Fails with `DbTableError::DbDoesNotExist` when no table was saved and with
+    /// `DbTableError::DbHeadersChanged` when the headers changed, see
+    /// `update_sqlite_with` for the other options.
+    pub fn update_sqlite(&self, config: &SqliteConfig) -> Result<DbChanges<U>, SqliteError> {
+        self.update_sqlite_with(config, &HeaderChange::Reject)
+    }
 
-        let tmp = DbObject::new(self.headers.clone());
-        let data: &[&ToSql] = &[
-            &bincode::serialize("__HEADER")?,
-            &bincode::serialize(&tmp.item)?,
-            &(tmp.hash as i64),
-        ];
+    /// Like `update_sqlite`, with `on_change` deciding what happens when the headers changed.
+    pub fn update_sqlite_with(
+        &self,
+        config: &SqliteConfig,
+        on_change: &HeaderChange<V>,
+    ) -> Result<DbChanges<U>, SqliteError> {
+        let mut connection = config.connection()?;
+        let transaction = connection.transaction()?;
+        config.add_sort_key::<U>(&transaction)?;
+        let table = quote_identifier(&config.table);
+        let mut changes = DbChanges::new();
 
-        if count_stmt.query_row(&[data[0], data[2]], |row| row.get::<_, i64>(0))? == 0 {
-            match exist_stmt.query_row(&[data[0]], |row| row.get::<_, i64>(0))? {
-                0 => {
-                    insert_stmt.execute(data)?;
+        let stored_headers = config
+            .stored_headers(&transaction)?
+            .ok_or(SqliteError::DbTableError(DbTableError::DbDoesNotExist))?;
+        if stored_headers != self.headers {
+            match on_change {
+                HeaderChange::Reject => {
+                    return Err(SqliteError::DbTableError(DbTableError::DbHeadersChanged(
+                        stored_headers,
+                    )))
                 }
-                1 => {
-                    update_stmt.execute(data)?;
+                // every stored row is overwritten or deleted below
+                HeaderChange::Rewrite(_) => (),
+                HeaderChange::Version => {
+                    config.move_to_version(&transaction)?;
+                    config.create_table_on(&transaction)?;
+                    self.save_sqlite_on(config, &transaction)?;
+                    transaction.commit()?;
+                    changes.inserted = self.keys().cloned().collect();
+                    return Ok(changes);
                 }
-                _ => unreachable!(),
             }
         }
 
-        let tmp = DbObject::new(self.meta_data.clone());
-        let data: &[&ToSql] = &[
-            &bincode::serialize("__META_DATA")?,
-            &bincode::serialize(&tmp.item)?,
-            &(tmp.hash as i64),
-        ];
+        let header_key = bincode::serialize("__HEADER")?;
+        let meta_key = bincode::serialize("__META_DATA")?;
 
-        if count_stmt.query_row(&[data[0], data[2]], |row| row.get::<_, i64>(0))? == 0 {
-            match exist_stmt.query_row(&[data[0]], |row| row.get::<_, i64>(0))? {
-                0 => {
-                    insert_stmt.execute(data)?;
-                }
-                1 => {
-                    update_stmt.execute(data)?;
-                }
-                _ => unreachable!(),
+        let mut existing = HashSet::new();
+        {
+            let mut stmt = transaction.prepare(&format!("SELECT key FROM {}", table))?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let key: Vec<u8> = row.get(0)?;
+                existing.insert(key);
             }
         }
 
-        for (k, v) in self.iter() {
-            let tmp = DbObject::new(v.clone());
-            let data: &[&ToSql] = &[
-                &bincode::serialize(k)?,
-                &bincode::serialize(&tmp.item)?,
-                &(tmp.hash as i64),
-            ];
+        {
+            let mut upsert_stmt = transaction.prepare(&format!(
+                "INSERT INTO {} (key, item, hash, sort_key) VALUES (?1, ?2, ?3, ?4)
+                    ON CONFLICT(key) DO UPDATE
+                    SET item = excluded.item, hash = excluded.hash, sort_key = excluded.sort_key
+                    WHERE hash != excluded.hash",
+                table
+            ))?;
 
-            if count_stmt.query_row(&[data[0], data[2]], |row| row.get::<_, i64>(0))? == 0 {
-                match exist_stmt.query_row(&[data[0]], |row| row.get::<_, i64>(0))? {
-                    0 => {
-                        insert_stmt.execute(data)?;
-                    }
-                    1 => {
-                        update_stmt.execute(data)?;
+            let tmp = DbObject::new(self.headers.clone());
+            upsert_stmt.execute(params![
+                &header_key,
+                bincode::serialize(&tmp.item)?,
+                tmp.hash as i64,
+                None::<Vec<u8>>
+            ])?;
+
+            let tmp = DbObject::new(self.meta_data.clone());
+            upsert_stmt.execute(params![
+                &meta_key,
+                bincode::serialize(&tmp.item)?,
+                tmp.hash as i64,
+                None::<Vec<u8>>
+            ])?;
+
+            for (k, v) in self.iter() {
+                let tmp = DbObject::new(v.clone());
+                let key = bincode::serialize(k)?;
+                let changed = upsert_stmt.execute(params![
+                    &key,
+                    bincode::serialize(&tmp.item)?,
+                    tmp.hash as i64,
+                    k.ordered_key()
+                ])?;
+
+                if existing.remove(&key) {
+                    if changed > 0 {
+                        changes.updated.push(k.clone());
                     }
-                    _ => unreachable!(),
+                } else {
+                    changes.inserted.push(k.clone());
                 }
             }
         }
-        Ok(())
+
+        existing.remove(&header_key);
+        existing.remove(&meta_key);
+        {
+            let mut delete_stmt =
+                transaction.prepare(&format!("DELETE FROM {} WHERE key = ?1", table))?;
+            for key in existing {
+                delete_stmt.execute([&key])?;
+                changes.deleted.push(bincode::deserialize(&key)?);
+            }
+        }
+        changes.deleted.sort();
+
+        transaction.commit()?;
+        Ok(changes)
     }
 
     pub fn from_sqlite(config: &SqliteConfig) -> Result<Table<U, V>, SqliteError> {
-        let connection = Self::connect_sqlite(config)?;
+        let connection = config.connection()?;
+        let mut stmt = connection.prepare(&format!(
+            "SELECT key, item FROM {}",
+            quote_identifier(&config.table)
+        ))?;
+        let rows = stmt.query([])?;
+        Self::from_sqlite_rows(rows)
+    }
+
+    /// Loads only the rows whose index falls in `range`.
+    ///
+    /// The range is compared against the `sort_key` column, so the filtering
+    /// happens inside SQLite. A table saved before range queries has no such
+    /// column until the next save or update, it is loaded whole and filtered in memory.
+    pub fn load_range_sqlite<R>(config: &SqliteConfig, range: R) -> Result<Table<U, V>, SqliteError>
+    where
+        R: RangeBounds<U>,
+    {
+        let connection = config.connection()?;
+        if !config.has_sort_key(&connection)? {
+            drop(connection);
+            let mut table = Self::from_sqlite(config)?;
+            table.data.retain(|k, _| range.contains(k));
+            return Ok(table);
+        }
+        let (start, end) = ordered_bounds(&range);
+
+        let mut conditions = vec!["sort_key IS NOT NULL"];
+        let mut data: Vec<Vec<u8>> = Vec::new();
+        for (bound, included, excluded) in [
+            (start, "sort_key >= ?", "sort_key > ?"),
+            (end, "sort_key <= ?", "sort_key < ?"),
+        ] {
+            match bound {
+                Bound::Included(x) => {
+                    conditions.push(included);
+                    data.push(x);
+                }
+                Bound::Excluded(x) => {
+                    conditions.push(excluded);
+                    data.push(x);
+                }
+                Bound::Unbounded => (),
+            }
+        }
 
+        let query = format!(
+            "SELECT key, item FROM {} WHERE sort_key IS NULL OR ({})",
+            quote_identifier(&config.table),
+            conditions.join(" AND ")
+        );
+        let mut stmt = connection.prepare(&query)?;
+        let rows = stmt.query(params_from_iter(data))?;
+        Self::from_sqlite_rows(rows)
+    }
+
+    fn from_sqlite_rows(mut rows: Rows) -> Result<Table<U, V>, SqliteError> {
         let mut headers: Vec<String> = Vec::new();
         let mut metadata: Option<HashMap<String, String>> = None;
         let mut btable = BTreeMap::new();
 
-        let mut stmt = connection.prepare("SELECT key, item FROM row")?;
-        let mut rows = stmt.query(NO_PARAMS)?;
         while let Some(row) = rows.next()? {
             let item_k: Vec<u8> = row.get(0)?;
             let item_v: Vec<u8> = row.get(1)?;
@@ -243,6 +675,9 @@ where
                 DbTableError::DbHeaderDoesNotExist,
             ));
         }
+        for row in btable.values() {
+            check_row_length(&headers, row).map_err(SqliteError::DbTableError)?;
+        }
 
         let mut table = Table::new_btreemap(headers, btable);
         if let Some(x) = metadata {
@@ -251,3 +686,144 @@ where
         Ok(table)
     }
 }
+
+/// Columnar layout: a plain SQL table with one column for the index and one
+/// column per header, so the data can be read without this crate.
+/// Meta data is not stored in this layout.
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord + SqliteColumn,
+    V: std::fmt::Debug + SqliteColumn,
+{
+    /// Creates the SQL table `config.table` with a column for the index and every header.
+    ///
+    /// The column types follow the affinity of `U` and `V`, and the index
+    /// column is the primary key.
+    pub fn init_sqlite_columns(&self, config: &SqliteConfig) -> Result<(), SqliteError> {
+        let mut columns = vec![format!(
+            "{} {} NOT NULL PRIMARY KEY",
+            quote_identifier(&config.index_column),
+            U::affinity()
+        )];
+        for header in &self.headers {
+            columns.push(format!(
+                "{} {} NOT NULL",
+                quote_identifier(header),
+                V::affinity()
+            ));
+        }
+
+        let connection = config.connection()?;
+        connection.execute(
+            &format!(
+                "CREATE TABLE {} ({})",
+                quote_identifier(&config.table),
+                columns.join(", ")
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Inserts all rows inside a single transaction.
+    ///
+    /// The SQL table needs a column for the index and every header, e.g. made by
+    /// `init_sqlite_columns`. Other columns must have a default value.
+    pub fn save_sqlite_columns(&self, config: &SqliteConfig) -> Result<(), SqliteError> {
+        let columns: Vec<String> = std::iter::once(&config.index_column)
+            .chain(self.headers.iter())
+            .map(|x| quote_identifier(x))
+            .collect();
+        let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+
+        let mut connection = config.connection()?;
+        let transaction = connection.transaction()?;
+        {
+            let mut stmt = transaction.prepare(&format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                quote_identifier(&config.table),
+                columns.join(", "),
+                placeholders.join(", ")
+            ))?;
+            for (k, v) in self.iter() {
+                let data =
+                    std::iter::once(k as &dyn ToSql).chain(v.iter().map(|x| x as &dyn ToSql));
+                stmt.execute(params_from_iter(data))?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Loads the SQL table `config.table`, which does not have to be made by this crate.
+    ///
+    /// `config.index_column` becomes the index and all other columns become
+    /// headers, in the order of the table. Fails with `SqliteError::Table`
+    /// listing the index values found in more than one row.
+    pub fn from_sqlite_columns(config: &SqliteConfig) -> Result<Table<U, V>, SqliteError> {
+        let connection = config.connection()?;
+        let mut stmt = connection.prepare(&format!(
+            "SELECT * FROM {}",
+            quote_identifier(&config.table)
+        ))?;
+        let index = stmt
+            .column_names()
+            .iter()
+            .position(|x| *x == config.index_column)
+            .ok_or_else(|| SqliteError::ColumnDoesNotExist(config.index_column.clone()))?;
+        Self::from_sqlite_statement(&mut stmt, index)
+    }
+
+    /// Builds a table from any query. The first column is the index and the
+    /// other columns become headers. Like `from_sqlite_columns`, the index
+    /// values have to be unique.
+    ///
+    /// ```ignore
+    /// let t: Table<i64, f64> =
+    ///     Table::from_sqlite_query(&config, "SELECT day, open, close FROM prices WHERE close > 10")?;
+    /// ```
+    pub fn from_sqlite_query(
+        config: &SqliteConfig,
+        query: &str,
+    ) -> Result<Table<U, V>, SqliteError> {
+        let connection = config.connection()?;
+        let mut stmt = connection.prepare(query)?;
+        Self::from_sqlite_statement(&mut stmt, 0)
+    }
+
+    /// reads the rows of `stmt`, with the column at `index` as index
+    fn from_sqlite_statement(
+        stmt: &mut Statement,
+        index: usize,
+    ) -> Result<Table<U, V>, SqliteError> {
+        let headers: Vec<String> = stmt
+            .column_names()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, x)| x.to_string())
+            .collect();
+        let column_count = stmt.column_count();
+
+        let mut btable = BTreeMap::new();
+        let mut collided = Vec::new();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let mut values = Vec::with_capacity(headers.len());
+            for i in (0..column_count).filter(|i| *i != index) {
+                values.push(row.get(i)?);
+            }
+            Table::insert_row(
+                &mut btable,
+                row.get(index)?,
+                values,
+                &DuplicatePolicy::Error,
+                &mut collided,
+            );
+        }
+        if !collided.is_empty() {
+            return Err(TableError::duplicates(collided).into());
+        }
+        Ok(Table::new_btreemap(headers, btable))
+    }
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "_db_base")]
+
+use crate::{DbChanges, DbTableError, Table};
+use std::ops::Bound;
+
+#[cfg(feature = "memory_db")]
+use crate::MemoryError;
+#[cfg(feature = "postgresql_db")]
+use crate::PostgresqlError;
+#[cfg(feature = "redis_db")]
+use crate::RedisError;
+#[cfg(feature = "sqlite_db")]
+use crate::SqliteError;
+#[cfg(feature = "unqlite_db")]
+use crate::UnqliteError;
+
+/// Error returned by every [`TableStore`].
+///
+/// Table level errors are lifted out of the backend errors, so
+/// `StoreError::DbTableError(DbTableError::DbExists)` means the same for all backends.
+#[derive(Debug)]
+pub enum StoreError {
+    DbTableError(DbTableError),
+    #[cfg(feature = "memory_db")]
+    Memory(MemoryError),
+    #[cfg(feature = "postgresql_db")]
+    Postgresql(PostgresqlError),
+    #[cfg(feature = "redis_db")]
+    Redis(RedisError),
+    #[cfg(feature = "sqlite_db")]
+    Sqlite(SqliteError),
+    #[cfg(feature = "unqlite_db")]
+    Unqlite(UnqliteError),
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<DbTableError> for StoreError {
+    fn from(err: DbTableError) -> StoreError {
+        StoreError::DbTableError(err)
+    }
+}
+
+#[cfg(feature = "memory_db")]
+impl From<MemoryError> for StoreError {
+    fn from(err: MemoryError) -> StoreError {
+        match err {
+            MemoryError::DbTableError(x) => StoreError::DbTableError(x),
+            x => StoreError::Memory(x),
+        }
+    }
+}
+
+#[cfg(feature = "postgresql_db")]
+impl From<PostgresqlError> for StoreError {
+    fn from(err: PostgresqlError) -> StoreError {
+        match err {
+            PostgresqlError::DbTableError(x) => StoreError::DbTableError(x),
+            x => StoreError::Postgresql(x),
+        }
+    }
+}
+
+#[cfg(feature = "redis_db")]
+impl From<RedisError> for StoreError {
+    fn from(err: RedisError) -> StoreError {
+        match err {
+            RedisError::DbTableError(x) => StoreError::DbTableError(x),
+            x => StoreError::Redis(x),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite_db")]
+impl From<SqliteError> for StoreError {
+    fn from(err: SqliteError) -> StoreError {
+        match err {
+            SqliteError::DbTableError(x) => StoreError::DbTableError(x),
+            x => StoreError::Sqlite(x),
+        }
+    }
+}
+
+#[cfg(feature = "unqlite_db")]
+impl From<UnqliteError> for StoreError {
+    fn from(err: UnqliteError) -> StoreError {
+        match err {
+            UnqliteError::DbTableError(x) => StoreError::DbTableError(x),
+            x => StoreError::Unqlite(x),
+        }
+    }
+}
+
+/// Storage backend for a [`Table`], implemented by the config of every database backend.
+///
+/// The trait is object safe, so the backend can be chosen at runtime with
+/// `Box<dyn TableStore<U, V>>`. The backend specific methods like `save_sqlite`
+/// stay available and return the backend error.
+pub trait TableStore<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+    V: std::fmt::Debug,
+{
+    /// prepares the store, e.g. creates the SQL table
+    fn init(&self) -> Result<(), StoreError>;
+    /// writes the whole table, fails with `DbExists` when a table was saved before
+    fn save(&self, table: &Table<U, V>) -> Result<(), StoreError>;
+    /// Writes the rows that changed and deletes the stored rows that are no
+    /// longer in the table, so the store holds exactly `table` afterwards.
+    ///
+    /// Fails with `DbDoesNotExist` when no table was saved, and returns the
+    /// keys that were inserted, updated and deleted.
+    fn update(&self, table: &Table<U, V>) -> Result<DbChanges<U>, StoreError>;
+    fn load(&self) -> Result<Table<U, V>, StoreError>;
+    /// Loads the rows whose index falls in `range`.
+    ///
+    /// Any range can be passed with `(range.start_bound(), range.end_bound())`.
+    fn load_range(&self, range: (Bound<&U>, Bound<&U>)) -> Result<Table<U, V>, StoreError>;
+    /// removes the table and all its rows, the inverse of `init` and `save`
+    fn delete(&self) -> Result<(), StoreError>;
+    /// true if a table was saved in the store
+    fn exists(&self) -> Result<bool, StoreError>;
+}
@@ -2,7 +2,7 @@ use core::borrow::Borrow;
 use core::ops::RangeBounds;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use crate::enums::IndexOrColumn;
+use crate::enums::{DuplicatePolicy, IndexOrColumn};
 use crate::traits::{BtreeMapTrait, TableMetaTrait, TableTrait};
 use crate::TableError;
 
@@ -68,12 +68,42 @@ where
         }
     }
 
+    /// creates a table, an index value that is found more than once is an error listing the collided keys.
+    /// Use `new_with_duplicates` to keep the first or last row or to aggregate them instead
     pub fn new(
         headers: Vec<String>,
         indexes: Vec<U>,
         data: Vec<Vec<V>>,
     ) -> Result<Table<U, V>, TableError> {
-        let data = Self::to_btreemap(indexes, data)?;
+        Self::new_with_duplicates(headers, indexes, data, DuplicatePolicy::Error)
+    }
+
+    /// creates a table, rows with the same index value are handled with `duplicates`
+    ///
+    ///```
+    /// use timeseries::{vec2, Table};
+    /// use timeseries::enums::DuplicatePolicy;
+    ///
+    /// let headers = vec![String::from("count")];
+    /// let data = vec2![[1], [2], [3]];
+    ///
+    /// let t = Table::new_with_duplicates(
+    ///     headers,
+    ///     vec![1, 2, 1],
+    ///     data,
+    ///     DuplicatePolicy::Aggregate(Box::new(|a, b| a + b)),
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(Some(&vec![4]), t.data.get(&1));
+    ///```
+    pub fn new_with_duplicates(
+        headers: Vec<String>,
+        indexes: Vec<U>,
+        data: Vec<Vec<V>>,
+        duplicates: DuplicatePolicy<V>,
+    ) -> Result<Table<U, V>, TableError> {
+        let data = Self::to_btreemap(indexes, data, &duplicates)?;
         Self::check_headers(&data, &headers)?;
         Ok(Table::new_btreemap(headers, data))
     }
@@ -210,8 +240,12 @@ where
         header_set.into_iter().collect()
     }
 
-    fn to_btreemap(indexes: Vec<U>, data: Vec<Vec<V>>) -> Result<BTreeMap<U, Vec<V>>, TableError> {
-        let len = match data.get(0) {
+    fn to_btreemap(
+        indexes: Vec<U>,
+        data: Vec<Vec<V>>,
+        duplicates: &DuplicatePolicy<V>,
+    ) -> Result<BTreeMap<U, Vec<V>>, TableError> {
+        let len = match data.first() {
             Some(x) => x.len(),
             None => return Err(TableError::new("data is empty")),
         };
@@ -220,16 +254,48 @@ where
             return Err(TableError::new("time and data length should be equal"));
         }
 
-        for (k, v) in indexes.into_iter().zip(data.into_iter()) {
+        let mut collided = Vec::new();
+        for (k, v) in indexes.into_iter().zip(data) {
             if v.len() != len {
                 return Err(TableError::new("all rows should have equal length"));
             }
-            tree_data.insert(k, v);
+            Self::insert_row(&mut tree_data, k, v, duplicates, &mut collided);
+        }
+        if !collided.is_empty() {
+            return Err(TableError::duplicates(collided));
         }
         Ok(tree_data)
     }
 
-    fn check_headers(data: &BTreeMap<U, Vec<V>>, headers: &Vec<String>) -> Result<(), TableError> {
+    /// inserts a row, if the key is already in the data the policy decides what happens.
+    /// Keys that collide with the `Error` policy are added to `collided`
+    pub(crate) fn insert_row(
+        data: &mut BTreeMap<U, Vec<V>>,
+        k: U,
+        row: Vec<V>,
+        duplicates: &DuplicatePolicy<V>,
+        collided: &mut Vec<String>,
+    ) {
+        match data.entry(k) {
+            Entry::Vacant(entry) => {
+                entry.insert(row);
+            }
+            Entry::Occupied(mut entry) => match duplicates {
+                DuplicatePolicy::Error => collided.push(format!("{:?}", entry.key())),
+                DuplicatePolicy::KeepFirst => (),
+                DuplicatePolicy::KeepLast => {
+                    entry.insert(row);
+                }
+                DuplicatePolicy::Aggregate(f) => {
+                    for (kept, duplicate) in entry.get_mut().iter_mut().zip(&row) {
+                        *kept = f(kept, duplicate);
+                    }
+                }
+            },
+        }
+    }
+
+    fn check_headers(data: &BTreeMap<U, Vec<V>>, headers: &[String]) -> Result<(), TableError> {
         let len = match data.values().next() {
             Some(x) => x.len(),
             None => return Err(TableError::new("data is empty")),
@@ -309,12 +375,7 @@ where
             let header = p.header.to_owned();
 
             for (_, v) in p {
-                let s;
-                if v > &avg {
-                    s = v.to_owned() - avg.to_owned();
-                } else {
-                    s = v.to_owned() - avg.to_owned();
-                }
+                let s = v.to_owned() - avg.to_owned();
                 result = result + s.clone() * s;
             }
             t.push((header, result.to_owned() / len.clone()))
@@ -322,6 +383,61 @@ where
         t
     }
 
+    /// new table with the rows for which `f` returns true
+    ///
+    ///```
+    /// use timeseries::{vec2, Table};
+    ///
+    /// let headers = vec![String::from("temperature"), String::from("humidity")];
+    /// let t = Table::new(headers, vec![1, 2, 3], vec2![[25, 60], [31, 40], [35, 20]]).unwrap();
+    ///
+    /// let hot = t.filter(|row| row["temperature"] > 30);
+    ///
+    /// assert_eq!(vec![&2, &3], hot.data.keys().collect::<Vec<_>>());
+    ///```
+    pub fn filter<F>(&self, f: F) -> Table<U, V>
+    where
+        U: Clone,
+        V: Clone,
+        F: Fn(&Row<'_, U, V, String>) -> bool,
+    {
+        let data = self
+            .iter_rows()
+            .filter(|row| f(row))
+            .map(|row| (row.index.clone(), row.values().to_vec()))
+            .collect();
+
+        let mut table = Table::new_btreemap(self.headers.clone(), data);
+        table.meta_data = self.meta_data.clone();
+        table
+    }
+
+    /// keeps only the rows for which `f` returns true
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: Fn(&Row<'_, U, V, String>) -> bool,
+    {
+        let headers = &self.headers;
+        self.data
+            .retain(|k, values| f(&Row::new(k, headers, values)));
+    }
+
+    /// position of a column by name or position,
+    /// returns an error if the column does not exist
+    pub fn column_index<X: Into<IndexOrColumn>>(&self, column: X) -> Result<usize, TableError> {
+        match column.into() {
+            IndexOrColumn::Column(name) => self
+                .headers
+                .iter()
+                .position(|x| x == &name)
+                .ok_or_else(|| TableError::new(format!("column {} not found", name))),
+            IndexOrColumn::Index(x) if x < self.headers.len() => Ok(x),
+            IndexOrColumn::Index(x) => {
+                Err(TableError::new(format!("column index {} is too high", x)))
+            }
+        }
+    }
+
     pub fn iter_rows(&self) -> IterRows<'_, U, V, String> {
         IterRows {
             iter: self.data.iter(),
@@ -415,30 +531,71 @@ pub struct IterRows<'a, K, V, H> {
     pub iter: Iter<'a, K, Vec<V>>,
 }
 
+/// A row of a table, iterates over the (header, value) pairs.
+/// Values can also be looked up by header with `row["header"]` or `row.get("header")`.
 #[derive(Debug)]
 pub struct Row<'a, K, V, H> {
     pub index: &'a K,
-    headers: std::slice::Iter<'a, H>,
-    values: std::slice::Iter<'a, V>,
+    headers: &'a [H],
+    values: &'a [V],
+    position: usize,
+}
+
+impl<'a, K, V, H> Row<'a, K, V, H> {
+    pub(crate) fn new(index: &'a K, headers: &'a [H], values: &'a [V]) -> Row<'a, K, V, H> {
+        Row {
+            index,
+            headers,
+            values,
+            position: 0,
+        }
+    }
+
+    /// the value of the column with the header, None if there is no such column
+    pub fn get<Q>(&self, header: &Q) -> Option<&'a V>
+    where
+        H: Borrow<Q>,
+        Q: ?Sized + PartialEq,
+    {
+        let position = self.headers.iter().position(|h| h.borrow() == header)?;
+        self.values.get(position)
+    }
+
+    /// all the values of the row
+    pub fn values(&self) -> &'a [V] {
+        self.values
+    }
+}
+
+impl<K, V, H> std::ops::Index<&str> for Row<'_, K, V, H>
+where
+    H: Borrow<str>,
+{
+    type Output = V;
+
+    /// panics if there is no column with the header
+    fn index(&self, header: &str) -> &V {
+        match self.get(header) {
+            Some(x) => x,
+            None => panic!("column {} not found", header),
+        }
+    }
 }
 
-impl<'a, 'b, K, V, H> Iterator for IterRows<'a, K, V, H> {
+impl<'a, K, V, H> Iterator for IterRows<'a, K, V, H> {
     type Item = Row<'a, K, V, H>;
     fn next(&mut self) -> Option<<Self as Iterator>::Item> {
         let (k, values) = self.iter.next()?;
-        Some(Row {
-            headers: self.headers.iter(),
-            index: k,
-            values: values.iter(),
-        })
+        Some(Row::new(k, self.headers, values))
     }
 }
 
 impl<'a, K, V, H> Iterator for Row<'a, K, V, H> {
     type Item = (&'a H, &'a V);
     fn next(&mut self) -> Option<<Self as Iterator>::Item> {
-        let h = self.headers.next()?;
-        let v = self.values.next()?;
+        let h = self.headers.get(self.position)?;
+        let v = self.values.get(self.position)?;
+        self.position += 1;
         Some((h, v))
     }
 }
@@ -555,51 +712,51 @@ where
     fn clear(&mut self) {
         self.data.clear()
     }
-    fn get<Q: ?Sized>(&self, key: &Q) -> Option<&Vec<V>>
+    fn get<Q>(&self, key: &Q) -> Option<&Vec<V>>
     where
         U: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized + Ord,
     {
         self.data.get(key)
     }
-    fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+    fn contains_key<Q>(&self, key: &Q) -> bool
     where
         U: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized + Ord,
     {
         self.data.contains_key(key)
     }
-    fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut Vec<V>>
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Vec<V>>
     where
         U: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized + Ord,
     {
         self.data.get_mut(key)
     }
     fn insert(&mut self, key: U, value: Vec<V>) -> Option<Vec<V>> {
         self.data.insert(key, value)
     }
-    fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<Vec<V>>
+    fn remove<Q>(&mut self, key: &Q) -> Option<Vec<V>>
     where
         U: Borrow<Q>,
-        Q: Ord,
+        Q: ?Sized + Ord,
     {
         self.data.remove(key)
     }
     fn append(&mut self, other: &mut Self) {
         self.data.append(&mut other.data)
     }
-    fn range<T: ?Sized, R>(&self, range: R) -> Range<'_, U, Vec<V>>
+    fn range<T, R>(&self, range: R) -> Range<'_, U, Vec<V>>
     where
-        T: Ord,
+        T: ?Sized + Ord,
         U: Borrow<T>,
         R: RangeBounds<T>,
     {
         self.data.range(range)
     }
-    fn range_mut<T: ?Sized, R>(&mut self, range: R) -> RangeMut<'_, U, Vec<V>>
+    fn range_mut<T, R>(&mut self, range: R) -> RangeMut<'_, U, Vec<V>>
     where
-        T: Ord,
+        T: ?Sized + Ord,
         U: Borrow<T>,
         R: RangeBounds<T>,
     {
@@ -648,9 +805,9 @@ where
     U: std::fmt::Debug + std::cmp::Ord + Clone,
     V: std::fmt::Debug + Clone,
 {
-    fn slice_owned<T: ?Sized, R>(&self, range: R) -> Table<U, V>
+    fn slice_owned<T, R>(&self, range: R) -> Table<U, V>
     where
-        T: Ord,
+        T: ?Sized + Ord,
         U: Borrow<T>,
         R: RangeBounds<T>,
     {
@@ -660,9 +817,9 @@ where
         }
         Table::new_btreemap(self.headers.to_owned(), t2)
     }
-    fn slice_inplace<T: ?Sized, R>(&mut self, range: R)
+    fn slice_inplace<T, R>(&mut self, range: R)
     where
-        T: Ord,
+        T: ?Sized + Ord,
         U: Borrow<T>,
         R: RangeBounds<T>,
     {
@@ -722,4 +879,129 @@ where
         self.headers.swap(a, b);
         Ok(())
     }
+
+    // `<Vec<V> as IntoIterator>::Item` is the cell type `V`, written out to match the trait
+
+    /// adds a column at the end, `values` should have a value for every row in index order
+    fn add_column<S: Into<String>>(
+        &mut self,
+        name: S,
+        values: Vec<<Vec<V> as IntoIterator>::Item>,
+    ) -> Result<(), TableError> {
+        let name = name.into();
+        if self.headers.contains(&name) {
+            return Err(TableError::new(format!("column {} already exists", name)));
+        }
+        if values.len() != self.data.len() {
+            return Err(TableError::new("column should have a value for every row"));
+        }
+
+        for (row, v) in self.data.values_mut().zip(values) {
+            row.push(v);
+        }
+        self.headers.push(name);
+        Ok(())
+    }
+
+    /// adds a column at the end, computed from every row
+    ///
+    ///```
+    /// use timeseries::{vec2, Table, TableTrait};
+    ///
+    /// let headers = vec![String::from("a"), String::from("b")];
+    /// let mut t = Table::new(headers, vec![1, 2], vec2![[1, 2], [3, 4]]).unwrap();
+    ///
+    /// t.add_column_with("sum", |row| row.map(|(_header, v)| v).sum()).unwrap();
+    ///
+    /// assert_eq!(Some(&vec![3, 4, 7]), t.data.get(&2));
+    ///```
+    fn add_column_with<S, F>(&mut self, name: S, f: F) -> Result<(), TableError>
+    where
+        S: Into<String>,
+        F: Fn(Row<'_, U, <Vec<V> as IntoIterator>::Item, String>) -> <Vec<V> as IntoIterator>::Item,
+    {
+        let values = self.iter_rows().map(f).collect();
+        self.add_column(name, values)
+    }
+
+    /// takes a string or usize and removes the column
+    fn drop_column<X: Into<IndexOrColumn>>(&mut self, column: X) -> Result<(), TableError> {
+        let index = self.column_index(column)?;
+        for row in self.data.values_mut() {
+            row.remove(index);
+        }
+        self.headers.remove(index);
+        Ok(())
+    }
+
+    /// takes a string or usize and gives the column a new name
+    fn rename_column<X: Into<IndexOrColumn>, S: Into<String>>(
+        &mut self,
+        column: X,
+        name: S,
+    ) -> Result<(), TableError> {
+        let index = self.column_index(column)?;
+        let name = name.into();
+        if self
+            .headers
+            .iter()
+            .enumerate()
+            .any(|(i, h)| i != index && h == &name)
+        {
+            return Err(TableError::new(format!("column {} already exists", name)));
+        }
+        self.headers[index] = name;
+        Ok(())
+    }
+
+    /// new table with only the given columns, in the given order
+    fn select(&self, columns: &[IndexOrColumn]) -> Result<Table<U, V>, TableError> {
+        let indexes = self.column_indexes(columns)?;
+        let headers = indexes.iter().map(|i| self.headers[*i].clone()).collect();
+        let data = self
+            .data
+            .iter()
+            .map(|(k, row)| (k.clone(), indexes.iter().map(|i| row[*i].clone()).collect()))
+            .collect();
+
+        let mut table = Table::new_btreemap(headers, data);
+        table.meta_data = self.meta_data.clone();
+        Ok(table)
+    }
+
+    /// puts the columns in the given order, every column should be in the order exactly once
+    fn reorder_columns(&mut self, order: &[IndexOrColumn]) -> Result<(), TableError> {
+        let indexes = self.column_indexes(order)?;
+        if indexes.len() != self.headers.len() {
+            return Err(TableError::new("order should contain every column"));
+        }
+
+        self.headers = indexes.iter().map(|i| self.headers[*i].clone()).collect();
+        for row in self.data.values_mut() {
+            *row = indexes.iter().map(|i| row[*i].clone()).collect();
+        }
+        Ok(())
+    }
+}
+
+impl<U, V> Table<U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+    V: std::fmt::Debug,
+{
+    /// positions of the columns, returns an error if a column is unknown or given twice
+    fn column_indexes(&self, columns: &[IndexOrColumn]) -> Result<Vec<usize>, TableError> {
+        let mut indexes = Vec::with_capacity(columns.len());
+        for column in columns {
+            let index = self.column_index(column.clone())?;
+            if indexes.contains(&index) {
+                return Err(TableError::new(format!(
+                    "column {} is selected more than once",
+                    self.headers[index]
+                )));
+            }
+            indexes.push(index);
+        }
+        Ok(indexes)
+    }
 }
@@ -1,4 +1,3 @@
-use crate::vec2;
 use crate::Table;
 use std::collections::HashMap;
 
@@ -56,14 +55,14 @@ fn new_table_large<'a>() -> Table<u8, &'a str> {
     Table::new(headers, indexes, d).unwrap()
 }
 
-fn new_table_data<'a>() -> Table<u8, i32> {
+fn new_table_data() -> Table<u8, i32> {
     let headers = vec![s!("p10"), s!("data"), s!("twentyfive"), s!("squares")];
 
     let indexes = vec![1, 2, 3, 4, 5, 6];
     let d = vec2![
-        [10, 10, 25, 01],
-        [20, 23, 25, 04],
-        [30, 36, 25, 09],
+        [10, 10, 25, 1],
+        [20, 23, 25, 4],
+        [30, 36, 25, 9],
         [40, 49, 25, 16],
         [50, 51, 25, 25],
         [60, 68, 25, 36],
@@ -72,14 +71,15 @@ fn new_table_data<'a>() -> Table<u8, i32> {
     Table::new(headers, indexes, d).unwrap()
 }
 
-fn new_table_float_data<'a>() -> Table<u8, f32> {
+#[cfg(any(feature = "num", feature = "rayon"))]
+fn new_table_float_data() -> Table<u8, f32> {
     let headers = vec![s!("p10"), s!("data"), s!("twentyfive"), s!("squares")];
 
     let indexes = vec![1, 2, 3, 4, 5, 6];
     let d = vec2![
-        [10.0, 10.0, 25.0, 01.0],
-        [20.0, 23.0, 25.0, 04.0],
-        [30.0, 36.0, 25.0, 09.0],
+        [10.0, 10.0, 25.0, 1.0],
+        [20.0, 23.0, 25.0, 4.0],
+        [30.0, 36.0, 25.0, 9.0],
         [40.0, 49.0, 25.0, 16.0],
         [50.0, 51.0, 25.0, 25.0],
         [60.0, 68.0, 25.0, 36.0],
@@ -159,6 +159,61 @@ mod array_test {
         assert_eq!(t1, t2);
     }
 
+    #[test]
+    fn table_borrowed_keys() {
+        use crate::BtreeMapTrait;
+
+        let mut t = Table::new(
+            vec![s!("h1")],
+            vec![s!("a"), s!("b"), s!("c")],
+            vec2![[1], [2], [3]],
+        )
+        .unwrap();
+        assert!(BtreeMapTrait::contains_key(&t, "a"));
+        assert_eq!(Some(&vec![2]), BtreeMapTrait::get(&t, "b"));
+        BtreeMapTrait::get_mut(&mut t, "b").unwrap()[0] = 4;
+        assert_eq!(Some(vec![4]), BtreeMapTrait::remove(&mut t, "b"));
+
+        let t2 = BtreeMapTrait::split_off(&mut t, "c");
+        assert_eq!(vec![&s!("a")], t.keys().collect::<Vec<_>>());
+        assert_eq!(vec![&s!("c")], t2.keys().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn table_duplicate_index() {
+        use crate::enums::DuplicatePolicy;
+
+        let headers = vec![s!("h1")];
+        let indexes = vec![1, 2, 1, 3, 2, 1];
+        let d = vec2![[1], [2], [3], [4], [5], [6]];
+
+        let error = Table::new(headers.clone(), indexes.clone(), d.clone()).unwrap_err();
+        assert_eq!(&[s!("1"), s!("2")], error.keys());
+        assert_eq!("duplicate index values: 1, 2", error.to_string());
+
+        let new = |policy| {
+            Table::new_with_duplicates(headers.clone(), indexes.clone(), d.clone(), policy).unwrap()
+        };
+
+        let first = new(DuplicatePolicy::KeepFirst);
+        assert_eq!(
+            vec![&vec![1], &vec![2], &vec![4]],
+            first.data.values().collect::<Vec<_>>()
+        );
+
+        let last = new(DuplicatePolicy::KeepLast);
+        assert_eq!(
+            vec![&vec![6], &vec![5], &vec![4]],
+            last.data.values().collect::<Vec<_>>()
+        );
+
+        let sum = new(DuplicatePolicy::Aggregate(Box::new(|a, b| a + b)));
+        assert_eq!(
+            vec![&vec![10], &vec![7], &vec![4]],
+            sum.data.values().collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn table_btree_trait_iter() {
         use crate::BtreeMapTrait;
@@ -403,7 +458,9 @@ mod array_test {
 
 #[cfg(test)]
 mod folding_test {
-    use super::{new_table_data, new_table_float_data, new_table_large};
+    #[cfg(any(feature = "num", feature = "rayon"))]
+    use super::new_table_float_data;
+    use super::{new_table_data, new_table_large};
 
     macro_rules! assert_float_eq {
         ($x:expr, $y:expr) => {
@@ -593,12 +650,10 @@ mod folding_test {
 
         assert_float_eq!(expected, output);
     }
-
 }
 
 #[cfg(all(test, feature = "serialize"))]
 mod serde_testing {
-    use crate::vec2;
     use crate::Table;
     use serde_test::{assert_tokens, Token};
 
@@ -691,6 +746,1294 @@ mod serde_testing {
         let t = new_table_large();
         let expected = serde_tokens();
 
-        assert_tokens(&t, &expected);
+        assert_tokens(&t, expected);
+    }
+}
+
+#[cfg(all(test, feature = "serialize"))]
+mod file_test {
+    use super::new_table_data;
+    use crate::{SnapshotError, Table, TableMetaTrait};
+    use std::collections::HashMap;
+
+    #[test]
+    fn save_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.snapshot");
+
+        let mut t = new_table_data();
+        let mut meta_data = HashMap::new();
+        meta_data.insert(String::from("source"), String::from("test"));
+        t.set_meta_data(meta_data);
+
+        t.save_file(&path).unwrap();
+        let t1: Table<u8, i32> = Table::load_file(&path).unwrap();
+        assert_eq!(t, t1);
+
+        // the temporary file is renamed away
+        assert_eq!(1, std::fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[test]
+    fn overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.snapshot");
+
+        let t = new_table_data();
+        t.save_file(&path).unwrap();
+        let t = t.filter(|row| row["data"] > 30);
+        t.save_file(&path).unwrap();
+        let t1: Table<u8, i32> = Table::load_file(&path).unwrap();
+        assert_eq!(t, t1);
+    }
+
+    #[test]
+    fn corrupted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.snapshot");
+        new_table_data().save_file(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        let mut flipped = bytes.clone();
+        let last = flipped.len() - 1;
+        flipped[last] ^= 1;
+        std::fs::write(&path, &flipped).unwrap();
+        match Table::<u8, i32>::load_file(&path) {
+            Err(SnapshotError::ChecksumMismatch) => (),
+            x => panic!("expected ChecksumMismatch, got {:?}", x),
+        }
+
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        match Table::<u8, i32>::load_file(&path) {
+            Err(SnapshotError::Truncated) => (),
+            x => panic!("expected Truncated, got {:?}", x),
+        }
+
+        let mut newer = bytes.clone();
+        newer[8] = 2;
+        std::fs::write(&path, &newer).unwrap();
+        match Table::<u8, i32>::load_file(&path) {
+            Err(SnapshotError::UnsupportedVersion(2)) => (),
+            x => panic!("expected UnsupportedVersion, got {:?}", x),
+        }
+
+        std::fs::write(&path, b"p10,data\n1,2\n").unwrap();
+        match Table::<u8, i32>::load_file(&path) {
+            Err(SnapshotError::NotASnapshot) => (),
+            x => panic!("expected NotASnapshot, got {:?}", x),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serialize"))]
+mod log_test {
+    use super::new_table_data;
+    use crate::{BtreeMapTrait, LogError, Table, TableLog};
+
+    fn headers() -> Vec<String> {
+        vec![s!("p10"), s!("data"), s!("twentyfive"), s!("squares")]
+    }
+
+    fn append_all(log: &mut TableLog<u8, i32>, t: &Table<u8, i32>) {
+        for (k, v) in t.iter() {
+            log.append(*k, v.clone()).unwrap();
+        }
+    }
+
+    #[test]
+    fn append_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.log");
+        let t = new_table_data();
+
+        let mut log = TableLog::open(&path, headers()).unwrap();
+        assert!(log.is_empty());
+        append_all(&mut log, &t);
+        log.append(2, vec![0, 0, 0, 0]).unwrap();
+        log.sync().unwrap();
+        assert_eq!(7, log.len());
+
+        let mut expected = t.clone();
+        expected.insert(2, vec![0, 0, 0, 0]);
+        assert_eq!(expected, log.replay().unwrap());
+
+        match log.append(8, vec![1, 2]) {
+            Err(LogError::Table(_)) => (),
+            x => panic!("expected a table error, got {:?}", x),
+        }
+
+        drop(log);
+        let log: TableLog<u8, i32> = TableLog::open(&path, headers()).unwrap();
+        assert_eq!(7, log.len());
+        assert_eq!(expected, log.replay().unwrap());
+    }
+
+    #[test]
+    fn torn_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.log");
+        let t = new_table_data();
+
+        let mut log = TableLog::open(&path, headers()).unwrap();
+        append_all(&mut log, &t);
+        assert_eq!(0, log.truncated());
+        drop(log);
+
+        // cuts the last record in half
+        let bytes = std::fs::read(&path).unwrap();
+        let torn_len = bytes.len() - 5;
+        std::fs::write(&path, &bytes[..torn_len]).unwrap();
+
+        let mut log: TableLog<u8, i32> = TableLog::open(&path, headers()).unwrap();
+        assert_eq!(5, log.len());
+        let valid_len = std::fs::metadata(&path).unwrap().len();
+        assert_eq!(torn_len as u64 - valid_len, log.truncated());
+
+        let mut expected = t.clone();
+        expected.remove(&6);
+        assert_eq!(expected, log.replay().unwrap());
+
+        // new records go after the valid part
+        log.append(6, vec![60, 68, 25, 36]).unwrap();
+        assert_eq!(t, log.replay().unwrap());
+    }
+
+    #[test]
+    fn compact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.log");
+        let t = new_table_data();
+
+        let mut log = TableLog::open(&path, headers()).unwrap();
+        append_all(&mut log, &t);
+        let log_len = std::fs::metadata(&path).unwrap().len();
+
+        assert_eq!(t, log.compact().unwrap());
+        assert!(log.is_empty());
+        assert!(std::fs::metadata(&path).unwrap().len() < log_len);
+        assert_eq!(t, Table::load_file(log.snapshot_path()).unwrap());
+
+        log.append(7, vec![70, 71, 25, 49]).unwrap();
+        let mut expected = t.clone();
+        expected.insert(7, vec![70, 71, 25, 49]);
+        assert_eq!(expected, log.replay().unwrap());
+
+        drop(log);
+        let log: TableLog<u8, i32> = TableLog::open(&path, headers()).unwrap();
+        assert_eq!(1, log.len());
+        assert_eq!(expected, log.replay().unwrap());
+    }
+
+    #[test]
+    fn wrong_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.log");
+        TableLog::<u8, i32>::open(&path, headers()).unwrap();
+
+        match TableLog::<u8, i32>::open(&path, vec![s!("p10")]) {
+            Err(LogError::HeaderMismatch(x)) => assert_eq!(headers(), x),
+            x => panic!("expected HeaderMismatch, got {:?}", x),
+        }
+
+        std::fs::write(&path, b"p10,data\n1,2\n").unwrap();
+        match TableLog::<u8, i32>::open(&path, headers()) {
+            Err(LogError::NotALog) => (),
+            x => panic!("expected NotALog, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn replay_replaced_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.log");
+        let t = new_table_data();
+
+        let mut log = TableLog::open(&path, headers()).unwrap();
+        append_all(&mut log, &t);
+        log.compact().unwrap();
+
+        // another table written to the snapshot
+        let other = Table::new(vec![s!("p10")], vec![1u8], vec![vec![10i32]]).unwrap();
+        other.save_file(log.snapshot_path()).unwrap();
+        match log.replay() {
+            Err(LogError::HeaderMismatch(x)) => assert_eq!(vec![s!("p10")], x),
+            x => panic!("expected HeaderMismatch, got {:?}", x),
+        }
+        std::fs::remove_file(log.snapshot_path()).unwrap();
+
+        // another log written to the path
+        std::fs::remove_file(&path).unwrap();
+        TableLog::<u8, i32>::open(&path, vec![s!("p10")]).unwrap();
+        match log.replay() {
+            Err(LogError::HeaderMismatch(x)) => assert_eq!(vec![s!("p10")], x),
+            x => panic!("expected HeaderMismatch, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn corrupt_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.log");
+        let t = new_table_data();
+
+        // offset of every row record
+        let mut log = TableLog::open(&path, headers()).unwrap();
+        let mut offsets = Vec::new();
+        for (k, v) in t.iter() {
+            offsets.push(std::fs::metadata(&path).unwrap().len());
+            log.append(*k, v.clone()).unwrap();
+        }
+        let bytes = std::fs::read(&path).unwrap();
+
+        // damages the last byte of the fourth record
+        let mut damaged = bytes.clone();
+        damaged[offsets[4] as usize - 1] ^= 0xff;
+        std::fs::write(&path, &damaged).unwrap();
+        match log.replay() {
+            Err(LogError::Corrupt(x)) => assert_eq!(offsets[3], x),
+            x => panic!("expected Corrupt, got {:?}", x),
+        }
+        drop(log);
+        match TableLog::<u8, i32>::open(&path, headers()) {
+            Err(LogError::Corrupt(x)) => assert_eq!(offsets[3], x),
+            x => panic!("expected Corrupt, got {:?}", x),
+        }
+        assert_eq!(damaged, std::fs::read(&path).unwrap());
+
+        // a damaged last record is a torn tail
+        let mut damaged = bytes.clone();
+        *damaged.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, &damaged).unwrap();
+        let log: TableLog<u8, i32> = TableLog::open(&path, headers()).unwrap();
+        assert_eq!(5, log.len());
+        assert_eq!(offsets[5], std::fs::metadata(&path).unwrap().len());
+    }
+
+    #[test]
+    fn damaged_headers_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.log");
+
+        let mut log = TableLog::open(&path, headers()).unwrap();
+        let file_header_len = 12;
+        let base_len = std::fs::metadata(&path).unwrap().len() as usize;
+        log.append(1, vec![10, 11, 25, 1]).unwrap();
+        drop(log);
+        let bytes = std::fs::read(&path).unwrap();
+
+        // with rows after it
+        let mut damaged = bytes.clone();
+        damaged[base_len - 1] ^= 0xff;
+        std::fs::write(&path, &damaged).unwrap();
+        match TableLog::<u8, i32>::open(&path, headers()) {
+            Err(LogError::Corrupt(x)) => assert_eq!(file_header_len, x),
+            x => panic!("expected Corrupt, got {:?}", x),
+        }
+        assert_eq!(damaged, std::fs::read(&path).unwrap());
+
+        // alone, the log is not wiped either
+        std::fs::write(&path, &damaged[..base_len]).unwrap();
+        match TableLog::<u8, i32>::open(&path, headers()) {
+            Err(LogError::Corrupt(x)) => assert_eq!(file_header_len, x),
+            x => panic!("expected Corrupt, got {:?}", x),
+        }
+        assert_eq!(&damaged[..base_len], &std::fs::read(&path).unwrap()[..]);
+
+        // a file header without records is a new log
+        std::fs::write(&path, &bytes[..file_header_len as usize]).unwrap();
+        let log: TableLog<u8, i32> = TableLog::open(&path, headers()).unwrap();
+        assert!(log.is_empty());
+        assert_eq!(&bytes[..base_len], &std::fs::read(&path).unwrap()[..]);
+    }
+}
+
+#[cfg(test)]
+mod rolling_test {
+    use super::new_table_data;
+    use crate::Table;
+
+    #[cfg(feature = "num")]
+    fn new_table_wave() -> Table<u8, i32> {
+        let headers = vec![s!("wave"), s!("const")];
+
+        let indexes = vec![1, 2, 3, 4, 5, 6];
+        let d = vec2![[5, 7], [1, 7], [4, 7], [2, 7], [8, 7], [3, 7]];
+
+        Table::new(headers, indexes, d).unwrap()
+    }
+
+    fn column<V: std::fmt::Debug + Clone>(table: &Table<u8, V>, index: usize) -> Vec<V> {
+        table.data.values().map(|row| row[index].clone()).collect()
+    }
+
+    #[test]
+    fn rolling_with_sum() {
+        let t1 = new_table_data();
+        let output = t1
+            .rolling_with(3, |values| values.iter().map(|v| **v).sum())
+            .unwrap();
+
+        assert_eq!(t1.headers, output.headers);
+        assert_eq!(vec![10, 30, 60, 90, 120, 150], column(&output, 0));
+        assert_eq!(vec![1, 5, 14, 29, 50, 77], column(&output, 3));
+    }
+
+    #[test]
+    fn rolling_zero_window() {
+        let t1 = new_table_data();
+        assert!(t1.rolling_with(0, |values| *values[0]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn rolling_statistics() {
+        use crate::enums::RollingStatistic;
+
+        let t1 = new_table_wave();
+        let cases = vec![
+            (RollingStatistic::Sum, vec![5, 6, 10, 7, 14, 13], 21),
+            (RollingStatistic::Mean, vec![5, 3, 3, 2, 4, 4], 7),
+            (RollingStatistic::Min, vec![5, 1, 1, 1, 2, 2], 7),
+            (RollingStatistic::Max, vec![5, 5, 5, 4, 8, 8], 7),
+            (RollingStatistic::Median, vec![5, 3, 4, 2, 4, 3], 7),
+            (RollingStatistic::Std, vec![0, 2, 1, 1, 2, 2], 0),
+        ];
+        for (statistic, expected, constant) in cases {
+            let output = t1.rolling(3, statistic).unwrap();
+            let expected: Vec<Option<i32>> = expected.into_iter().map(Some).collect();
+            assert_eq!(expected, column(&output, 0), "{:?}", statistic);
+            assert_eq!(
+                Some(&Some(constant)),
+                column(&output, 1).last(),
+                "{:?}",
+                statistic
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn rolling_std_large_values() {
+        use crate::enums::RollingStatistic;
+
+        let indexes: Vec<u8> = (0..20).collect();
+        let d: Vec<Vec<f64>> = (0..20).map(|i| vec![1e9 + f64::from(i % 3)]).collect();
+        let t1 = Table::new(vec![s!("x")], indexes, d).unwrap();
+
+        // every full window holds 1e9, 1e9 + 1 and 1e9 + 2 in some order
+        let output = t1.rolling(3, RollingStatistic::Std).unwrap();
+        for std in column(&output, 0).into_iter().skip(2) {
+            assert!(
+                (std.unwrap() - (2.0f64 / 3.0).sqrt()).abs() < 1e-6,
+                "{:?}",
+                std
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn rolling_integer_overflow() {
+        use crate::enums::RollingStatistic;
+
+        let indexes: Vec<u16> = (0..300).collect();
+        let t1 = Table::new(vec![s!("x")], indexes, vec![vec![200u8]; 300]).unwrap();
+
+        // sums above 255 do not fit in a u8, the mean always does
+        let output = t1.rolling(300, RollingStatistic::Sum).unwrap();
+        assert_eq!(Some(&vec![Some(200)]), output.data.get(&0));
+        assert_eq!(Some(&vec![None]), output.data.get(&1));
+        let output = t1.rolling(300, RollingStatistic::Mean).unwrap();
+        assert_eq!(Some(&vec![Some(200)]), output.data.get(&299));
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn rolling_nan() {
+        use crate::enums::RollingStatistic;
+
+        let d = vec2![[1.0], [f64::NAN], [3.0], [2.0], [5.0], [4.0]];
+        let t1 = Table::new(vec![s!("x")], vec![1u8, 2, 3, 4, 5, 6], d).unwrap();
+
+        // windows holding the NaN give NaN, the windows after it are not affected
+        for (statistic, last) in [
+            (RollingStatistic::Sum, 11.0),
+            (RollingStatistic::Median, 4.0),
+            (RollingStatistic::Min, 2.0),
+            (RollingStatistic::Std, (14.0f64 / 9.0).sqrt()),
+        ] {
+            let output = column(&t1.rolling(3, statistic).unwrap(), 0);
+            assert!(output[1].unwrap().is_nan(), "{:?}", statistic);
+            assert!(output[3].unwrap().is_nan(), "{:?}", statistic);
+            assert!((output[5].unwrap() - last).abs() < 1e-9, "{:?}", statistic);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn rolling_matches_full_window() {
+        use crate::enums::RollingStatistic;
+
+        let headers = vec![s!("x")];
+        let indexes: Vec<u8> = (0..50).collect();
+        let d: Vec<Vec<i32>> = (0..50).map(|i| vec![(i * 37) % 11 - 5]).collect();
+        let t1 = Table::new(headers, indexes, d).unwrap();
+
+        let min = t1
+            .rolling_with(4, |values| values.iter().map(|v| **v).min().unwrap())
+            .unwrap();
+        let max = t1
+            .rolling_with(4, |values| values.iter().map(|v| **v).max().unwrap())
+            .unwrap();
+        let median = t1
+            .rolling_with(4, |values| {
+                let mut sorted: Vec<i32> = values.iter().map(|v| **v).collect();
+                sorted.sort();
+                let middle = sorted.len() / 2;
+                if sorted.len() % 2 == 1 {
+                    sorted[middle]
+                } else {
+                    (sorted[middle - 1] + sorted[middle]) / 2
+                }
+            })
+            .unwrap();
+
+        for (expected, statistic) in [
+            (min, RollingStatistic::Min),
+            (max, RollingStatistic::Max),
+            (median, RollingStatistic::Median),
+        ] {
+            let expected: Vec<Option<i32>> = column(&expected, 0).into_iter().map(Some).collect();
+            let output = t1.rolling(4, statistic).unwrap();
+            assert_eq!(expected, column(&output, 0), "{:?}", statistic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod typed_test {
+    use crate::{DType, Table, TypedTable, Value};
+
+    fn new_table_strings() -> Table<u8, String> {
+        let headers = vec![
+            s!("count"),
+            s!("ratio"),
+            s!("flag"),
+            s!("name"),
+            s!("extra"),
+        ];
+
+        let indexes = vec![1, 2, 3];
+        let d = vec2![
+            [s!("1"), s!("0.5"), s!("true"), s!("a"), s!("")],
+            [s!("2"), s!("1"), s!("false"), s!("b"), s!("3")],
+            [s!("3"), s!("1.5"), s!("true"), s!("4"), s!("")],
+        ];
+
+        Table::new(headers, indexes, d).unwrap()
+    }
+
+    #[test]
+    fn infer_dtypes() {
+        let t = new_table_strings().into_typed();
+
+        assert_eq!(
+            &[
+                DType::Int,
+                DType::Float,
+                DType::Bool,
+                DType::Str,
+                DType::Optional(Box::new(DType::Int))
+            ],
+            t.dtypes()
+        );
+        assert_eq!(&DType::Float, t.dtype("ratio").unwrap());
+        assert_eq!(&DType::Bool, t.dtype(2).unwrap());
+        assert!(t.dtype("missing").is_err());
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let t = new_table_strings().into_typed();
+
+        assert_eq!(Some(&2), t.get::<i64, _>(&2, "count").unwrap());
+        assert_eq!(Some(&1.0), t.get::<f64, _>(&2, "ratio").unwrap());
+        assert_eq!(Some(&false), t.get::<bool, _>(&2, "flag").unwrap());
+        assert_eq!(Some(&s!("4")), t.get::<String, _>(&3, "name").unwrap());
+        assert_eq!(None, t.get::<i64, _>(&3, "extra").unwrap());
+
+        // wrong type and unknown key
+        assert!(t.get::<f64, _>(&2, "count").is_err());
+        assert!(t.get::<i64, _>(&9, "count").is_err());
+
+        let extra: Vec<Option<&i64>> = t
+            .column::<i64, _>("extra")
+            .unwrap()
+            .into_iter()
+            .map(|(_, v)| v)
+            .collect();
+        assert_eq!(vec![None, Some(&3), None], extra);
+    }
+
+    #[test]
+    fn typed_table_checks_cells() {
+        let headers = vec![s!("count")];
+        let table = Table::new(headers, vec![1, 2], vec2![[Value::Int(1)], [Value::Null]]).unwrap();
+
+        assert!(TypedTable::new(table.clone(), vec![DType::Int]).is_err());
+        assert!(TypedTable::new(table.clone(), vec![]).is_err());
+        assert!(TypedTable::new(table, vec![DType::Optional(Box::new(DType::Int))]).is_ok());
+    }
+
+    #[test]
+    #[cfg(all(feature = "chrono", feature = "csv"))]
+    fn infer_csv_datetimes() {
+        use crate::read_csv_to_datetable;
+        use chrono::NaiveDateTime;
+
+        let data = "index,time,value\n1,2010-02-26 00:00:00.000,1.5\n2,2010-03-04 12:30:00.000,2\n";
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+        let t = read_csv_to_datetable(&mut rdr, |x| x.parse::<u8>())
+            .unwrap()
+            .into_typed();
+
+        assert_eq!(&[DType::DateTime, DType::Float], t.dtypes());
+        assert_eq!(
+            Some(&NaiveDateTime::parse_from_str("2010-03-04 12:30", "%F %H:%M").unwrap()),
+            t.get::<NaiveDateTime, _>(&2, "time").unwrap()
+        );
+    }
+}
+
+#[cfg(test)]
+mod missing_test {
+    use crate::enums::{Axis, FillMethod};
+    use crate::Table;
+
+    fn new_table_gaps() -> Table<u8, Option<i32>> {
+        let headers = vec![s!("a"), s!("b"), s!("c")];
+
+        let indexes = vec![1, 2, 3, 4, 5];
+        let d = vec2![
+            [None, Some(10), Some(1)],
+            [Some(2), None, Some(1)],
+            [None, None, Some(1)],
+            [Some(8), Some(40), Some(1)],
+            [Some(6), None, Some(1)],
+        ];
+
+        Table::new(headers, indexes, d).unwrap()
+    }
+
+    fn column(table: &Table<u8, Option<i32>>, index: usize) -> Vec<Option<i32>> {
+        table.data.values().map(|row| row[index]).collect()
+    }
+
+    #[test]
+    fn isna() {
+        let t = new_table_gaps();
+        assert_eq!(vec![(s!("a"), 2), (s!("b"), 3), (s!("c"), 0)], t.isna());
+    }
+
+    #[test]
+    fn fillna() {
+        let t = new_table_gaps();
+
+        let value = t.fillna(FillMethod::Value(0));
+        assert_eq!(
+            vec![Some(0), Some(2), Some(0), Some(8), Some(6)],
+            column(&value, 0)
+        );
+
+        let forward = t.fillna(FillMethod::Forward);
+        assert_eq!(
+            vec![None, Some(2), Some(2), Some(8), Some(6)],
+            column(&forward, 0)
+        );
+        assert_eq!(
+            vec![Some(10), Some(10), Some(10), Some(40), Some(40)],
+            column(&forward, 1)
+        );
+
+        let backward = t.fillna(FillMethod::Backward);
+        assert_eq!(
+            vec![Some(2), Some(2), Some(8), Some(8), Some(6)],
+            column(&backward, 0)
+        );
+        assert_eq!(
+            vec![Some(10), Some(40), Some(40), Some(40), None],
+            column(&backward, 1)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn interpolate() {
+        let t = new_table_gaps();
+        let output = t.interpolate();
+
+        assert_eq!(
+            vec![None, Some(2), Some(5), Some(8), Some(6)],
+            column(&output, 0)
+        );
+        assert_eq!(
+            vec![Some(10), Some(20), Some(30), Some(40), None],
+            column(&output, 1)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn interpolate_unsigned_decreasing() {
+        let t = Table::new(
+            vec![s!("a")],
+            vec![1, 2, 3],
+            vec2![[Some(10u32)], [None], [Some(4)]],
+        )
+        .unwrap();
+        let output = t.interpolate();
+
+        assert_eq!(
+            vec![Some(10), Some(7), Some(4)],
+            output.data.values().map(|row| row[0]).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn dropna() {
+        let t = new_table_gaps();
+
+        let rows = t.dropna(Axis::Row);
+        assert_eq!(
+            Table::new(vec![s!("a"), s!("b"), s!("c")], vec![4], vec2![[8, 40, 1]]).unwrap(),
+            rows
+        );
+
+        let columns = t.dropna(Axis::Column);
+        assert_eq!(&[s!("c")], &columns.headers[..]);
+        assert_eq!(Some(&vec![1]), columns.data.get(&5));
+    }
+
+    #[test]
+    fn sum_skipna() {
+        let t = new_table_gaps();
+        assert_eq!(
+            vec![(s!("a"), 16), (s!("b"), 50), (s!("c"), 5)],
+            t.sum_columns_skipna()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn avg_var_skipna() {
+        let headers = vec![s!("a"), s!("empty")];
+        let d = vec2![[Some(2.0), None], [None, None], [Some(4.0), None]];
+        let t: Table<u8, Option<f64>> = Table::new(headers, vec![1, 2, 3], d).unwrap();
+
+        assert_eq!(
+            vec![(s!("a"), Some(3.0)), (s!("empty"), None)],
+            t.avg_columns_skipna()
+        );
+        assert_eq!(
+            vec![(s!("a"), Some(1.0)), (s!("empty"), None)],
+            t.var_columns_skipna()
+        );
+    }
+}
+
+#[cfg(test)]
+mod join_test {
+    use crate::enums::JoinType;
+    use crate::Table;
+
+    fn left() -> Table<u8, i32> {
+        let headers = vec![s!("id"), s!("left")];
+        Table::new(headers, vec![1, 2, 3], vec2![[1, 10], [2, 20], [3, 30]]).unwrap()
+    }
+
+    fn right() -> Table<u8, i32> {
+        let headers = vec![s!("id"), s!("right")];
+        Table::new(headers, vec![2, 3, 4], vec2![[2, 200], [3, 300], [4, 400]]).unwrap()
+    }
+
+    #[test]
+    fn join_headers_suffixed() {
+        let t = left().join(&right(), JoinType::Inner);
+        assert_eq!(
+            vec![s!("id_left"), s!("left"), s!("id_right"), s!("right")],
+            t.headers
+        );
+
+        let t = left().join_suffixed(&right(), JoinType::Inner, "_x", "_y");
+        assert_eq!(
+            vec![s!("id_x"), s!("left"), s!("id_y"), s!("right")],
+            t.headers
+        );
+    }
+
+    #[test]
+    fn join_types() {
+        let headers = vec![s!("id_left"), s!("left"), s!("id_right"), s!("right")];
+
+        let inner = Table::new(
+            headers.clone(),
+            vec![2, 3],
+            vec2![
+                [Some(2), Some(20), Some(2), Some(200)],
+                [Some(3), Some(30), Some(3), Some(300)]
+            ],
+        )
+        .unwrap();
+        assert_eq!(inner, left().join(&right(), JoinType::Inner));
+
+        let left_join = left().join(&right(), JoinType::Left);
+        assert_eq!(vec![&1, &2, &3], left_join.data.keys().collect::<Vec<_>>());
+        assert_eq!(
+            Some(&vec![Some(1), Some(10), None, None]),
+            left_join.data.get(&1)
+        );
+
+        let right_join = left().join(&right(), JoinType::Right);
+        assert_eq!(vec![&2, &3, &4], right_join.data.keys().collect::<Vec<_>>());
+        assert_eq!(
+            Some(&vec![None, None, Some(4), Some(400)]),
+            right_join.data.get(&4)
+        );
+
+        let outer = left().join(&right(), JoinType::Outer);
+        assert_eq!(vec![&1, &2, &3, &4], outer.data.keys().collect::<Vec<_>>());
+        assert_eq!(
+            outer.isna(),
+            vec![
+                (s!("id_left"), 1),
+                (s!("left"), 1),
+                (s!("id_right"), 1),
+                (s!("right"), 1)
+            ]
+        );
+    }
+}
+
+#[cfg(all(test, feature = "csv"))]
+mod csv_test {
+    use crate::enums::DuplicatePolicy;
+    use crate::{read_csv, write_csv, Table, TableMetaTrait};
+
+    #[test]
+    fn read_csv_index_by_name() {
+        let data = "name,id,value\na,2,1.5\nb,1,2.5\n";
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+
+        let t = read_csv(|x| x.parse::<u8>())
+            .index("id")
+            .read(&mut rdr)
+            .unwrap();
+
+        let expected = Table::new(
+            vec![s!("name"), s!("value")],
+            vec![1, 2],
+            vec2![[s!("b"), s!("2.5")], [s!("a"), s!("1.5")]],
+        )
+        .unwrap();
+        assert_eq!(expected.data, t.data);
+        assert_eq!(expected.headers, t.headers);
+        assert_eq!(Some(&s!("id")), t.get_meta_key(&s!("time_column")));
+    }
+
+    #[test]
+    fn read_csv_headerless() {
+        let data = "1,10,x\n2,20,y\n";
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_bytes());
+
+        let t = read_csv(|x| x.parse::<u8>())
+            .index(1)
+            .values(|header, x| match header {
+                "column_0" => x.parse::<i32>().map_err(|e| e.to_string()),
+                _ => Ok(x.len() as i32),
+            })
+            .read(&mut rdr)
+            .unwrap();
+
+        assert_eq!(vec![s!("column_0"), s!("column_2")], t.headers);
+        assert_eq!(Some(&vec![2, 1]), t.data.get(&20));
+        assert_eq!(Some(&s!("column_1")), t.get_meta_key(&s!("time_column")));
+    }
+
+    #[test]
+    fn read_csv_errors() {
+        let data = "id,value\n1,a\nx,b\n";
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+        assert!(read_csv(|x| x.parse::<u8>()).read(&mut rdr).is_err());
+
+        let data = "id,value\n1,a\n";
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+        assert!(read_csv(|x| x.parse::<u8>())
+            .index("missing")
+            .read(&mut rdr)
+            .is_err());
+    }
+
+    #[test]
+    fn read_csv_duplicates() {
+        let data = "id,value\n1,a\n2,b\n1,c\n";
+        let read = |policy| {
+            let mut rdr = csv::Reader::from_reader(data.as_bytes());
+            read_csv(|x| x.parse::<u8>())
+                .duplicates(policy)
+                .read(&mut rdr)
+        };
+
+        let error = read(DuplicatePolicy::Error).unwrap_err();
+        assert_eq!("duplicate index values: 1", error.to_string());
+
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+        let joined = read_csv(|x| x.parse::<u8>())
+            .duplicates(DuplicatePolicy::Aggregate(Box::new(
+                |a: &String, b: &String| format!("{}{}", a, b),
+            )))
+            .read(&mut rdr)
+            .unwrap();
+        assert_eq!(Some(&vec![s!("ac")]), joined.data.get(&1));
+
+        let first = read(DuplicatePolicy::KeepFirst).unwrap();
+        assert_eq!(Some(&vec![s!("a")]), first.data.get(&1));
+
+        let last = read(DuplicatePolicy::KeepLast).unwrap();
+        assert_eq!(Some(&vec![s!("c")]), last.data.get(&1));
+    }
+
+    #[test]
+    fn read_csv_flexible_record_length() {
+        for data in &["a,b,id\nx,y,1\nx,2\n", "a,b,id\nx,y,1\nx,y,2,z\n"] {
+            let mut rdr = csv::ReaderBuilder::new()
+                .flexible(true)
+                .from_reader(data.as_bytes());
+            let error = read_csv(|x| x.parse::<u8>())
+                .index("id")
+                .read(&mut rdr)
+                .unwrap_err();
+            assert!(error.to_string().starts_with("record on line 3"));
+        }
+    }
+
+    #[test]
+    fn read_csv_aggregate_before_values() {
+        let data = "id,value\n1,1\n2,5\n1,2\n";
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+
+        let reader = read_csv(|x| x.parse::<u8>())
+            .duplicates(DuplicatePolicy::Aggregate(Box::new(|a, b| {
+                format!("{}{}", a, b)
+            })))
+            .values(|_, x| x.parse::<u32>());
+        assert!(reader.read(&mut rdr).is_err());
+
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+        let t = reader
+            .duplicates(DuplicatePolicy::Aggregate(Box::new(|a, b| a + b)))
+            .read(&mut rdr)
+            .unwrap();
+        assert_eq!(Some(&vec![3]), t.data.get(&1));
+        assert_eq!(Some(&vec![5]), t.data.get(&2));
+    }
+
+    #[test]
+    fn write_csv_default_index_header() {
+        let t = Table::new(vec![s!("a"), s!("b")], vec![2, 1], vec2![[3, 4], [1, 2]]).unwrap();
+
+        let mut wtr = csv::Writer::from_writer(vec![]);
+        write_csv(&mut wtr, &t, |k| k.to_string(), |v| v.to_string()).unwrap();
+
+        let output = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!("index,a,b\n1,1,2\n2,3,4\n", output);
+    }
+
+    #[test]
+    fn write_csv_time_column() {
+        let mut t = Table::new(vec![s!("a")], vec![1], vec2![["x"]]).unwrap();
+        t.set_meta_key(s!("time_column"), s!("timestamp"));
+
+        let mut wtr = csv::WriterBuilder::new()
+            .delimiter(b'\t')
+            .from_writer(vec![]);
+        write_csv(&mut wtr, &t, |k| k.to_string(), |v| v.to_string()).unwrap();
+
+        let output = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!("timestamp\ta\n1\tx\n", output);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn read_write_round_trip() {
+        use crate::read_csv_to_datetable;
+        use chrono::NaiveDateTime;
+
+        let data = "d87datum\td87syst\td50bezeich
+2010-02-26 00:00:00.000\t2428\tCentrale
+2010-03-04 00:00:00.000\t2401\tCentrale
+";
+        let format = "%F %H:%M:%S%.3f";
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .from_reader(data.as_bytes());
+        let t =
+            read_csv_to_datetable(&mut rdr, |x| NaiveDateTime::parse_from_str(x, format)).unwrap();
+
+        let mut wtr = csv::WriterBuilder::new()
+            .delimiter(b'\t')
+            .from_writer(vec![]);
+        write_csv(
+            &mut wtr,
+            &t,
+            |k| k.format(format).to_string(),
+            |v| v.clone(),
+        )
+        .unwrap();
+
+        let output = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(data, output);
+    }
+}
+
+#[cfg(test)]
+mod column_test {
+    use super::{new_table_data, new_table_large};
+    use crate::enums::IndexOrColumn;
+    use crate::{BtreeMapTrait, TableTrait};
+
+    #[test]
+    fn add_column() {
+        let mut t1 = new_table_data();
+        t1.add_column("ones", vec![1; 6]).unwrap();
+
+        assert_eq!(
+            &[
+                s!("p10"),
+                s!("data"),
+                s!("twentyfive"),
+                s!("squares"),
+                s!("ones")
+            ],
+            t1.headers()
+        );
+        assert_eq!(Some(&vec![10, 10, 25, 1, 1]), t1.get(&1));
+
+        assert!(t1.add_column("ones", vec![1; 6]).is_err());
+        assert!(t1.add_column("short", vec![1; 5]).is_err());
+    }
+
+    #[test]
+    fn add_column_with_row() {
+        let mut t1 = new_table_data();
+        t1.add_column_with("p10_plus_squares", |row| {
+            row.filter(|(h, _)| *h == "p10" || *h == "squares")
+                .map(|(_, v)| v)
+                .sum()
+        })
+        .unwrap();
+
+        assert_eq!(Some(&vec![60, 68, 25, 36, 96]), t1.get(&6));
+    }
+
+    #[test]
+    fn drop_column() {
+        let mut t1 = new_table_large();
+        t1.drop_column("text").unwrap();
+        t1.drop_column(0).unwrap();
+
+        assert_eq!(&[s!("test"), s!("data")], t1.headers());
+        assert_eq!(Some(&vec!["test", "uvwx"]), t1.get(&6));
+        assert!(t1.drop_column("text").is_err());
+        assert!(t1.drop_column(2).is_err());
+    }
+
+    #[test]
+    fn rename_column() {
+        let mut t1 = new_table_large();
+        t1.rename_column("text", "label").unwrap();
+        t1.rename_column(0, "number").unwrap();
+
+        assert_eq!(
+            &[s!("number"), s!("label"), s!("test"), s!("data")],
+            t1.headers()
+        );
+        assert!(t1.rename_column("unknown", "x").is_err());
+        assert!(t1.rename_column("label", "data").is_err());
+    }
+
+    #[test]
+    fn select() {
+        let t1 = new_table_large();
+        let t2 = t1
+            .select(&[IndexOrColumn::from("data"), IndexOrColumn::from(0)])
+            .unwrap();
+
+        assert_eq!(&[s!("data"), s!("number")], t2.headers());
+        assert_eq!(Some(&vec!["abcd", "1"]), t2.get(&1));
+        assert_eq!(t1.len(), t2.len());
+
+        assert!(t1.select(&[IndexOrColumn::from("unknown")]).is_err());
+        assert!(t1
+            .select(&[IndexOrColumn::from("data"), IndexOrColumn::from(3)])
+            .is_err());
+    }
+
+    #[test]
+    fn reorder_columns() {
+        let mut t1 = new_table_large();
+        let order: Vec<IndexOrColumn> = vec!["data".into(), "test".into(), 1.into(), 0.into()];
+        t1.reorder_columns(&order).unwrap();
+
+        assert_eq!(
+            &[s!("data"), s!("test"), s!("text"), s!("number")],
+            t1.headers()
+        );
+        assert_eq!(Some(&vec!["efgh", "test", "Test02", "2"]), t1.get(&2));
+
+        assert!(t1.reorder_columns(&order[..3]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod filter_test {
+    use super::{new_table_data, new_table_large};
+    use crate::BtreeMapTrait;
+
+    #[test]
+    fn row_lookup() {
+        let t1 = new_table_large();
+        let row = t1.iter_rows().nth(2).unwrap();
+
+        assert_eq!(&3, row.index);
+        assert_eq!("Test03", row["text"]);
+        assert_eq!(Some(&"ijkl"), row.get("data"));
+        assert_eq!(None, row.get("unknown"));
+        assert_eq!(4, row.count());
+    }
+
+    #[test]
+    #[should_panic]
+    fn row_lookup_unknown() {
+        let t1 = new_table_large();
+        let row = t1.iter_rows().next().unwrap();
+        let _ = row["unknown"];
+    }
+
+    #[test]
+    fn filter() {
+        let t1 = new_table_data();
+        let t2 = t1.filter(|row| row["data"] > 30 && row["squares"] < 30);
+
+        assert_eq!(vec![&3, &4, &5], t2.keys().collect::<Vec<_>>());
+        assert_eq!(t1.headers, t2.headers);
+        assert_eq!(6, t1.len());
+    }
+
+    #[test]
+    fn retain() {
+        let mut t1 = new_table_large();
+        t1.retain(|row| row["number"] != "2" && *row.index < 5);
+
+        assert_eq!(vec![&1, &3, &4], t1.keys().collect::<Vec<_>>());
+    }
+}
+
+#[cfg(test)]
+mod group_test {
+    use super::new_table_large;
+    #[cfg(feature = "num")]
+    use crate::Table;
+
+    #[cfg(feature = "num")]
+    fn new_table_groups() -> Table<u8, i32> {
+        let headers = vec![s!("value"), s!("kind"), s!("other")];
+
+        let indexes = vec![1, 2, 3, 4, 5];
+        let d = vec2![[1, 7, 2], [2, 8, 4], [3, 7, 6], [4, 7, 8], [5, 8, 10]];
+
+        Table::new(headers, indexes, d).unwrap()
+    }
+
+    #[test]
+    fn group_by_keys() {
+        let t1 = new_table_large();
+        let groups = t1.group_by("test").unwrap();
+
+        assert_eq!(1, groups.len());
+        assert_eq!(vec![&"test"], groups.keys().collect::<Vec<_>>());
+        assert!(t1.group_by("unknown").is_err());
+    }
+
+    #[test]
+    fn group_by_agg_with() {
+        let t1 = new_table_large();
+        let joined =
+            t1.group_by(2)
+                .unwrap()
+                .agg_with(|values| if values.len() == 6 { "six" } else { "other" });
+
+        assert_eq!(&[s!("number"), s!("text"), s!("data")], &joined.headers[..]);
+        assert_eq!(Some(&vec!["six", "six", "six"]), joined.data.get(&"test"));
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn group_by_agg() {
+        use crate::enums::Aggregation;
+
+        let t1 = new_table_groups();
+        let groups = t1.group_by("kind").unwrap();
+
+        let sum = groups.agg(Aggregation::Sum).unwrap();
+        assert_eq!(&[s!("value"), s!("other")], &sum.headers[..]);
+        assert_eq!(Some(&vec![8, 16]), sum.data.get(&7));
+        assert_eq!(Some(&vec![7, 14]), sum.data.get(&8));
+
+        let count = groups.agg(Aggregation::Count).unwrap();
+        assert_eq!(Some(&vec![3, 3]), count.data.get(&7));
+        assert_eq!(Some(&vec![2, 2]), count.data.get(&8));
+
+        let mean = groups.agg(Aggregation::Mean).unwrap();
+        assert_eq!(Some(&vec![2, 5]), mean.data.get(&7));
+
+        let min = groups.agg(Aggregation::Min).unwrap();
+        assert_eq!(Some(&vec![2, 4]), min.data.get(&8));
+
+        let max = groups.agg(Aggregation::Max).unwrap();
+        assert_eq!(Some(&vec![4, 8]), max.data.get(&7));
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn group_by_index_floats() {
+        use crate::enums::Aggregation;
+
+        let t1 = Table::new(
+            vec![s!("a"), s!("b")],
+            vec![1, 2, 11, 12],
+            vec2![[1.5, -1.0], [0.5, 2.0], [4.0, 3.0], [2.0, 1.0]],
+        )
+        .unwrap();
+        let groups = t1.group_by_index(|k: &u8| k / 10);
+        assert_eq!(vec![&0, &1], groups.keys().collect::<Vec<_>>());
+
+        let min = groups.agg(Aggregation::Min).unwrap();
+        assert_eq!(&[s!("a"), s!("b")], &min.headers[..]);
+        assert_eq!(Some(&vec![0.5, -1.0]), min.data.get(&0));
+
+        let max = groups.agg(Aggregation::Max).unwrap();
+        assert_eq!(Some(&vec![4.0, 3.0]), max.data.get(&1));
+    }
+
+    #[test]
+    #[cfg(feature = "num")]
+    fn group_by_agg_does_not_fit() {
+        use crate::enums::Aggregation;
+
+        let indexes: Vec<u16> = (0..300).collect();
+        let t1 = Table::new(
+            vec![s!("kind"), s!("value")],
+            indexes,
+            vec![vec![0u8, 1]; 300],
+        )
+        .unwrap();
+        let groups = t1.group_by("kind").unwrap();
+
+        assert!(groups.agg(Aggregation::Count).is_err());
+        assert_eq!(
+            Some(&vec![1]),
+            groups.agg(Aggregation::Max).unwrap().data.get(&0)
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "num", feature = "rayon"))]
+    fn group_by_p_agg() {
+        use crate::enums::Aggregation;
+
+        let t1 = new_table_groups();
+        let groups = t1.group_by("kind").unwrap();
+
+        assert_eq!(
+            groups.agg(Aggregation::Sum).unwrap(),
+            groups.p_agg(Aggregation::Sum).unwrap()
+        );
+        assert_eq!(
+            groups.agg_with(|values| *values[0]),
+            groups.p_agg_with(|values| *values[0])
+        );
+    }
+}
+
+#[cfg(test)]
+mod reshape_test {
+    use crate::{Table, TableMetaTrait};
+
+    fn new_table_long_format() -> Table<u8, &'static str> {
+        let headers = vec![s!("timestamp"), s!("sensor_id"), s!("value")];
+
+        let indexes = vec![1, 2, 3, 4, 5];
+        let d = vec2![
+            ["12:00", "b", "1.5"],
+            ["12:00", "a", "2.5"],
+            ["12:01", "a", "3.5"],
+            ["12:02", "b", "4.5"],
+            ["12:02", "a", "5.5"],
+        ];
+
+        let mut t = Table::new(headers, indexes, d).unwrap();
+        t.set_meta_key(s!("unit"), s!("celsius"));
+        t
+    }
+
+    #[test]
+    fn pivot() {
+        let t1 = new_table_long_format();
+        let wide = t1.pivot("timestamp", "sensor_id", 2).unwrap();
+
+        let expected = Table::new(
+            vec![s!("a"), s!("b")],
+            vec!["12:00", "12:01", "12:02"],
+            vec2![
+                [Some("2.5"), Some("1.5")],
+                [Some("3.5"), None],
+                [Some("5.5"), Some("4.5")]
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(expected.headers, wide.headers);
+        assert_eq!(expected.data, wide.data);
+        assert_eq!(Some(&s!("celsius")), wide.get_meta_key(&s!("unit")));
+    }
+
+    #[test]
+    fn pivot_errors() {
+        let t1 = new_table_long_format();
+        assert!(t1.pivot("timestamp", "unknown", "value").is_err());
+
+        let mut t2 = new_table_long_format();
+        t2.data.insert(6, vec!["12:01", "a", "9.9"]);
+        let error = t2.pivot("timestamp", "sensor_id", "value").unwrap_err();
+        assert_eq!(&[s!("(\"12:01\", \"a\")")], error.keys());
+    }
+
+    #[test]
+    fn melt_pivot_round_trip() {
+        let t1 = new_table_long_format();
+        let wide = t1.pivot("timestamp", "sensor_id", "value").unwrap();
+        let long = wide.melt();
+
+        assert_eq!(&[s!("value")], &long.headers[..]);
+        assert_eq!(6, long.data.len());
+        assert_eq!(Some(&vec![None]), long.data.get(&("12:01", s!("b"))));
+        assert_eq!(Some(&vec![Some("4.5")]), long.data.get(&("12:02", s!("b"))));
+        assert_eq!(Some(&s!("celsius")), long.get_meta_key(&s!("unit")));
+
+        let present: usize = long.data.values().filter(|row| row[0].is_some()).count();
+        assert_eq!(t1.data.len(), present);
     }
 }
@@ -3,41 +3,41 @@ use core::ops::RangeBounds;
 use std::collections::HashMap;
 
 use crate::enums::IndexOrColumn;
-use crate::TableError;
+use crate::{Row, TableError};
 use std::collections::btree_map::{Entry, Iter, IterMut, Keys, Range, RangeMut, Values, ValuesMut};
 
 /// Trait that passes the methods from the [BTreeMap](https://doc.rust-lang.org/std/collections/struct.BTreeMap.html).
 pub trait BtreeMapTrait<K: Ord, V> {
     fn clear(&mut self);
-    fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    fn get<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Ord;
-    // fn get_key_value<Q: ?Sized>(&self, k: &Q) -> Option<(&K, &V)>
+        Q: ?Sized + Ord;
+    // fn get_key_value<Q>(&self, k: &Q) -> Option<(&K, &V)>
     //     where K: Borrow<Q>,
-    //           Q: Ord;
-    fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+    //           Q: ?Sized + Ord;
+    fn contains_key<Q>(&self, key: &Q) -> bool
     where
         K: Borrow<Q>,
-        Q: Ord;
-    fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+        Q: ?Sized + Ord;
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
     where
         K: Borrow<Q>,
-        Q: Ord;
+        Q: ?Sized + Ord;
     fn insert(&mut self, key: K, value: V) -> Option<V>;
-    fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
     where
         K: Borrow<Q>,
-        Q: Ord;
+        Q: ?Sized + Ord;
     fn append(&mut self, other: &mut Self);
-    fn range<T: ?Sized, R>(&self, range: R) -> Range<'_, K, V>
+    fn range<T, R>(&self, range: R) -> Range<'_, K, V>
     where
-        T: Ord,
+        T: ?Sized + Ord,
         K: Borrow<T>,
         R: RangeBounds<T>;
-    fn range_mut<T: ?Sized, R>(&mut self, range: R) -> RangeMut<'_, K, V>
+    fn range_mut<T, R>(&mut self, range: R) -> RangeMut<'_, K, V>
     where
-        T: Ord,
+        T: ?Sized + Ord,
         K: Borrow<T>,
         R: RangeBounds<T>;
     fn entry(&mut self, key: K) -> Entry<'_, K, V>;
@@ -56,14 +56,14 @@ pub trait BtreeMapTrait<K: Ord, V> {
 }
 
 pub trait TableTrait<K: Ord, V, B: BtreeMapTrait<K, V>> {
-    fn slice_owned<T: ?Sized, R>(&self, range: R) -> B
+    fn slice_owned<T, R>(&self, range: R) -> B
     where
-        T: Ord,
+        T: ?Sized + Ord,
         K: Borrow<T>,
         R: RangeBounds<T>;
-    fn slice_inplace<T: ?Sized, R>(&mut self, range: R)
+    fn slice_inplace<T, R>(&mut self, range: R)
     where
-        T: Ord,
+        T: ?Sized + Ord,
         K: Borrow<T>,
         R: RangeBounds<T>;
     fn headers(&self) -> &[String];
@@ -72,6 +72,55 @@ pub trait TableTrait<K: Ord, V, B: BtreeMapTrait<K, V>> {
         X: Into<IndexOrColumn>,
         Y: Into<IndexOrColumn>;
     fn swap(&mut self, a: usize, b: usize) -> Result<(), TableError>;
+
+    // The column operations below were added later, their default returns an
+    // error so existing implementors keep compiling.
+
+    /// adds a column at the end, `values` should have a value for every row in index order
+    fn add_column<S>(&mut self, _name: S, _values: Vec<V::Item>) -> Result<(), TableError>
+    where
+        S: Into<String>,
+        V: IntoIterator,
+    {
+        Err(unsupported("add_column"))
+    }
+    /// adds a column at the end, computed from every row
+    fn add_column_with<S, F>(&mut self, _name: S, _f: F) -> Result<(), TableError>
+    where
+        S: Into<String>,
+        V: IntoIterator,
+        F: Fn(Row<'_, K, V::Item, String>) -> V::Item,
+    {
+        Err(unsupported("add_column_with"))
+    }
+    /// takes a string or usize and removes the column
+    fn drop_column<X>(&mut self, _column: X) -> Result<(), TableError>
+    where
+        X: Into<IndexOrColumn>,
+    {
+        Err(unsupported("drop_column"))
+    }
+    /// takes a string or usize and gives the column a new name
+    fn rename_column<X, S>(&mut self, _column: X, _name: S) -> Result<(), TableError>
+    where
+        X: Into<IndexOrColumn>,
+        S: Into<String>,
+    {
+        Err(unsupported("rename_column"))
+    }
+    /// new table with only the given columns, in the given order
+    fn select(&self, _columns: &[IndexOrColumn]) -> Result<B, TableError> {
+        Err(unsupported("select"))
+    }
+    /// puts the columns in the given order, every column should be in the order exactly once
+    fn reorder_columns(&mut self, _order: &[IndexOrColumn]) -> Result<(), TableError> {
+        Err(unsupported("reorder_columns"))
+    }
+}
+
+/// error of a column operation that a `TableTrait` implementor does not provide
+fn unsupported(operation: &str) -> TableError {
+    TableError::new(format!("{} is not supported by this table", operation))
 }
 
 pub trait TableMetaTrait<M, N> {
@@ -0,0 +1,329 @@
+use std::collections::BTreeMap;
+
+use crate::enums::IndexOrColumn;
+use crate::{Table, TableError};
+
+#[cfg(feature = "chrono")]
+use chrono::NaiveDateTime;
+
+/// formats that are tried when a string is parsed as a datetime
+#[cfg(feature = "chrono")]
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+/// Type of the values in a column of a `TypedTable`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DType {
+    Int,
+    Float,
+    Bool,
+    Str,
+    #[cfg(feature = "chrono")]
+    DateTime,
+    /// column where cells can be `Value::Null`
+    Optional(Box<DType>),
+}
+
+/// A single cell of a `TypedTable`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    #[cfg(feature = "chrono")]
+    DateTime(NaiveDateTime),
+    Null,
+}
+
+impl DType {
+    /// checks if the value can be stored in a column of this type
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (DType::Optional(_), Value::Null) => true,
+            (DType::Optional(inner), x) => inner.accepts(x),
+            (DType::Int, Value::Int(_)) => true,
+            (DType::Float, Value::Float(_)) => true,
+            (DType::Bool, Value::Bool(_)) => true,
+            (DType::Str, Value::Str(_)) => true,
+            #[cfg(feature = "chrono")]
+            (DType::DateTime, Value::DateTime(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// the type without the `Optional` wrapper
+    pub fn inner(&self) -> &DType {
+        match self {
+            DType::Optional(inner) => inner.inner(),
+            x => x,
+        }
+    }
+
+    /// parses a string into a value of this type, an empty string is `Value::Null` for optional columns
+    pub fn parse(&self, s: &str) -> Result<Value, TableError> {
+        let error = || TableError::new(format!("can not parse {:?} as {:?}", s, self));
+        match self {
+            DType::Optional(_) if s.is_empty() => Ok(Value::Null),
+            DType::Optional(inner) => inner.parse(s),
+            DType::Int => s.parse().map(Value::Int).map_err(|_| error()),
+            DType::Float => s.parse().map(Value::Float).map_err(|_| error()),
+            DType::Bool => s.parse().map(Value::Bool).map_err(|_| error()),
+            DType::Str => Ok(Value::Str(s.to_string())),
+            #[cfg(feature = "chrono")]
+            DType::DateTime => DATETIME_FORMATS
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+                .map(Value::DateTime)
+                .ok_or_else(error),
+        }
+    }
+
+    /// infers the most specific type that can hold all values.
+    /// Tries integer, float, boolean, datetime and falls back to string,
+    /// if one of the values is empty the type is optional.
+    pub fn infer<'a, I>(values: I) -> DType
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut candidates = vec![DType::Int, DType::Float, DType::Bool];
+        #[cfg(feature = "chrono")]
+        candidates.push(DType::DateTime);
+        let mut optional = false;
+        let mut seen = false;
+
+        for v in values {
+            if v.is_empty() {
+                optional = true;
+                continue;
+            }
+            seen = true;
+            candidates.retain(|dtype| dtype.parse(v).is_ok());
+        }
+
+        let dtype = match candidates.into_iter().next() {
+            Some(x) if seen => x,
+            _ => DType::Str,
+        };
+        if optional {
+            DType::Optional(Box::new(dtype))
+        } else {
+            dtype
+        }
+    }
+}
+
+/// Rust types that can be read from a `Value`.
+pub trait TypedValue: Sized {
+    const DTYPE: DType;
+
+    fn from_value(value: &Value) -> Option<&Self>;
+}
+
+impl TypedValue for i64 {
+    const DTYPE: DType = DType::Int;
+
+    fn from_value(value: &Value) -> Option<&Self> {
+        match value {
+            Value::Int(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl TypedValue for f64 {
+    const DTYPE: DType = DType::Float;
+
+    fn from_value(value: &Value) -> Option<&Self> {
+        match value {
+            Value::Float(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl TypedValue for bool {
+    const DTYPE: DType = DType::Bool;
+
+    fn from_value(value: &Value) -> Option<&Self> {
+        match value {
+            Value::Bool(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl TypedValue for String {
+    const DTYPE: DType = DType::Str;
+
+    fn from_value(value: &Value) -> Option<&Self> {
+        match value {
+            Value::Str(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TypedValue for NaiveDateTime {
+    const DTYPE: DType = DType::DateTime;
+
+    fn from_value(value: &Value) -> Option<&Self> {
+        match value {
+            Value::DateTime(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+/// Table where every column has its own type.
+///
+///```
+/// use timeseries::{vec2, Table, DType};
+///
+/// let headers = vec![String::from("count"), String::from("name")];
+/// let data = vec2![
+///     [String::from("1"), String::from("first")],
+///     [String::from("2"), String::from("")],
+/// ];
+///
+/// let t = Table::new(headers, vec![1, 2], data).unwrap().into_typed();
+///
+/// assert_eq!(&[DType::Int, DType::Optional(Box::new(DType::Str))], t.dtypes());
+/// assert_eq!(Some(&2), t.get::<i64, _>(&2, "count").unwrap());
+/// assert_eq!(None, t.get::<String, _>(&2, "name").unwrap());
+///```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedTable<U>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+{
+    table: Table<U, Value>,
+    dtypes: Vec<DType>,
+}
+
+impl<U> TypedTable<U>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+{
+    /// creates a typed table, returns an error if a cell does not match the type of its column
+    pub fn new(table: Table<U, Value>, dtypes: Vec<DType>) -> Result<TypedTable<U>, TableError> {
+        if table.headers.len() != dtypes.len() {
+            return Err(TableError::new("every column should have a type"));
+        }
+        for (k, row) in table.data.iter() {
+            for ((header, dtype), value) in table.headers.iter().zip(&dtypes).zip(row) {
+                if !dtype.accepts(value) {
+                    return Err(TableError::new(format!(
+                        "value {:?} at index {:?} does not fit column {} of type {:?}",
+                        value, k, header, dtype
+                    )));
+                }
+            }
+        }
+        Ok(TypedTable { table, dtypes })
+    }
+
+    pub fn table(&self) -> &Table<U, Value> {
+        &self.table
+    }
+
+    pub fn into_inner(self) -> Table<U, Value> {
+        self.table
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.table.headers
+    }
+
+    pub fn dtypes(&self) -> &[DType] {
+        &self.dtypes
+    }
+
+    /// type of a column by name or position
+    pub fn dtype<X: Into<IndexOrColumn>>(&self, column: X) -> Result<&DType, TableError> {
+        let index = self.column_index(column)?;
+        Ok(&self.dtypes[index])
+    }
+
+    /// gets a cell as a rust type, returns None for missing values and
+    /// an error if the column or key does not exist or the column has another type
+    pub fn get<T, X>(&self, key: &U, column: X) -> Result<Option<&T>, TableError>
+    where
+        T: TypedValue,
+        X: Into<IndexOrColumn>,
+    {
+        let index = self.typed_column_index::<T, X>(column)?;
+        let row = self
+            .table
+            .data
+            .get(key)
+            .ok_or_else(|| TableError::new(format!("index {:?} not found", key)))?;
+        Ok(T::from_value(&row[index]))
+    }
+
+    /// gets a whole column as a rust type, missing values are None
+    pub fn column<T, X>(&self, column: X) -> Result<Vec<(&U, Option<&T>)>, TableError>
+    where
+        T: TypedValue,
+        X: Into<IndexOrColumn>,
+    {
+        let index = self.typed_column_index::<T, X>(column)?;
+        Ok(self
+            .table
+            .data
+            .iter()
+            .map(|(k, row)| (k, T::from_value(&row[index])))
+            .collect())
+    }
+
+    fn typed_column_index<T, X>(&self, column: X) -> Result<usize, TableError>
+    where
+        T: TypedValue,
+        X: Into<IndexOrColumn>,
+    {
+        let index = self.column_index(column)?;
+        if *self.dtypes[index].inner() != T::DTYPE {
+            return Err(TableError::new(format!(
+                "column {} has type {:?}, not {:?}",
+                self.table.headers[index],
+                self.dtypes[index],
+                T::DTYPE
+            )));
+        }
+        Ok(index)
+    }
+
+    fn column_index<X: Into<IndexOrColumn>>(&self, column: X) -> Result<usize, TableError> {
+        self.table.column_index(column)
+    }
+}
+
+impl<U> Table<U, String>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+{
+    /// infers the type of every column and parses the cells,
+    /// used to convert the output of the csv readers into typed columns
+    pub fn into_typed(self) -> TypedTable<U> {
+        let dtypes: Vec<DType> = (0..self.headers.len())
+            .map(|i| DType::infer(self.data.values().map(|row| row[i].as_str())))
+            .collect();
+
+        let data: BTreeMap<U, Vec<Value>> = self
+            .data
+            .into_iter()
+            .map(|(k, row)| {
+                let values = row
+                    .iter()
+                    .zip(&dtypes)
+                    .map(|(v, dtype)| dtype.parse(v).expect("inferred type parses the column"))
+                    .collect();
+                (k, values)
+            })
+            .collect();
+
+        let mut table = Table::new_btreemap(self.headers, data);
+        table.meta_data = self.meta_data;
+        TypedTable { table, dtypes }
+    }
+}
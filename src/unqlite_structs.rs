@@ -1,7 +1,13 @@
 #![cfg(feature = "unqlite_db")]
 
-use crate::{BtreeMapTrait, DbObject, DbTableError, Table, TableMetaTrait};
+use crate::connection::{ConnectionGuard, ReusedConnection};
+use crate::db_structs::{check_row_length, namespace_prefix, rewrite_row};
+use crate::{
+    BtreeMapTrait, DbChanges, DbObject, DbTableError, HeaderChange, StoreError, Table,
+    TableMetaTrait, TableStore,
+};
 use std::collections::{BTreeMap, HashMap};
+use std::ops::{Bound, RangeBounds};
 use unqlite::{Cursor, Transaction, UnQLite, KV};
 
 #[derive(Debug)]
@@ -29,51 +35,249 @@ impl From<unqlite::Error> for UnqliteError {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct UnqliteConfig {
+    pub path: String,
+    /// prefix for every key of the table, `None` means the table owns the whole file
+    pub namespace: Option<String>,
+    reused: ReusedConnection<UnQLite>,
+}
+
+impl UnqliteConfig {
+    pub fn new<P>(path: P) -> UnqliteConfig
+    where
+        P: Into<String>,
+    {
+        UnqliteConfig {
+            path: path.into(),
+            namespace: None,
+            reused: ReusedConnection::default(),
+        }
+    }
+
+    /// Prefixes all keys with the length of `name` and `name`, e.g. `7:sensors:`,
+    /// so many tables can share one file.
+    pub fn with_namespace<S>(mut self, name: S) -> UnqliteConfig
+    where
+        S: Into<String>,
+    {
+        self.namespace = Some(name.into());
+        self
+    }
+
+    /// Keeps the file open and uses it for every call made with this config
+    /// and its clones, instead of opening the file per call.
+    pub fn reuse_connection(mut self) -> UnqliteConfig {
+        self.reused = ReusedConnection::lazy();
+        self
+    }
+
+    /// Like `reuse_connection`, with a database opened by the caller.
+    pub fn with_connection(mut self, db: UnQLite) -> UnqliteConfig {
+        self.reused = ReusedConnection::with(db);
+        self
+    }
+
+    /// the kept database, or a newly opened one when it is not reused
+    fn connection(&self) -> Result<ConnectionGuard<'_, UnQLite>, UnqliteError> {
+        self.reused
+            .get(|_| true, || Ok(UnQLite::create(&self.path)))
+    }
+
+    /// Config of an older version of the table, kept by `HeaderChange::Version`.
+    ///
+    /// The version lives in the namespace `<namespace>_v<n>`.
+    pub fn version(&self, n: u32) -> UnqliteConfig {
+        let namespace = self.namespace.as_deref().unwrap_or_default();
+        self.clone().with_namespace(format!("{}_v{}", namespace, n))
+    }
+
+    fn prefix(&self) -> Vec<u8> {
+        namespace_prefix(self.namespace.as_deref())
+    }
+
+    /// adds the namespace prefix to a serialized key
+    fn key(&self, key: Vec<u8>) -> Vec<u8> {
+        let mut prefixed = self.prefix();
+        prefixed.extend(key);
+        prefixed
+    }
+
+    /// checks if the table was saved before
+    fn has_table(&self, db: &UnQLite) -> Result<bool, UnqliteError> {
+        Ok(match self.namespace {
+            Some(_) => db.kv_contains(self.key(bincode::serialize("__HEADER")?)),
+            None => db.first().is_some(),
+        })
+    }
+
+    /// keys and values of every row of the table, without the special keys
+    fn rows(&self, db: &UnQLite) -> Result<Vec<(Vec<u8>, Vec<u8>)>, UnqliteError> {
+        let prefix = self.prefix();
+        let header_key = self.key(bincode::serialize("__HEADER")?);
+        let meta_key = self.key(bincode::serialize("__META_DATA")?);
+
+        let mut rows = Vec::new();
+        let mut entry = db.first();
+        while let Some(cursor) = entry {
+            let (item_k, item_v) = cursor.key_value();
+            entry = cursor.next();
+            if item_k.starts_with(&prefix) && item_k != header_key && item_k != meta_key {
+                rows.push((item_k, item_v));
+            }
+        }
+        Ok(rows)
+    }
+
+    /// moves every key to the first free version and returns its number
+    fn move_to_version(&self, db: &UnQLite) -> Result<u32, UnqliteError> {
+        if self.namespace.is_none() {
+            return Err(UnqliteError::DbTableError(
+                DbTableError::DbNamespaceRequired,
+            ));
+        }
+        let mut n = 1;
+        while self.version(n).has_table(db)? {
+            n += 1;
+        }
+
+        let prefix = self.prefix();
+        let version_prefix = self.version(n).prefix();
+        let mut moved = Vec::new();
+        let mut entry = db.first();
+        while let Some(cursor) = entry {
+            let (item_k, item_v) = cursor.key_value();
+            entry = cursor.next();
+            if item_k.starts_with(&prefix) {
+                moved.push((item_k, item_v));
+            }
+        }
+        for (item_k, item_v) in moved {
+            let mut key = version_prefix.clone();
+            key.extend_from_slice(&item_k[prefix.len()..]);
+            db.kv_store(key, item_v)?;
+            db.kv_delete(item_k)?;
+        }
+        Ok(n)
+    }
+
+    pub(crate) fn delete_keys(&self) -> Result<(), UnqliteError> {
+        let prefix = self.prefix();
+        let db = self.connection()?;
+
+        let mut entry = db.first();
+        while let Some(cursor) = entry {
+            entry = if cursor.key().starts_with(&prefix) {
+                cursor.delete()
+            } else {
+                cursor.next()
+            };
+        }
+        db.commit()?;
+        Ok(())
+    }
+}
+
+impl<U, V> TableStore<U, V> for UnqliteConfig
+where
+    U: std::fmt::Debug + Clone + std::cmp::Ord + serde::de::DeserializeOwned + serde::Serialize,
+    V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
+{
+    /// unqlite needs no preparation
+    fn init(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    fn save(&self, table: &Table<U, V>) -> Result<(), StoreError> {
+        Ok(table.save_unqlite(self)?)
+    }
+
+    fn update(&self, table: &Table<U, V>) -> Result<DbChanges<U>, StoreError> {
+        Ok(table.sync_unqlite(self)?)
+    }
+
+    fn load(&self) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::from_unqlite(self)?)
+    }
+
+    fn load_range(&self, range: (Bound<&U>, Bound<&U>)) -> Result<Table<U, V>, StoreError> {
+        Ok(Table::load_range_unqlite(self, range)?)
+    }
+
+    fn delete(&self) -> Result<(), StoreError> {
+        Ok(self.delete_keys()?)
+    }
+
+    fn exists(&self) -> Result<bool, StoreError> {
+        let db = self.connection()?;
+        Ok(self.has_table(&db)?)
+    }
+}
+
+impl From<&UnqliteConfig> for UnqliteConfig {
+    fn from(config: &UnqliteConfig) -> UnqliteConfig {
+        config.clone()
+    }
+}
+
+impl From<&str> for UnqliteConfig {
+    fn from(path: &str) -> UnqliteConfig {
+        UnqliteConfig::new(path)
+    }
+}
+
+impl From<String> for UnqliteConfig {
+    fn from(path: String) -> UnqliteConfig {
+        UnqliteConfig::new(path)
+    }
+}
+
+impl From<&String> for UnqliteConfig {
+    fn from(path: &String) -> UnqliteConfig {
+        UnqliteConfig::new(path.as_str())
+    }
+}
+
 impl<U, V> Table<U, V>
 where
     U: std::fmt::Debug + Clone + std::cmp::Ord + serde::de::DeserializeOwned + serde::Serialize,
     V: std::fmt::Debug + Clone + serde::de::DeserializeOwned + serde::Serialize,
 {
-    pub fn from_unqlite<P: AsRef<str>>(filename: P) -> Result<Table<U, V>, UnqliteError> {
-        let db = UnQLite::create(filename);
-        let mut first = db
-            .first()
-            .ok_or(UnqliteError::DbTableError(DbTableError::DbDoesNotExist))?;
+    pub fn from_unqlite<P: Into<UnqliteConfig>>(filename: P) -> Result<Table<U, V>, UnqliteError> {
+        Self::load_range_unqlite(filename, ..)
+    }
 
-        // let (header_key, header_value) = first.key_value();
+    /// Loads only the rows whose index falls in `range`.
+    ///
+    /// UnQLite has no ordered key access, so every key is read and the rows
+    /// outside the range are skipped before their values are deserialized.
+    pub fn load_range_unqlite<P, R>(filename: P, range: R) -> Result<Table<U, V>, UnqliteError>
+    where
+        P: Into<UnqliteConfig>,
+        R: RangeBounds<U>,
+    {
+        let config = filename.into();
+        let prefix = config.prefix();
+        let db = config.connection()?;
+        let mut entry = Some(
+            db.first()
+                .ok_or(UnqliteError::DbTableError(DbTableError::DbDoesNotExist))?,
+        );
 
         let mut headers: DbObject<Vec<String>> = DbObject::new(Vec::new());
         let mut metadata: DbObject<Option<HashMap<String, String>>> = DbObject::new(None);
         let mut btable = BTreeMap::new();
-        // let mut table = Table::new_btreemap(headers, std::collections::BTreeMap::new());
-
-        let mut n = true;
-        let (item_k, item_v) = first.key_value();
-        if let Ok(x) = bincode::deserialize::<String>(&item_k) {
-            if x == "__META_DATA" {
-                n = false;
-                metadata = bincode::deserialize(&item_v)?;
-            }
-            if x == "__HEADER" {
-                n = false;
-                headers = bincode::deserialize(&item_v)?;
-            }
-        }
 
-        if n {
-            let k: U = bincode::deserialize(&item_k)?;
-            let v: DbObject<Vec<V>> = bincode::deserialize(&item_v)?;
-            // let v: Vec<V> = bincode::deserialize(&item_v)?;
-            btable.insert(k, v.item);
-        }
+        while let Some(cursor) = entry {
+            let (item_k, item_v) = cursor.key_value();
+            entry = cursor.next();
+            if !item_k.starts_with(&prefix) {
+                continue;
+            }
+            let item_k = &item_k[prefix.len()..];
 
-        // let mut first = db
-        //     .first()
-        //     .ok_or(UnqliteError::DbTableError(DbTableError::DbDoesNotExist))?;
-        while let Some(cursor) = first.next() {
             let mut n = true;
-            let (item_k, item_v) = cursor.key_value();
-            if let Ok(x) = bincode::deserialize::<String>(&item_k) {
+            if let Ok(x) = bincode::deserialize::<String>(item_k) {
                 if x == "__META_DATA" {
                     n = false;
                     metadata = bincode::deserialize(&item_v)?;
@@ -85,18 +289,21 @@ where
             }
 
             if n {
-                let k: U = bincode::deserialize(&item_k)?;
-                let v: DbObject<Vec<V>> = bincode::deserialize(&item_v)?;
-                // let v: Vec<V> = bincode::deserialize(&item_v)?;
-                btable.insert(k, v.item);
+                let k: U = bincode::deserialize(item_k)?;
+                if range.contains(&k) {
+                    let v: DbObject<Vec<V>> = bincode::deserialize(&item_v)?;
+                    btable.insert(k, v.item);
+                }
             }
-            first = cursor;
         }
         if headers.item == Vec::<String>::new() {
             return Err(UnqliteError::DbTableError(
                 DbTableError::DbHeaderDoesNotExist,
             ));
         }
+        for row in btable.values() {
+            check_row_length(&headers.item, row).map_err(UnqliteError::DbTableError)?;
+        }
 
         let mut table = Table::new_btreemap(headers.item, btable);
         if let Some(x) = metadata.item {
@@ -105,21 +312,76 @@ where
         Ok(table)
     }
 
-    pub fn update_unqlite<P: AsRef<str>>(&self, filename: P) -> Result<Vec<U>, UnqliteError> {
-        let db = UnQLite::create(filename);
-        let mut changed_keys = Vec::new();
+    /// Writes the rows whose hash changed. Stored rows that are not in the
+    /// table are kept, use `sync_unqlite` to delete them.
+    /// Returns the keys that were inserted and updated.
+    ///
+    /// Fails with `DbTableError::DbDoesNotExist` when no table was saved and with
+    /// `DbTableError::DbHeadersChanged` when the headers changed, see
+    /// `update_unqlite_with` for the other options.
+    pub fn update_unqlite<P: Into<UnqliteConfig>>(
+        &self,
+        filename: P,
+    ) -> Result<DbChanges<U>, UnqliteError> {
+        self.update_unqlite_with(filename, &HeaderChange::Reject)
+    }
 
-        match db.first() {
-            Some(_) => (),
-            None => return Err(UnqliteError::DbTableError(DbTableError::DbDoesNotExist)),
-        };
+    /// Like `update_unqlite`, with `on_change` deciding what happens when the headers changed.
+    pub fn update_unqlite_with<P: Into<UnqliteConfig>>(
+        &self,
+        filename: P,
+        on_change: &HeaderChange<V>,
+    ) -> Result<DbChanges<U>, UnqliteError> {
+        self.write_unqlite(filename.into(), on_change, false)
+    }
+
+    /// Like `update_unqlite`, but also deletes the stored rows that are no
+    /// longer in the table, so the stored table equals this table afterwards.
+    /// Returns the keys that were inserted, updated and deleted.
+    pub fn sync_unqlite<P: Into<UnqliteConfig>>(
+        &self,
+        filename: P,
+    ) -> Result<DbChanges<U>, UnqliteError> {
+        self.write_unqlite(filename.into(), &HeaderChange::Reject, true)
+    }
+
+    fn write_unqlite(
+        &self,
+        config: UnqliteConfig,
+        on_change: &HeaderChange<V>,
+        delete_stale: bool,
+    ) -> Result<DbChanges<U>, UnqliteError> {
+        let db = config.connection()?;
+        let mut changes = DbChanges::new();
 
-        let header_key = bincode::serialize("__HEADER")?;
+        if !config.has_table(&db)? {
+            return Err(UnqliteError::DbTableError(DbTableError::DbDoesNotExist));
+        }
+
+        let header_key = config.key(bincode::serialize("__HEADER")?);
         let db_table_header = DbObject::new(self.headers.clone());
+        // the stored headers and fill value when the stored rows that are kept need a rewrite
+        let mut rewrite = None;
         match db.kv_fetch(&header_key) {
             Ok(x) => {
                 let db_object: DbObject<Vec<String>> = bincode::deserialize(&x)?;
                 if db_table_header != db_object {
+                    match on_change {
+                        HeaderChange::Reject => {
+                            return Err(UnqliteError::DbTableError(DbTableError::DbHeadersChanged(
+                                db_object.item,
+                            )))
+                        }
+                        HeaderChange::Rewrite(fill) => rewrite = Some((db_object.item, fill)),
+                        HeaderChange::Version => {
+                            config.move_to_version(&db)?;
+                            db.commit()?;
+                            drop(db);
+                            self.save_unqlite(config)?;
+                            changes.inserted = self.keys().cloned().collect();
+                            return Ok(changes);
+                        }
+                    }
                     db.kv_store(header_key, bincode::serialize(&db_table_header)?)?;
                 }
             }
@@ -128,7 +390,7 @@ where
             }
         }
 
-        let meta_key = bincode::serialize("__META_DATA")?;
+        let meta_key = config.key(bincode::serialize("__META_DATA")?);
         let db_meta_data = DbObject::new(self.meta_data.clone());
         match db.kv_fetch(&meta_key) {
             Ok(x) => {
@@ -143,57 +405,84 @@ where
             }
         }
 
+        let mut stored: HashMap<Vec<u8>, Vec<u8>> = config.rows(&db)?.into_iter().collect();
         for (k, v) in self.iter() {
             let tmp = DbObject::new(v.clone());
-            let key = bincode::serialize(k)?;
-            match db.kv_fetch(&key) {
-                Ok(x) => {
+            let key = config.key(bincode::serialize(k)?);
+            match stored.remove(&key) {
+                Some(x) => {
                     let db_object: DbObject<Vec<V>> = bincode::deserialize(&x)?;
                     if tmp != db_object {
                         db.kv_store(key, bincode::serialize(&tmp)?)?;
-                        changed_keys.push(k.clone());
+                        changes.updated.push(k.clone());
                     }
                 }
-                Err(_e) => {
+                None => {
                     db.kv_store(key, bincode::serialize(&tmp)?)?;
-                    changed_keys.push(k.clone());
+                    changes.inserted.push(k.clone());
                 }
             }
         }
-        Ok(changed_keys)
+
+        let prefix_len = config.prefix().len();
+        for (key, value) in stored {
+            let k: U = bincode::deserialize(&key[prefix_len..])?;
+            if delete_stale {
+                db.kv_delete(key)?;
+                changes.deleted.push(k);
+            } else if let Some((from, fill)) = &rewrite {
+                let row: DbObject<Vec<V>> = bincode::deserialize(&value)?;
+                let row = DbObject::new(rewrite_row(&row.item, from, &self.headers, *fill));
+                db.kv_store(key, bincode::serialize(&row)?)?;
+                changes.updated.push(k);
+            }
+        }
+        changes.updated.sort();
+        changes.deleted.sort();
+        db.commit()?;
+        Ok(changes)
     }
 
-    pub fn save_unqlite<P: AsRef<str>>(&self, filename: P) -> Result<(), UnqliteError> {
-        let db = UnQLite::create(filename);
+    pub fn save_unqlite<P: Into<UnqliteConfig>>(&self, filename: P) -> Result<(), UnqliteError> {
+        let config = filename.into();
+        let db = config.connection()?;
 
-        match db.first() {
-            Some(_) => return Err(UnqliteError::DbTableError(DbTableError::DbExists)),
-            None => (),
-        };
+        if config.has_table(&db)? {
+            return Err(UnqliteError::DbTableError(DbTableError::DbExists));
+        }
 
         db.kv_store(
-            bincode::serialize("__HEADER")?,
+            config.key(bincode::serialize("__HEADER")?),
             bincode::serialize(&DbObject::new(self.headers.clone()))?,
         )?;
 
         db.kv_store(
-            bincode::serialize("__META_DATA")?,
+            config.key(bincode::serialize("__META_DATA")?),
             bincode::serialize(&DbObject::new(self.meta_data.clone()))?,
         )?;
 
         for (k, v) in self.iter() {
             let tmp = DbObject::new(v.clone());
-            db.kv_store(bincode::serialize(k)?, bincode::serialize(&tmp)?)?;
+            db.kv_store(
+                config.key(bincode::serialize(k)?),
+                bincode::serialize(&tmp)?,
+            )?;
         }
+        // a kept database is only written back on commit or close
+        db.commit()?;
         Ok(())
     }
 
-    pub fn save_unqlite_override<P: AsRef<str>>(&self, filename: P) -> Result<(), UnqliteError> {
-        match self.save_unqlite(&filename) {
+    pub fn save_unqlite_override<P: Into<UnqliteConfig>>(
+        &self,
+        filename: P,
+    ) -> Result<(), UnqliteError> {
+        let config = filename.into();
+        match self.save_unqlite(config.clone()) {
             Ok(x) => return Ok(x),
             Err(UnqliteError::DbTableError(DbTableError::DbExists)) => {
-                self.delete_unqlite(&filename)?;
-                self.save_unqlite(&filename)?;
+                self.delete_unqlite(config.clone())?;
+                self.save_unqlite(config)?;
             }
             Err(e) => return Err(e),
         };
@@ -201,20 +490,9 @@ where
     }
 
     /// removes cursors in unqlite database, but does not delete the data
-    pub fn delete_unqlite<P: AsRef<str>>(&self, filename: P) -> Result<(), UnqliteError> {
-        let db = UnQLite::create(filename);
-        match db.first() {
-            None => return Ok(()),
-            Some(_) => (),
-        };
-
-        while let Some(cursor) = db.first() {
-            match cursor.delete() {
-                Some(_) => (),
-                None => (),
-            };
-        }
-        db.commit().unwrap();
-        Ok(())
+    ///
+    /// With a namespace only the keys of that namespace are removed.
+    pub fn delete_unqlite<P: Into<UnqliteConfig>>(&self, filename: P) -> Result<(), UnqliteError> {
+        filename.into().delete_keys()
     }
 }
@@ -4,8 +4,12 @@ extern crate csv;
 #[cfg(feature = "chrono")]
 extern crate chrono;
 
-#[cfg(all(feature = "chrono", feature = "csv"))]
-use crate::{Table, TableMetaTrait, TableReadError};
+#[cfg(feature = "csv")]
+use crate::enums::{DuplicatePolicy, IndexOrColumn};
+#[cfg(feature = "csv")]
+use crate::{Table, TableError, TableMetaTrait, TableReadError, TableWriteError};
+#[cfg(feature = "csv")]
+use std::collections::BTreeMap;
 
 #[cfg(all(feature = "chrono", feature = "csv"))]
 pub fn read_csv_to_datetable<R, U, F, Y>(
@@ -18,33 +22,223 @@ where
     F: Fn(&str) -> Result<U, Y>,
     Y: std::fmt::Display,
 {
-    let csv_header = rdr.headers()?;
-    let mut header_iter = csv_header.iter().map(|x| x.to_string());
-    let time_column = header_iter.next().expect("no header found");
-    let header = header_iter.collect();
-
-    let mut indexes = vec![];
-    let mut data = vec![];
-    for result in rdr.records() {
-        let record = result?.to_owned();
-        let mut first = true;
-        let mut row: Vec<String> = vec![];
-        for v in record.iter() {
-            if first {
-                let i = match index_parse(v) {
-                    Ok(x) => x,
-                    Err(e) => return Err(TableReadError::new(format!("{}", e))),
-                };
-                indexes.push(i);
-                first = false;
-            } else {
-                row.push(v.to_string())
+    read_csv(index_parse)
+        .duplicates(DuplicatePolicy::KeepLast)
+        .read(rdr)
+}
+
+#[cfg(feature = "csv")]
+type IndexParser<'a, U> = Box<dyn Fn(&str) -> Result<U, String> + 'a>;
+#[cfg(feature = "csv")]
+type ValueParser<'a, V> = Box<dyn Fn(&str, &str) -> Result<V, String> + 'a>;
+
+/// Builder to read a csv into a table, created with [read_csv](fn.read_csv.html).
+#[cfg(feature = "csv")]
+pub struct CsvReader<'a, U, V> {
+    index: IndexOrColumn,
+    duplicates: DuplicatePolicy<V>,
+    // an `Aggregate` was set before `values`, `read` rejects it
+    dropped_aggregate: bool,
+    index_parse: IndexParser<'a, U>,
+    value_parse: ValueParser<'a, V>,
+}
+
+/// reads a csv into a table, the index is parsed with `index_parse`.
+///
+/// By default the first column is the index, the values are strings and duplicate index values are an error.
+/// If the `csv::Reader` has no headers the columns are named `column_0`, `column_1`, ...
+/// The name of the index column is stored in the `time_column` meta key.
+///
+///```
+/// use timeseries::{csv, read_csv};
+///
+/// let data = "name,id,value\na,1,1.5\nb,2,2.5\n";
+/// let mut rdr = csv::Reader::from_reader(data.as_bytes());
+///
+/// let t = read_csv(|x| x.parse::<u32>())
+///     .index("id")
+///     .values(|_header, x| x.parse::<f64>().or(Ok::<f64, String>(0.0)))
+///     .read(&mut rdr)
+///     .unwrap();
+///
+/// assert_eq!(&[String::from("name"), String::from("value")], &t.headers[..]);
+/// assert_eq!(Some(&vec![0.0, 2.5]), t.data.get(&2));
+///```
+#[cfg(feature = "csv")]
+pub fn read_csv<'a, U, F, Y>(index_parse: F) -> CsvReader<'a, U, String>
+where
+    F: Fn(&str) -> Result<U, Y> + 'a,
+    Y: std::fmt::Display,
+{
+    CsvReader {
+        index: IndexOrColumn::Index(0),
+        duplicates: DuplicatePolicy::Error,
+        dropped_aggregate: false,
+        index_parse: Box::new(move |x| index_parse(x).map_err(|e| format!("{}", e))),
+        value_parse: Box::new(|_, x| Ok(x.to_string())),
+    }
+}
+
+#[cfg(feature = "csv")]
+impl<'a, U, V> CsvReader<'a, U, V>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+    V: std::fmt::Debug,
+{
+    /// column to use as index, by name or position
+    pub fn index<X: Into<IndexOrColumn>>(mut self, index: X) -> CsvReader<'a, U, V> {
+        self.index = index.into();
+        self
+    }
+
+    /// what to do with rows that have the same index.
+    /// An `Aggregate` combines parsed values, so it has to be set after `values`
+    pub fn duplicates(mut self, policy: DuplicatePolicy<V>) -> CsvReader<'a, U, V> {
+        self.duplicates = policy;
+        self.dropped_aggregate = false;
+        self
+    }
+
+    pub fn read<R: std::io::Read>(
+        &self,
+        rdr: &mut csv::Reader<R>,
+    ) -> Result<Table<U, V>, TableReadError> {
+        let mut columns: Vec<String> = if rdr.has_headers() {
+            rdr.headers()?.iter().map(|x| x.to_string()).collect()
+        } else {
+            (0..rdr.headers()?.len())
+                .map(|i| format!("column_{}", i))
+                .collect()
+        };
+
+        let index = match self.index {
+            IndexOrColumn::Column(ref name) => columns.iter().position(|x| x == name),
+            IndexOrColumn::Index(x) if x < columns.len() => Some(x),
+            IndexOrColumn::Index(_) => None,
+        }
+        .ok_or_else(|| TableReadError::new(format!("index column {:?} not found", self.index)))?;
+        let time_column = columns.remove(index);
+        if self.dropped_aggregate {
+            return Err(TableReadError::new(
+                "the Aggregate duplicate policy was set before values, set it after values",
+            ));
+        }
+
+        let mut data = BTreeMap::new();
+        let mut collided = Vec::new();
+        for result in rdr.records() {
+            let record = result?;
+            // a flexible reader lets records differ from the header
+            if record.len() != columns.len() + 1 {
+                let line = record.position().map_or(0, |x| x.line());
+                return Err(TableReadError::new(format!(
+                    "record on line {} has {} fields, expected {}",
+                    line,
+                    record.len(),
+                    columns.len() + 1
+                )));
             }
+            let k = (self.index_parse)(&record[index]).map_err(TableReadError::new)?;
+            let cells = record
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != index)
+                .map(|(_, v)| v);
+
+            let row = self.parse_row(&columns, cells)?;
+            Table::insert_row(&mut data, k, row, &self.duplicates, &mut collided);
+        }
+        if !collided.is_empty() {
+            return Err(TableError::duplicates(collided).into());
         }
-        data.push(row);
+
+        let mut t = Table::new_btreemap(columns, data);
+        t.set_meta_key(String::from("time_column"), time_column);
+        Ok(t)
+    }
+
+    fn parse_row<'b, I>(&self, columns: &[String], cells: I) -> Result<Vec<V>, TableReadError>
+    where
+        I: Iterator<Item = &'b str>,
+    {
+        columns
+            .iter()
+            .zip(cells)
+            .map(|(header, v)| (self.value_parse)(header, v).map_err(TableReadError::new))
+            .collect()
     }
+}
 
-    let mut t = Table::new(header, indexes, data)?;
-    t.set_meta_key(String::from("time_column"), time_column);
-    Ok(t)
+#[cfg(feature = "csv")]
+impl<'a, U> CsvReader<'a, U, String>
+where
+    U: std::fmt::Debug + std::cmp::Ord,
+{
+    /// parses the cells, the closure gets the header of the column and the cell.
+    /// An `Aggregate` duplicate policy set before can't combine the parsed values,
+    /// `read` fails until `duplicates` is called again.
+    pub fn values<W, F, Y>(self, value_parse: F) -> CsvReader<'a, U, W>
+    where
+        F: Fn(&str, &str) -> Result<W, Y> + 'a,
+        Y: std::fmt::Display,
+    {
+        let (duplicates, dropped_aggregate) = match self.duplicates {
+            DuplicatePolicy::Error => (DuplicatePolicy::Error, false),
+            DuplicatePolicy::KeepFirst => (DuplicatePolicy::KeepFirst, false),
+            DuplicatePolicy::KeepLast => (DuplicatePolicy::KeepLast, false),
+            DuplicatePolicy::Aggregate(_) => (DuplicatePolicy::Error, true),
+        };
+        CsvReader {
+            index: self.index,
+            duplicates,
+            dropped_aggregate,
+            index_parse: self.index_parse,
+            value_parse: Box::new(move |h, x| value_parse(h, x).map_err(|e| format!("{}", e))),
+        }
+    }
+}
+
+/// writes the table as csv, the index is the first column.
+/// The header of the index is the `time_column` meta key that `read_csv_to_datetable` stores, or `index` if it is not set.
+///
+///```
+/// use timeseries::{csv, vec2, write_csv, Table};
+///
+/// let t = Table::new(vec![String::from("value")], vec![1, 2], vec2![[1.5], [2.0]]).unwrap();
+///
+/// let mut wtr = csv::Writer::from_writer(vec![]);
+/// write_csv(&mut wtr, &t, |k| k.to_string(), |v| format!("{:.1}", v)).unwrap();
+///
+/// let output = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+/// assert_eq!("index,value\n1,1.5\n2,2.0\n", output);
+///```
+#[cfg(feature = "csv")]
+pub fn write_csv<W, U, V, F, G>(
+    wtr: &mut csv::Writer<W>,
+    table: &Table<U, V>,
+    index_format: F,
+    value_format: G,
+) -> Result<(), TableWriteError>
+where
+    W: std::io::Write,
+    U: std::fmt::Debug + std::cmp::Ord,
+    V: std::fmt::Debug,
+    F: Fn(&U) -> String,
+    G: Fn(&V) -> String,
+{
+    let time_column = match table.get_meta_key(&String::from("time_column")) {
+        Some(x) => x.to_owned(),
+        None => String::from("index"),
+    };
+    let mut header = vec![time_column];
+    header.extend(table.headers.iter().cloned());
+    wtr.write_record(&header)?;
+
+    for (k, row) in table.data.iter() {
+        let mut record = vec![index_format(k)];
+        record.extend(row.iter().map(&value_format));
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()?;
+    Ok(())
 }
@@ -0,0 +1,222 @@
+#![cfg(feature = "memory_db")]
+
+use std::collections::HashMap;
+use std::ops::RangeBounds;
+use timeseries::{
+    vec2, BtreeMapTrait, DbChanges, DbTableError, HeaderChange, MemoryConfig, MemoryError,
+    StoreError, Table, TableMetaTrait, TableStore,
+};
+
+macro_rules! s {
+    ($t:expr) => {
+        String::from($t)
+    };
+}
+
+fn new_table_data() -> Table<u8, i32> {
+    let headers = vec![s!("p10"), s!("data"), s!("twentyfive"), s!("squares")];
+
+    let indexes = vec![1, 2, 3, 4, 5, 6];
+    let d = vec2![
+        [10, 10, 25, 1],
+        [20, 23, 25, 4],
+        [30, 36, 25, 9],
+        [40, 49, 25, 16],
+        [50, 51, 25, 25],
+        [60, 68, 25, 36],
+    ];
+
+    Table::new(headers, indexes, d).unwrap()
+}
+
+#[test]
+fn memory_table_int() {
+    let config = MemoryConfig::new();
+    let mut t = new_table_data();
+    let mut meta_data = HashMap::new();
+    meta_data.insert(s!("source"), s!("test"));
+    t.set_meta_data(meta_data);
+
+    t.save_memory(&config).unwrap();
+    // 6 rows, the header and the meta data
+    assert_eq!(8, config.len());
+
+    let t1: Table<u8, i32> = Table::from_memory(&config).unwrap();
+    assert_eq!(t, t1);
+}
+
+#[test]
+fn memory_save_twice() {
+    let config = MemoryConfig::new();
+    let t = new_table_data();
+    t.save_memory(&config).unwrap();
+
+    match t.save_memory(&config) {
+        Err(MemoryError::DbTableError(DbTableError::DbExists)) => (),
+        x => panic!("expected DbExists, got {:?}", x),
+    }
+}
+
+#[test]
+fn memory_save_concurrent() {
+    let config = MemoryConfig::new();
+    let saved: Vec<bool> = (0..8)
+        .map(|_| {
+            let config = config.clone();
+            std::thread::spawn(move || new_table_data().save_memory(&config).is_ok())
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|x| x.join().unwrap())
+        .collect();
+
+    assert_eq!(1, saved.iter().filter(|x| **x).count());
+    assert_eq!(new_table_data(), Table::from_memory(&config).unwrap());
+}
+
+#[test]
+fn memory_load_empty() {
+    let config = MemoryConfig::new();
+    match Table::<u8, i32>::from_memory(&config) {
+        Err(MemoryError::DbTableError(DbTableError::DbHeaderDoesNotExist)) => (),
+        x => panic!("expected DbHeaderDoesNotExist, got {:?}", x),
+    }
+
+    match new_table_data().update_memory(&config) {
+        Err(MemoryError::DbTableError(DbTableError::DbDoesNotExist)) => (),
+        x => panic!("expected DbDoesNotExist, got {:?}", x),
+    }
+}
+
+#[test]
+fn memory_update() {
+    let config = MemoryConfig::new();
+    let mut t = new_table_data();
+    t.save_memory(&config).unwrap();
+    assert!(t.update_memory(&config).unwrap().is_empty());
+
+    t.insert(7, vec![70, 71, 25, 49]);
+    t.insert(2, vec![0, 0, 0, 0]);
+    t.remove(&1);
+
+    let changes = t.update_memory(&config).unwrap();
+    let expected = DbChanges {
+        inserted: vec![7],
+        updated: vec![2],
+        deleted: vec![1],
+    };
+    assert_eq!(expected, changes);
+
+    let t1: Table<u8, i32> = Table::from_memory(&config).unwrap();
+    assert_eq!(t, t1);
+}
+
+#[test]
+fn memory_header_change() {
+    let config = MemoryConfig::new().with_namespace("numbers");
+    let t = new_table_data();
+    t.save_memory(&config).unwrap();
+
+    let t1: Table<u8, i32> = Table::new(
+        vec![s!("squares"), s!("cubes")],
+        vec![1, 2],
+        vec2![[1, 1], [4, 8]],
+    )
+    .unwrap();
+    match t1.update_memory(&config) {
+        Err(MemoryError::DbTableError(DbTableError::DbHeadersChanged(x))) => {
+            assert_eq!(t.headers, x)
+        }
+        x => panic!("expected DbHeadersChanged, got {:?}", x),
+    }
+    assert_eq!(t, Table::from_memory(&config).unwrap());
+
+    let changes = t1
+        .update_memory_with(&config, &HeaderChange::Rewrite(0))
+        .unwrap();
+    assert_eq!(vec![1, 2], changes.updated);
+    assert_eq!(t1, Table::from_memory(&config).unwrap());
+
+    let changes = t
+        .update_memory_with(&config, &HeaderChange::Version)
+        .unwrap();
+    assert_eq!(vec![1, 2, 3, 4, 5, 6], changes.inserted);
+    assert_eq!(t, Table::from_memory(&config).unwrap());
+    assert_eq!(t1, Table::from_memory(&config.version(1)).unwrap());
+
+    t1.update_memory_with(&config, &HeaderChange::Version)
+        .unwrap();
+    assert_eq!(t, Table::from_memory(&config.version(2)).unwrap());
+    assert_eq!(t1, Table::from_memory(&config).unwrap());
+
+    // without a namespace there is no place to keep the old version
+    let config = MemoryConfig::new();
+    t.save_memory(&config).unwrap();
+    match t1.update_memory_with(&config, &HeaderChange::Version) {
+        Err(MemoryError::DbTableError(DbTableError::DbNamespaceRequired)) => (),
+        x => panic!("expected DbNamespaceRequired, got {:?}", x),
+    }
+}
+
+#[test]
+fn memory_namespaces() {
+    let config = MemoryConfig::new();
+    let first = config.clone().with_namespace("first");
+    let second = config.clone().with_namespace("second");
+
+    let t = new_table_data();
+    let t2 = t.filter(|row| row["p10"] > 30);
+    t.save_memory(&first).unwrap();
+    t2.save_memory(&second).unwrap();
+
+    assert_eq!(t, Table::from_memory(&first).unwrap());
+    assert_eq!(t2, Table::from_memory(&second).unwrap());
+
+    t.delete_memory(&first).unwrap();
+    assert!(Table::<u8, i32>::from_memory(&first).is_err());
+    assert_eq!(t2, Table::from_memory(&second).unwrap());
+}
+
+#[test]
+fn memory_nested_namespaces() {
+    let config = MemoryConfig::new();
+    let sensors = config.clone().with_namespace("sensors");
+    let room = config.clone().with_namespace("sensors:room1");
+
+    let t = new_table_data();
+    let t2 = Table::new(vec![s!("name")], vec![1000u32], vec2![[s!("room1")]]).unwrap();
+    t.save_memory(&sensors).unwrap();
+    t2.save_memory(&room).unwrap();
+
+    assert_eq!(t, Table::from_memory(&sensors).unwrap());
+    assert_eq!(t2, Table::from_memory(&room).unwrap());
+
+    t.delete_memory(&sensors).unwrap();
+    assert!(Table::<u8, i32>::from_memory(&sensors).is_err());
+    assert_eq!(t2, Table::from_memory(&room).unwrap());
+}
+
+#[test]
+fn memory_table_store() {
+    let store: Box<dyn TableStore<u8, i32>> = Box::new(MemoryConfig::new());
+    let t = new_table_data();
+
+    store.init().unwrap();
+    assert!(!store.exists().unwrap());
+    store.save(&t).unwrap();
+    assert!(store.exists().unwrap());
+
+    match store.save(&t) {
+        Err(StoreError::DbTableError(DbTableError::DbExists)) => (),
+        x => panic!("expected DbExists, got {:?}", x),
+    }
+
+    let range = 2..4;
+    let t1 = store
+        .load_range((range.start_bound(), range.end_bound()))
+        .unwrap();
+    assert_eq!(vec![&2, &3], t1.keys().collect::<Vec<_>>());
+
+    store.delete().unwrap();
+    assert!(!store.exists().unwrap());
+}
@@ -0,0 +1,433 @@
+#![cfg(feature = "sqlite_db")]
+
+extern crate tempfile;
+
+use std::ops::RangeBounds;
+use timeseries::{
+    vec2, BtreeMapTrait, DbChanges, DbTableError, HeaderChange, SqliteConfig, SqliteError,
+    StoreError, Table, TableStore,
+};
+
+macro_rules! s {
+    ($t:expr) => {
+        String::from($t)
+    };
+}
+
+fn new_table_data() -> Table<u8, i32> {
+    let headers = vec![s!("p10"), s!("data"), s!("twentyfive"), s!("squares")];
+
+    let indexes = vec![1, 2, 3, 4, 5, 6];
+    let d = vec2![
+        [10, 10, 25, 1],
+        [20, 23, 25, 4],
+        [30, 36, 25, 9],
+        [40, 49, 25, 16],
+        [50, 51, 25, 25],
+        [60, 68, 25, 36],
+    ];
+
+    Table::new(headers, indexes, d).unwrap()
+}
+
+fn create_tmp_config<T>(test: T)
+where
+    T: FnOnce(&SqliteConfig) + std::panic::UnwindSafe,
+{
+    let tmp_db = tempfile::NamedTempFile::new().expect("error creating test file");
+    let tmp_path = tmp_db.into_temp_path();
+    let config = SqliteConfig::new(tmp_path.to_path_buf());
+
+    let result = std::panic::catch_unwind(|| test(&config));
+
+    tmp_path.close().unwrap();
+
+    assert!(result.is_ok())
+}
+
+#[test]
+fn sqlite_table_int() {
+    create_tmp_config(|config| {
+        let t = new_table_data();
+        t.init_sqlite(config).unwrap();
+        t.save_sqlite(config).unwrap();
+        let t1: Table<u8, i32> = Table::from_sqlite(config).unwrap();
+        assert_eq!(t, t1);
+    })
+}
+
+#[test]
+fn sqlite_update_unchanged() {
+    create_tmp_config(|config| {
+        let t = new_table_data();
+        t.init_sqlite(config).unwrap();
+        t.save_sqlite(config).unwrap();
+
+        let changes = t.update_sqlite(config).unwrap();
+        assert!(changes.is_empty());
+    })
+}
+
+#[test]
+fn sqlite_update_changes() {
+    create_tmp_config(|config| {
+        let mut t = new_table_data();
+        t.init_sqlite(config).unwrap();
+        t.save_sqlite(config).unwrap();
+
+        t.insert(7, vec![70, 71, 25, 49]);
+        t.insert(2, vec![0, 0, 0, 0]);
+        t.remove(&1);
+        t.remove(&5);
+
+        let changes = t.update_sqlite(config).unwrap();
+        let expected = DbChanges {
+            inserted: vec![7],
+            updated: vec![2],
+            deleted: vec![1, 5],
+        };
+        assert_eq!(expected, changes);
+        assert_eq!(4, changes.len());
+
+        let t1: Table<u8, i32> = Table::from_sqlite(config).unwrap();
+        assert_eq!(t, t1);
+    })
+}
+
+#[test]
+fn sqlite_update_empty_database() {
+    create_tmp_config(|config| {
+        let t = new_table_data();
+        t.init_sqlite(config).unwrap();
+
+        match t.update_sqlite(config) {
+            Err(SqliteError::DbTableError(DbTableError::DbDoesNotExist)) => (),
+            x => panic!("expected DbDoesNotExist, got {:?}", x),
+        }
+    })
+}
+
+fn new_table_signed() -> Table<i64, i32> {
+    let headers = vec![s!("value")];
+    let indexes = vec![-300, -256, -1, 0, 1, 255, 256, 70000];
+    let d = indexes.iter().map(|x| vec![*x as i32]).collect();
+
+    Table::new(headers, indexes, d).unwrap()
+}
+
+#[test]
+fn sqlite_load_range() {
+    create_tmp_config(|config| {
+        let t = new_table_signed();
+        t.init_sqlite(config).unwrap();
+        t.save_sqlite(config).unwrap();
+
+        let t1: Table<i64, i32> = Table::load_range_sqlite(config, -256..256).unwrap();
+        assert_eq!(
+            vec![&-256, &-1, &0, &1, &255],
+            t1.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(t.headers, t1.headers);
+
+        let t1: Table<i64, i32> = Table::load_range_sqlite(config, 256..).unwrap();
+        assert_eq!(vec![&256, &70000], t1.keys().collect::<Vec<_>>());
+
+        let t1: Table<i64, i32> = Table::load_range_sqlite(config, ..=-256).unwrap();
+        assert_eq!(vec![&-300, &-256], t1.keys().collect::<Vec<_>>());
+
+        let t1: Table<i64, i32> = Table::load_range_sqlite(config, ..).unwrap();
+        assert_eq!(t, t1);
+    })
+}
+
+#[test]
+fn sqlite_load_range_after_update() {
+    create_tmp_config(|config| {
+        let mut t = new_table_signed();
+        t.init_sqlite(config).unwrap();
+        t.save_sqlite(config).unwrap();
+
+        t.insert(-2, vec![-2]);
+        t.update_sqlite(config).unwrap();
+
+        let t1: Table<i64, i32> = Table::load_range_sqlite(config, -10..10).unwrap();
+        assert_eq!(vec![&-2, &-1, &0, &1], t1.keys().collect::<Vec<_>>());
+    })
+}
+
+#[test]
+fn sqlite_table_without_sort_key() {
+    create_tmp_config(|config| {
+        let mut t = new_table_signed();
+        t.init_sqlite(config).unwrap();
+        t.save_sqlite(config).unwrap();
+
+        // the layout of tables saved before range queries
+        let connection = config.connect().unwrap();
+        connection
+            .execute_batch("DROP INDEX row_sort_key; ALTER TABLE row DROP COLUMN sort_key;")
+            .unwrap();
+
+        let t1: Table<i64, i32> = Table::load_range_sqlite(config, -256..256).unwrap();
+        assert_eq!(
+            vec![&-256, &-1, &0, &1, &255],
+            t1.keys().collect::<Vec<_>>()
+        );
+
+        t.insert(-2, vec![-2]);
+        t.update_sqlite(config).unwrap();
+
+        let sort_keys: i64 = connection
+            .query_row(
+                "SELECT count(*) FROM row WHERE sort_key IS NOT NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(t.len() as i64, sort_keys);
+
+        let t1: Table<i64, i32> = Table::load_range_sqlite(config, -10..10).unwrap();
+        assert_eq!(vec![&-2, &-1, &0, &1], t1.keys().collect::<Vec<_>>());
+    })
+}
+
+#[test]
+fn sqlite_header_change() {
+    create_tmp_config(|config| {
+        let t = new_table_data();
+        t.init_sqlite(config).unwrap();
+        t.save_sqlite(config).unwrap();
+
+        let t1: Table<u8, i32> = Table::new(
+            vec![s!("squares"), s!("cubes")],
+            vec![1, 2],
+            vec2![[1, 1], [4, 8]],
+        )
+        .unwrap();
+        match t1.update_sqlite(config) {
+            Err(SqliteError::DbTableError(DbTableError::DbHeadersChanged(x))) => {
+                assert_eq!(t.headers, x)
+            }
+            x => panic!("expected DbHeadersChanged, got {:?}", x),
+        }
+        assert_eq!(t, Table::from_sqlite(config).unwrap());
+
+        t1.update_sqlite_with(config, &HeaderChange::Rewrite(0))
+            .unwrap();
+        assert_eq!(t1, Table::from_sqlite(config).unwrap());
+
+        let changes = t
+            .update_sqlite_with(config, &HeaderChange::Version)
+            .unwrap();
+        assert_eq!(vec![1, 2, 3, 4, 5, 6], changes.inserted);
+        assert_eq!(t, Table::from_sqlite(config).unwrap());
+        assert_eq!(t1, Table::from_sqlite(&config.version(1)).unwrap());
+
+        // the new version has a working range index
+        let t2: Table<u8, i32> = Table::load_range_sqlite(config, 5..).unwrap();
+        assert_eq!(vec![&5, &6], t2.keys().collect::<Vec<_>>());
+    })
+}
+
+#[test]
+fn sqlite_misaligned_rows() {
+    create_tmp_config(|config| {
+        let t = new_table_data();
+        t.init_sqlite(config).unwrap();
+        t.save_sqlite(config).unwrap();
+
+        // a row written by hand with one value too many
+        let connection = config.connect().unwrap();
+        connection
+            .execute(
+                "UPDATE row SET item = ?1 WHERE key = ?2",
+                [
+                    bincode::serialize(&vec![1, 2, 3, 4, 5]).unwrap(),
+                    bincode::serialize(&3u8).unwrap(),
+                ],
+            )
+            .unwrap();
+
+        match Table::<u8, i32>::from_sqlite(config) {
+            Err(SqliteError::DbTableError(DbTableError::DbRowLength { expected, found })) => {
+                assert_eq!((4, 5), (expected, found))
+            }
+            x => panic!("expected DbRowLength, got {:?}", x),
+        }
+    })
+}
+
+#[test]
+fn sqlite_named_tables() {
+    create_tmp_config(|config| {
+        let prices = config.clone().with_table("prices");
+        let volumes = config.clone().with_table("volume \"daily\"");
+
+        let t1 = new_table_data();
+        let t2 = new_table_signed();
+        t1.init_sqlite(&prices).unwrap();
+        t2.init_sqlite(&volumes).unwrap();
+        t1.save_sqlite(&prices).unwrap();
+        t2.save_sqlite(&volumes).unwrap();
+
+        let t3: Table<u8, i32> = Table::from_sqlite(&prices).unwrap();
+        let t4: Table<i64, i32> = Table::from_sqlite(&volumes).unwrap();
+        assert_eq!(t1, t3);
+        assert_eq!(t2, t4);
+
+        t1.uninit_sqlite(&prices).unwrap();
+        let t4: Table<i64, i32> = Table::load_range_sqlite(&volumes, 0..).unwrap();
+        assert_eq!(5, t4.keys().count());
+    })
+}
+
+#[test]
+fn sqlite_table_store() {
+    create_tmp_config(|config| {
+        let store: Box<dyn TableStore<u8, i32>> = Box::new(config.clone().with_table("store"));
+        assert!(!store.exists().unwrap());
+
+        let mut t = new_table_data();
+        store.init().unwrap();
+        assert!(!store.exists().unwrap());
+        store.save(&t).unwrap();
+        assert!(store.exists().unwrap());
+
+        t.insert(7, vec![70, 71, 25, 49]);
+        store.update(&t).unwrap();
+        assert_eq!(t, store.load().unwrap());
+
+        let range = 3..=5;
+        let t1 = store
+            .load_range((range.start_bound(), range.end_bound()))
+            .unwrap();
+        assert_eq!(vec![&3, &4, &5], t1.keys().collect::<Vec<_>>());
+
+        store.delete().unwrap();
+        assert!(!store.exists().unwrap());
+        match store.load() {
+            Err(StoreError::Sqlite(_)) => (),
+            x => panic!("expected a sqlite error, got {:?}", x),
+        }
+    })
+}
+
+#[test]
+fn sqlite_columns() {
+    create_tmp_config(|config| {
+        let config = config.clone().with_table("prices");
+        let t = new_table_data();
+        t.init_sqlite_columns(&config).unwrap();
+        t.save_sqlite_columns(&config).unwrap();
+
+        let t1: Table<u8, i32> = Table::from_sqlite_columns(&config).unwrap();
+        assert_eq!(t, t1);
+
+        // the rows are plain SQL
+        let connection = config.connect().unwrap();
+        let total: i64 = connection
+            .query_row(
+                "SELECT sum(squares) FROM prices WHERE p10 > 30",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(16 + 25 + 36, total);
+
+        match Table::<u8, i32>::from_sqlite_columns(&config.with_index_column("day")) {
+            Err(SqliteError::ColumnDoesNotExist(x)) => assert_eq!("day", x),
+            x => panic!("expected ColumnDoesNotExist, got {:?}", x),
+        }
+    })
+}
+
+#[test]
+fn sqlite_query() {
+    create_tmp_config(|config| {
+        let connection = config.connect().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE prices (open REAL, close REAL, day INTEGER PRIMARY KEY);
+                INSERT INTO prices VALUES (1.5, 2.0, 3), (2.0, 2.5, 1), (2.5, 1.0, 2);",
+            )
+            .unwrap();
+
+        let t: Table<i64, f64> = Table::from_sqlite_query(
+            config,
+            "SELECT day, close, open FROM prices WHERE close >= 2 ORDER BY day DESC",
+        )
+        .unwrap();
+        assert_eq!(vec![s!("close"), s!("open")], t.headers);
+        assert_eq!(vec![&1, &3], t.keys().collect::<Vec<_>>());
+        assert_eq!(Some(&vec![2.0, 1.5]), t.get(&3));
+
+        let t: Table<i64, f64> = Table::from_sqlite_columns(
+            &config.clone().with_table("prices").with_index_column("day"),
+        )
+        .unwrap();
+        assert_eq!(vec![s!("open"), s!("close")], t.headers);
+        assert_eq!(3, t.keys().count());
+    })
+}
+
+#[test]
+fn sqlite_query_duplicate_index() {
+    create_tmp_config(|config| {
+        let connection = config.connect().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE trades (day INTEGER, price REAL);
+                INSERT INTO trades VALUES (1, 1.5), (2, 2.0), (1, 2.5), (3, 1.0), (1, 3.0);",
+            )
+            .unwrap();
+
+        let config = config.clone().with_table("trades").with_index_column("day");
+        match Table::<i64, f64>::from_sqlite_columns(&config) {
+            Err(SqliteError::Table(x)) => assert_eq!(vec![s!("1")], x.keys()),
+            x => panic!("expected duplicate index values, got {:?}", x),
+        }
+        match Table::<i64, f64>::from_sqlite_query(&config, "SELECT day, price FROM trades") {
+            Err(SqliteError::Table(x)) => assert_eq!(vec![s!("1")], x.keys()),
+            x => panic!("expected duplicate index values, got {:?}", x),
+        }
+
+        let t: Table<i64, f64> = Table::from_sqlite_query(
+            &config,
+            "SELECT day, max(price) AS price FROM trades GROUP BY day",
+        )
+        .unwrap();
+        assert_eq!(Some(&vec![3.0]), t.get(&1));
+        assert_eq!(3, t.keys().count());
+    })
+}
+
+#[test]
+fn sqlite_reuse_connection() {
+    // an in-memory database only lives as long as its connection
+    let config = SqliteConfig::new(":memory:").reuse_connection();
+    let mut t = new_table_data();
+    t.init_sqlite(&config).unwrap();
+    t.save_sqlite(&config).unwrap();
+
+    t.insert(7, vec![70, 71, 25, 49]);
+    t.update_sqlite(&config).unwrap();
+    assert_eq!(t, Table::from_sqlite(&config).unwrap());
+
+    // clones share the connection
+    let prices = config.clone().with_table("prices");
+    t.init_sqlite_columns(&prices).unwrap();
+    t.save_sqlite_columns(&prices).unwrap();
+    assert_eq!(t, Table::from_sqlite_columns(&prices).unwrap());
+    assert!(TableStore::<u8, i32>::exists(&config).unwrap());
+
+    let connection = timeseries::rusqlite::Connection::open_in_memory().unwrap();
+    connection
+        .execute_batch("CREATE TABLE prices (close REAL, day INTEGER PRIMARY KEY);")
+        .unwrap();
+    let config = SqliteConfig::new(":memory:")
+        .with_table("prices")
+        .with_index_column("day")
+        .with_connection(connection);
+    let t: Table<i64, f64> = Table::from_sqlite_columns(&config).unwrap();
+    assert_eq!(vec![s!("close")], t.headers);
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "_db_base")]
+
+//! The `TableStore` contract, checked against every backend.
+
+extern crate tempfile;
+
+use std::ops::RangeBounds;
+use timeseries::{vec2, BtreeMapTrait, DbChanges, DbTableError, StoreError, Table, TableStore};
+
+macro_rules! s {
+    ($t:expr) => {
+        String::from($t)
+    };
+}
+
+fn new_table_data() -> Table<u8, i32> {
+    let headers = vec![s!("p10"), s!("data"), s!("twentyfive"), s!("squares")];
+
+    let indexes = vec![1, 2, 3, 4, 5, 6];
+    let d = vec2![
+        [10, 10, 25, 1],
+        [20, 23, 25, 4],
+        [30, 36, 25, 9],
+        [40, 49, 25, 16],
+        [50, 51, 25, 25],
+        [60, 68, 25, 36],
+    ];
+
+    Table::new(headers, indexes, d).unwrap()
+}
+
+fn check_store(store: &dyn TableStore<u8, i32>) {
+    let mut t = new_table_data();
+
+    store.init().unwrap();
+    assert!(!store.exists().unwrap());
+    match store.update(&t) {
+        Err(StoreError::DbTableError(DbTableError::DbDoesNotExist)) => (),
+        x => panic!("expected DbDoesNotExist, got {:?}", x),
+    }
+
+    store.save(&t).unwrap();
+    assert!(store.exists().unwrap());
+    match store.save(&t) {
+        Err(StoreError::DbTableError(DbTableError::DbExists)) => (),
+        x => panic!("expected DbExists, got {:?}", x),
+    }
+    assert!(store.update(&t).unwrap().is_empty());
+
+    t.insert(7, vec![70, 71, 25, 49]);
+    t.insert(2, vec![0, 0, 0, 0]);
+    t.remove(&1);
+    t.remove(&4);
+    let expected = DbChanges {
+        inserted: vec![7],
+        updated: vec![2],
+        deleted: vec![1, 4],
+    };
+    assert_eq!(expected, store.update(&t).unwrap());
+    assert_eq!(t, store.load().unwrap());
+
+    let range = 2..5;
+    let t1 = store
+        .load_range((range.start_bound(), range.end_bound()))
+        .unwrap();
+    assert_eq!(vec![&2, &3], t1.keys().collect::<Vec<_>>());
+
+    store.delete().unwrap();
+    assert!(!store.exists().unwrap());
+}
+
+#[cfg(feature = "memory_db")]
+#[test]
+fn memory_store() {
+    check_store(&timeseries::MemoryConfig::new().with_namespace("store"));
+}
+
+#[cfg(feature = "sqlite_db")]
+#[test]
+fn sqlite_store() {
+    let tmp_path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    check_store(&timeseries::SqliteConfig::new(tmp_path.to_path_buf()));
+}
+
+#[cfg(feature = "unqlite_db")]
+#[test]
+fn unqlite_store() {
+    let tmp_path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    check_store(&timeseries::UnqliteConfig::new(tmp_path.to_str().unwrap()));
+}
+
+#[cfg(feature = "postgresql_db")]
+#[test]
+#[ignore = "needs a postgres server on localhost"]
+fn postgresql_store() {
+    let config = timeseries::PostgresConfig::new(
+        "host=localhost user=postgres dbname=postgres",
+        postgres::NoTls,
+    )
+    .unwrap()
+    .with_table("store_test");
+    TableStore::<u8, i32>::delete(&config).unwrap();
+    check_store(&config);
+}
+
+#[cfg(feature = "redis_db")]
+#[test]
+#[ignore = "needs a redis server on localhost"]
+fn redis_store() {
+    let config = timeseries::RedisConfig::new("redis://127.0.0.1/")
+        .unwrap()
+        .with_namespace("store_test");
+    TableStore::<u8, i32>::delete(&config).unwrap();
+    check_store(&config);
+}
@@ -3,7 +3,10 @@
 extern crate tempfile;
 use timeseries;
 
-use timeseries::{vec2, BtreeMapTrait, Table, TableMetaTrait};
+use timeseries::{
+    vec2, BtreeMapTrait, DbTableError, HeaderChange, Table, TableMetaTrait, UnqliteConfig,
+    UnqliteError,
+};
 
 macro_rules! s {
     ($t:expr) => {
@@ -109,8 +112,9 @@ fn unqlite_table_update_int() {
         let t1: Table<u8, i32> = Table::from_unqlite(tmp_path_str).unwrap();
 
         t.insert(7, vec![70, 72, 25, 49]);
-        let updated_keys = t.update_unqlite(tmp_path_str).unwrap();
-        assert_eq!(vec![7], updated_keys);
+        let changes = t.update_unqlite(tmp_path_str).unwrap();
+        assert_eq!(vec![7], changes.inserted);
+        assert!(changes.updated.is_empty());
 
         let t2: Table<u8, i32> = Table::from_unqlite(tmp_path_str).unwrap();
 
@@ -118,14 +122,110 @@ fn unqlite_table_update_int() {
 
         t.insert(6, vec![60, 54, 25, 36]);
         t.insert(5, vec![50, 59, 25, 25]);
-        let updated_keys = t.update_unqlite(tmp_path_str).unwrap();
-        assert_eq!(vec![5, 6], updated_keys);
+        let changes = t.update_unqlite(tmp_path_str).unwrap();
+        assert_eq!(vec![5, 6], changes.updated);
+        assert!(changes.inserted.is_empty());
 
         let t3: Table<u8, i32> = Table::from_unqlite(tmp_path_str).unwrap();
-
-        t3.delete_unqlite(tmp_path_str).unwrap();
         assert_eq!(t, t3);
         assert_ne!(t, t2);
         assert_ne!(t, t1);
+
+        // update keeps stored rows that are missing from the table, sync deletes them
+        t.remove(&1);
+        assert!(t.update_unqlite(tmp_path_str).unwrap().is_empty());
+        assert_eq!(t3, Table::from_unqlite(tmp_path_str).unwrap());
+        let changes = t.sync_unqlite(tmp_path_str).unwrap();
+        assert_eq!(vec![1], changes.deleted);
+        assert_eq!(t, Table::from_unqlite(tmp_path_str).unwrap());
+
+        t.delete_unqlite(tmp_path_str).unwrap();
     });
 }
+
+#[test]
+fn unqlite_namespaces() {
+    create_tmp_file(|tmp_path_str| {
+        let numbers = UnqliteConfig::new(tmp_path_str).with_namespace("numbers");
+        let strings = UnqliteConfig::new(tmp_path_str).with_namespace("strings");
+
+        let t1 = new_table_data();
+        let t2 = new_table_large();
+        t1.save_unqlite(numbers.clone()).unwrap();
+        t2.save_unqlite(strings.clone()).unwrap();
+
+        let t3: Table<u8, i32> = Table::from_unqlite(numbers.clone()).unwrap();
+        let t4: Table<u8, String> = Table::from_unqlite(strings.clone()).unwrap();
+        assert_eq!(t1, t3);
+        assert_eq!(t2, t4);
+
+        t1.delete_unqlite(numbers.clone()).unwrap();
+        assert!(Table::<u8, i32>::from_unqlite(numbers).is_err());
+        let t4: Table<u8, String> = Table::from_unqlite(strings).unwrap();
+        assert_eq!(t2, t4);
+    })
+}
+
+#[test]
+fn unqlite_nested_namespaces() {
+    create_tmp_file(|tmp_path_str| {
+        let sensors = UnqliteConfig::new(tmp_path_str).with_namespace("sensors");
+        let room = UnqliteConfig::new(tmp_path_str).with_namespace("sensors:room1");
+
+        let t1 = new_table_data();
+        let t2 = new_table_large();
+        t1.save_unqlite(sensors.clone()).unwrap();
+        t2.save_unqlite(room.clone()).unwrap();
+
+        let t3: Table<u8, i32> = Table::from_unqlite(sensors.clone()).unwrap();
+        assert_eq!(t1, t3);
+
+        t1.delete_unqlite(sensors.clone()).unwrap();
+        assert!(Table::<u8, i32>::from_unqlite(sensors).is_err());
+        let t4: Table<u8, String> = Table::from_unqlite(room).unwrap();
+        assert_eq!(t2, t4);
+    })
+}
+
+#[test]
+fn unqlite_header_change() {
+    create_tmp_file(|tmp_path_str| {
+        let config = UnqliteConfig::new(tmp_path_str).with_namespace("numbers");
+        let t = new_table_data();
+        t.save_unqlite(config.clone()).unwrap();
+
+        let t1: Table<u8, i32> = Table::new(
+            vec![s!("squares"), s!("cubes")],
+            vec![1, 2],
+            vec2![[1, 1], [4, 8]],
+        )
+        .unwrap();
+        match t1.update_unqlite(config.clone()) {
+            Err(UnqliteError::DbTableError(DbTableError::DbHeadersChanged(x))) => {
+                assert_eq!(t.headers, x)
+            }
+            x => panic!("expected DbHeadersChanged, got {:?}", x),
+        }
+
+        // the stored rows that are not in t1 keep their squares and get the fill value
+        let changes = t1
+            .update_unqlite_with(config.clone(), &HeaderChange::Rewrite(0))
+            .unwrap();
+        assert_eq!(vec![1, 2, 3, 4, 5, 6], changes.updated);
+        let t2: Table<u8, i32> = Table::from_unqlite(config.clone()).unwrap();
+        let expected = Table::new(
+            t1.headers.clone(),
+            vec![1, 2, 3, 4, 5, 6],
+            vec2![[1, 1], [4, 8], [9, 0], [16, 0], [25, 0], [36, 0]],
+        )
+        .unwrap();
+        assert_eq!(expected, t2);
+
+        let mut t3 = t.clone();
+        t3.insert(7, vec![70, 72, 25, 49]);
+        t3.update_unqlite_with(config.clone(), &HeaderChange::Version)
+            .unwrap();
+        assert_eq!(t3, Table::from_unqlite(config.clone()).unwrap());
+        assert_eq!(t2, Table::from_unqlite(config.version(1)).unwrap());
+    })
+}